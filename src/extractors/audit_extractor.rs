@@ -0,0 +1,141 @@
+use crate::utils::token::generate_token;
+use crate::AppState;
+use actix_web::{dev::Payload, web, Error, FromRequest, HttpMessage, HttpRequest};
+use mongodb::bson::oid::ObjectId;
+use std::{
+    future::{ready, Ready},
+    net::IpAddr,
+};
+
+const REQUEST_ID_HEADER: &str = "x-request-id";
+const FORWARDED_FOR_HEADER: &str = "x-forwarded-for";
+
+/// The user signed in by the `AuthenticatedUser` extractor, kept in the request extensions
+/// so the audit context reuses it instead of authenticating the request again.
+#[derive(Clone, Copy)]
+pub struct AuthenticatedActor(pub ObjectId);
+
+/// Who is making a request and from where, attached to every audit log entry.
+/// Extracting it never fails: anonymous requests simply have no actor.
+pub struct AuditContext {
+    actor_id: Option<ObjectId>,   // Set explicitly, e.g. the user who just logged in
+    request: Option<HttpRequest>, // Holds the `AuthenticatedActor` once the handler arguments are extracted
+    pub ip: Option<String>,
+    pub request_id: String, // From the `X-Request-Id` header, generated when missing
+}
+
+impl AuditContext {
+    /// Context for changes made by the API itself, e.g. background jobs.
+    pub fn system() -> AuditContext {
+        AuditContext { actor_id: None, request: None, ip: None, request_id: generate_token(16) }
+    }
+
+    /// The same context acting as another user, e.g. the user who just logged in.
+    pub fn with_actor(&self, actor_id: ObjectId) -> AuditContext {
+        AuditContext {
+            actor_id: Some(actor_id),
+            request: None,
+            ip: self.ip.clone(),
+            request_id: self.request_id.clone(),
+        }
+    }
+
+    /// The user making the change: the explicit actor, or the one the handler authenticated.
+    pub fn actor_id(&self) -> Option<ObjectId> {
+        self.actor_id.or_else(|| {
+            let request = self.request.as_ref()?;
+            let actor = request.extensions().get::<AuthenticatedActor>().copied();
+            actor.map(|actor| actor.0)
+        })
+    }
+}
+
+// Helper function to find the address of the client. `X-Forwarded-For` is only read when the
+// connection comes from a trusted proxy, and then from the right, skipping the trusted proxies,
+// since anything on its left was written by the client.
+fn client_ip(peer: Option<IpAddr>, forwarded_for: Option<&str>, trusted_proxies: &[IpAddr]) -> Option<String> {
+    let peer = peer?;
+    if !trusted_proxies.contains(&peer) {
+        return Some(peer.to_string());
+    }
+    let forwarded: Vec<IpAddr> = forwarded_for
+        .unwrap_or_default()
+        .split(',')
+        .map_while(|hop| hop.trim().parse().ok())
+        .collect();
+    let client = forwarded.into_iter().rev().find(|hop| !trusted_proxies.contains(hop));
+    Some(client.unwrap_or(peer).to_string())
+}
+
+impl FromRequest for AuditContext {
+    type Error = Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Self::Future {
+        let trusted_proxies = req
+            .app_data::<web::Data<AppState>>()
+            .map(|app_data| app_data.service_manager.audit_service.trusted_proxies().to_vec())
+            .unwrap_or_default();
+        let forwarded_for = req.headers().get(FORWARDED_FOR_HEADER).and_then(|value| value.to_str().ok());
+        let ip = client_ip(req.peer_addr().map(|addr| addr.ip()), forwarded_for, &trusted_proxies);
+        let request_id = req
+            .headers()
+            .get(REQUEST_ID_HEADER)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string)
+            .unwrap_or_else(|| generate_token(16));
+
+        ready(Ok(AuditContext { actor_id: None, request: Some(req.clone()), ip, request_id }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ip(text: &str) -> IpAddr {
+        text.parse().unwrap()
+    }
+
+    #[test]
+    fn forwarded_header_is_ignored_without_a_trusted_proxy() {
+        let found = client_ip(Some(ip("203.0.113.7")), Some("198.51.100.1"), &[]);
+        assert_eq!(found.as_deref(), Some("203.0.113.7"));
+    }
+
+    #[test]
+    fn trusted_proxy_forwards_the_client_address() {
+        let proxy = ip("10.0.0.2");
+        let found = client_ip(Some(proxy), Some("198.51.100.1, 203.0.113.9"), &[proxy]);
+        assert_eq!(found.as_deref(), Some("203.0.113.9"));
+    }
+
+    #[test]
+    fn chained_trusted_proxies_are_skipped() {
+        let proxies = [ip("10.0.0.2"), ip("10.0.0.3")];
+        let found = client_ip(Some(proxies[0]), Some("203.0.113.9, 10.0.0.3"), &proxies);
+        assert_eq!(found.as_deref(), Some("203.0.113.9"));
+    }
+
+    #[test]
+    fn trusted_proxy_without_header_is_the_client() {
+        let proxy = ip("10.0.0.2");
+        assert_eq!(client_ip(Some(proxy), None, &[proxy]).as_deref(), Some("10.0.0.2"));
+    }
+
+    #[actix_web::test]
+    async fn actor_is_the_user_authenticated_for_the_request() {
+        let req = actix_web::test::TestRequest::default()
+            .peer_addr("203.0.113.7:4000".parse().unwrap())
+            .insert_header((FORWARDED_FOR_HEADER, "198.51.100.1"))
+            .to_http_request();
+        let audit = AuditContext::extract(&req).await.unwrap();
+        assert_eq!(audit.actor_id(), None);
+        assert_eq!(audit.ip.as_deref(), Some("203.0.113.7"));
+
+        // Extracted after the audit context, as with `(AuditContext, AuthenticatedUser)` arguments
+        let user_id = ObjectId::new();
+        req.extensions_mut().insert(AuthenticatedActor(user_id));
+        assert_eq!(audit.actor_id(), Some(user_id));
+    }
+}
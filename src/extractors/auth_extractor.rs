@@ -0,0 +1,199 @@
+use crate::extractors::audit_extractor::AuthenticatedActor;
+use crate::models::{
+    api_key_model::ApiScope,
+    session_model::SessionKind,
+    user_model::{User, UserRole},
+};
+use crate::AppState;
+use actix_web::{
+    dev::Payload,
+    error::{ErrorInternalServerError, ErrorUnauthorized},
+    http::header,
+    web, Error, FromRequest, HttpMessage, HttpRequest, HttpResponse,
+};
+use mongodb::bson::oid::ObjectId;
+use std::{future::Future, pin::Pin};
+
+/// How the request was authenticated.
+pub enum Credential {
+    Session,                        // `Authorization: Bearer <session token>`
+    ApiKey { scopes: Vec<ApiScope> }, // `Authorization: ApiKey <key>`
+}
+
+/// The user behind the `Authorization` header of a request, authenticated either by a
+/// session token or by a personal API key. Add it as a handler argument to require authentication.
+pub struct AuthenticatedUser {
+    pub user_id: ObjectId,
+    pub user: User,
+    pub credential: Credential,
+}
+
+impl AuthenticatedUser {
+    /// Whether the request is allowed to act with the given scope.
+    /// Sessions carry every scope of the user, API keys only the ones they were created with.
+    pub fn has_scope(&self, scope: ApiScope) -> bool {
+        let is_admin = self.user.role == UserRole::Admin;
+        match &self.credential {
+            Credential::Session => scope != ApiScope::Admin || is_admin,
+            Credential::ApiKey { scopes, .. } => {
+                (scopes.contains(&ApiScope::Admin) && is_admin)
+                    || (scope != ApiScope::Admin && scopes.contains(&scope))
+            }
+        }
+    }
+
+    /// Whether the request may act on the data of its own user. Sessions always can, API keys only
+    /// with a scope beyond `catalog_read`.
+    pub fn can_act_as_user(&self) -> bool {
+        match &self.credential {
+            Credential::Session => true,
+            Credential::ApiKey { scopes } => scopes.iter().any(|scope| *scope != ApiScope::CatalogRead),
+        }
+    }
+
+    /// Whether the request may read the personal data of a user: their own, or any with the admin scope.
+    pub fn can_access_user(&self, user_id: &str) -> bool {
+        (self.user_id.to_hex() == user_id && self.can_act_as_user()) || self.has_scope(ApiScope::Admin)
+    }
+
+    pub fn is_api_key(&self) -> bool {
+        matches!(self.credential, Credential::ApiKey { .. })
+    }
+}
+
+/// Check that the request may access a user and parse their id, for the `/users/{id}/...` routes.
+/// Returns the error response when it may not.
+pub fn check_user_access(auth: &AuthenticatedUser, user_id: &str) -> Result<ObjectId, Box<HttpResponse>> {
+    if !auth.can_access_user(user_id) {
+        return Err(Box::new(HttpResponse::Forbidden().body("Not allowed to access this user")));
+    }
+    ObjectId::parse_str(user_id).map_err(|_| Box::new(HttpResponse::BadRequest().body("Invalid user id")))
+}
+
+enum AuthorizationHeader {
+    Bearer(String),
+    ApiKey(String),
+}
+
+// Helper function to read the credential from the request headers.
+fn authorization_header(req: &HttpRequest) -> Option<AuthorizationHeader> {
+    let value = req.headers().get(header::AUTHORIZATION)?.to_str().ok()?;
+
+    if let Some(token) = value.strip_prefix("Bearer ") {
+        Some(AuthorizationHeader::Bearer(token.trim().to_string()))
+    } else {
+        value
+            .strip_prefix("ApiKey ")
+            .map(|key| AuthorizationHeader::ApiKey(key.trim().to_string()))
+    }
+}
+
+impl FromRequest for AuthenticatedUser {
+    type Error = Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self, Self::Error>>>>;
+
+    fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Self::Future {
+        let req = req.clone();
+        let app_data = req.app_data::<web::Data<AppState>>().cloned();
+        let authorization = authorization_header(&req);
+
+        Box::pin(async move {
+            let app_data = app_data.ok_or_else(|| ErrorInternalServerError("Application state is not configured"))?;
+            let authorization = authorization.ok_or_else(|| ErrorUnauthorized("Missing credentials"))?;
+            let services = &app_data.service_manager;
+
+            let (user_id, credential) = match authorization {
+                AuthorizationHeader::Bearer(token) => {
+                    let session = services
+                        .session_service
+                        .find_valid(&token, SessionKind::Session)
+                        .await
+                        .map_err(|e| {
+                            eprintln!("Error while validating session: {:?}", e);
+                            ErrorInternalServerError("Failed to validate the session")
+                        })?
+                        .ok_or_else(|| ErrorUnauthorized("Invalid or expired session"))?;
+                    (session.user_id, Credential::Session)
+                }
+                AuthorizationHeader::ApiKey(key) => {
+                    let api_key = services
+                        .api_key_service
+                        .authenticate(&key)
+                        .await
+                        .map_err(|e| {
+                            eprintln!("Error while validating API key: {:?}", e);
+                            ErrorInternalServerError("Failed to validate the API key")
+                        })?
+                        .ok_or_else(|| ErrorUnauthorized("Invalid, revoked or expired API key"))?;
+                    (api_key.user_id, Credential::ApiKey { scopes: api_key.scopes })
+                }
+            };
+
+            let user = services
+                .user_service
+                .get_by_id(&user_id.to_hex())
+                .await
+                .map_err(|e| {
+                    eprintln!("Error while loading authenticated user: {:?}", e);
+                    ErrorInternalServerError("Failed to load the authenticated user")
+                })?
+                .ok_or_else(|| ErrorUnauthorized("The authenticated user no longer exists"))?;
+
+            // Picked up by the audit context of the same request
+            req.extensions_mut().insert(AuthenticatedActor(user_id));
+            Ok(AuthenticatedUser { user_id, user, credential })
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bson::DateTime;
+
+    // Helper function to authenticate a user with the given role and credential.
+    fn authenticated(role: UserRole, credential: Credential) -> AuthenticatedUser {
+        let user_id = ObjectId::new();
+        let user = User {
+            _id: Some(user_id),
+            name: "Ada".to_string(),
+            lastname: "Lovelace".to_string(),
+            major: "Mathematics".to_string(),
+            email: "ada@example.com".to_string(),
+            password: String::new(),
+            watched_ids: None,
+            email_verified: true,
+            role,
+            created_at: DateTime::now(),
+            updated_at: DateTime::now(),
+            deleted_at: None,
+        };
+        AuthenticatedUser { user_id, user, credential }
+    }
+
+    #[test]
+    fn catalog_keys_cannot_access_the_user() {
+        let auth = authenticated(UserRole::Learner, Credential::ApiKey { scopes: vec![ApiScope::CatalogRead] });
+        assert!(auth.has_scope(ApiScope::CatalogRead));
+        assert!(!auth.has_scope(ApiScope::WatchedWrite));
+        assert!(!auth.can_act_as_user());
+        assert!(!auth.can_access_user(&auth.user_id.to_hex()));
+    }
+
+    #[test]
+    fn keys_reach_other_users_only_with_the_admin_scope() {
+        let learner = authenticated(UserRole::Learner, Credential::ApiKey { scopes: vec![ApiScope::WatchedWrite] });
+        assert!(learner.can_access_user(&learner.user_id.to_hex()));
+        assert!(!learner.can_access_user(&ObjectId::new().to_hex()));
+
+        // The admin scope of a learner's key grants nothing
+        let learner = authenticated(UserRole::Learner, Credential::ApiKey { scopes: vec![ApiScope::Admin] });
+        assert!(!learner.can_access_user(&ObjectId::new().to_hex()));
+
+        let admin = authenticated(UserRole::Admin, Credential::ApiKey { scopes: vec![ApiScope::Admin] });
+        assert!(admin.can_access_user(&ObjectId::new().to_hex()));
+        let session = authenticated(UserRole::Learner, Credential::Session);
+        assert!(session.can_access_user(&session.user_id.to_hex()));
+        assert!(!session.can_access_user(&ObjectId::new().to_hex()));
+    }
+}
@@ -0,0 +1,2 @@
+pub mod audit_extractor;
+pub mod auth_extractor;
\ No newline at end of file
@@ -0,0 +1,279 @@
+use super::{FetchError, FetchedPage, LinkStatus, PageFetcher};
+use actix_web::rt::task::spawn_blocking;
+use async_trait::async_trait;
+use hyper::client::connect::dns::Name;
+use reqwest::{
+    dns::{Addrs, Resolve, Resolving},
+    header::CONTENT_TYPE,
+    redirect::Policy,
+    Client,
+};
+use std::{
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, ToSocketAddrs},
+    sync::Arc,
+    time::Duration,
+};
+
+const MAX_REDIRECTS: usize = 10;
+const MAX_BODY_BYTES: usize = 2 * 1024 * 1024; // Metadata lives in the head, the rest is dropped
+const USER_AGENT: &str = concat!("mylearning_api/", env!("CARGO_PKG_VERSION"));
+
+/// Fetcher downloading pages over HTTP(S) with reqwest. Unless `allow_private_hosts`, only
+/// public addresses are reached, so links given by users cannot probe the internal network.
+pub struct HttpFetcher {
+    client: Client,
+    allow_private_hosts: bool,
+}
+
+/// Whether an address can be reached from the internet, e.g. not loopback, private, link-local
+/// or reserved.
+pub fn is_public(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => is_public_v4(ip),
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(ip) => is_public_v4(ip),
+            None => is_public_v6(ip),
+        },
+    }
+}
+
+fn is_public_v4(ip: Ipv4Addr) -> bool {
+    let [a, b, c, _] = ip.octets();
+    !(ip.is_unspecified()
+        || ip.is_loopback()
+        || ip.is_private()
+        || ip.is_link_local()
+        || ip.is_broadcast()
+        || ip.is_documentation()
+        || ip.is_multicast()
+        || a == 0                                  // "This network"
+        || (a == 100 && (64..128).contains(&b))    // Shared address space, 100.64.0.0/10
+        || (a == 192 && b == 0 && c == 0)          // Protocol assignments, 192.0.0.0/24
+        || (a == 198 && (b == 18 || b == 19))      // Benchmarking, 198.18.0.0/15
+        || a >= 240)                               // Reserved
+}
+
+fn is_public_v6(ip: Ipv6Addr) -> bool {
+    let segments = ip.segments();
+    // NAT64 addresses, 64:ff9b::/96, reach the IPv4 address in their last 32 bits
+    if segments[..6] == [0x64, 0xff9b, 0, 0, 0, 0] {
+        let [.., a, b, c, d] = ip.octets();
+        return is_public_v4(Ipv4Addr::new(a, b, c, d));
+    }
+    !(ip.is_unspecified()
+        || ip.is_loopback()
+        || ip.is_multicast()
+        || (segments[0] & 0xfe00) == 0xfc00                 // Unique local, fc00::/7
+        || (segments[0] & 0xffc0) == 0xfe80                 // Link-local, fe80::/10
+        || (segments[0] == 0x2001 && segments[1] == 0xdb8)) // Documentation, 2001:db8::/32
+}
+
+// Helper function to reject the hosts given as a non public IP address. Names are checked once
+// resolved, by `PublicResolver`.
+fn check_host(url: &url::Url) -> Result<(), FetchError> {
+    let ip = match url.host() {
+        Some(url::Host::Ipv4(ip)) => IpAddr::V4(ip),
+        Some(url::Host::Ipv6(ip)) => IpAddr::V6(ip),
+        Some(url::Host::Domain(_)) => return Ok(()),
+        None => return Err(FetchError::InvalidUrl("the URL has no host".to_string())),
+    };
+    if is_public(ip) {
+        Ok(())
+    } else {
+        Err(FetchError::InvalidUrl("the host is not allowed".to_string()))
+    }
+}
+
+/// DNS resolver dropping the non public addresses, a name resolving only to such addresses fails.
+/// Resolved for every connection, so redirects and DNS rebinding go through it too.
+struct PublicResolver;
+
+impl Resolve for PublicResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        let host = name.as_str().to_string();
+        Box::pin(async move {
+            let addrs: Vec<SocketAddr> = spawn_blocking(move || (host.as_str(), 0).to_socket_addrs())
+                .await??
+                .filter(|addr| is_public(addr.ip()))
+                .collect();
+            if addrs.is_empty() {
+                return Err("the host has no public address".into());
+            }
+            Ok(Box::new(addrs.into_iter()) as Addrs)
+        })
+    }
+}
+
+// Helper function to parse a link, only http and https are fetched.
+fn http_url(url: &str) -> Result<url::Url, FetchError> {
+    let parsed = url::Url::parse(url.trim()).map_err(|e| FetchError::InvalidUrl(e.to_string()))?;
+    if parsed.scheme() != "http" && parsed.scheme() != "https" {
+        return Err(FetchError::InvalidUrl("only http and https are supported".to_string()));
+    }
+    Ok(parsed)
+}
+
+impl HttpFetcher {
+    pub fn new(timeout: Duration, allow_private_hosts: bool) -> HttpFetcher {
+        let mut builder = Client::builder().timeout(timeout).user_agent(USER_AGENT);
+        if allow_private_hosts {
+            builder = builder.redirect(Policy::limited(MAX_REDIRECTS));
+        } else {
+            // Every redirect is checked like the first URL, a proxy would resolve the names itself
+            let policy = Policy::custom(|attempt| {
+                if attempt.previous().len() >= MAX_REDIRECTS {
+                    return attempt.error("too many redirects");
+                }
+                match http_url(attempt.url().as_str()).and_then(|url| check_host(&url)) {
+                    Ok(()) => attempt.follow(),
+                    Err(e) => attempt.error(e),
+                }
+            });
+            builder = builder.redirect(policy).no_proxy().dns_resolver(Arc::new(PublicResolver));
+        }
+        let client = builder.build().expect("Failed to build the HTTP client");
+        HttpFetcher { client, allow_private_hosts }
+    }
+
+    // Helper function to parse and check a link before it is requested.
+    fn target(&self, url: &str) -> Result<url::Url, FetchError> {
+        let parsed = http_url(url)?;
+        if !self.allow_private_hosts {
+            check_host(&parsed)?;
+        }
+        Ok(parsed)
+    }
+}
+
+#[async_trait]
+impl PageFetcher for HttpFetcher {
+    async fn fetch(&self, url: &str) -> Result<FetchedPage, FetchError> {
+        let mut response = self.client.get(self.target(url)?).send().await?;
+        let status = response.status();
+        if !status.is_success() {
+            return Err(FetchError::Status(status.as_u16()));
+        }
+        // A missing content type is given the benefit of the doubt
+        let content_type = response
+            .headers()
+            .get(CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or("text/html")
+            .to_ascii_lowercase();
+        if !content_type.starts_with("text/html") && !content_type.starts_with("application/xhtml+xml") {
+            return Err(FetchError::NotHtml(content_type));
+        }
+
+        let final_url = response.url().to_string();
+        let mut body = Vec::new();
+        while let Some(chunk) = response.chunk().await? {
+            body.extend_from_slice(&chunk);
+            if body.len() >= MAX_BODY_BYTES {
+                body.truncate(MAX_BODY_BYTES);
+                break;
+            }
+        }
+
+        Ok(FetchedPage {
+            url: final_url,
+            status: status.as_u16(),
+            body: String::from_utf8_lossy(&body).into_owned(),
+        })
+    }
+
+    async fn check(&self, url: &str) -> Result<LinkStatus, FetchError> {
+        let parsed = self.target(url)?;
+        // Some servers refuse or mishandle HEAD, a GET is tried before reporting a failure
+        if let Ok(response) = self.client.head(parsed.clone()).send().await {
+            if response.status().is_success() {
+                return Ok(LinkStatus { url: response.url().to_string(), status: response.status().as_u16() });
+            }
+        }
+        let response = self.client.get(parsed).send().await?;
+        Ok(LinkStatus { url: response.url().to_string(), status: response.status().as_u16() })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test_server::serve;
+    use actix_web::{get, HttpResponse};
+
+    #[get("/page")]
+    async fn page() -> HttpResponse {
+        HttpResponse::Ok().content_type("text/html").body("<html><head><title>Page</title></head></html>")
+    }
+
+    #[get("/moved")]
+    async fn moved() -> HttpResponse {
+        HttpResponse::MovedPermanently().insert_header(("Location", "/page")).finish()
+    }
+
+    #[get("/data.json")]
+    async fn data() -> HttpResponse {
+        HttpResponse::Ok().content_type("application/json").body("{}")
+    }
+
+    #[get("/large")]
+    async fn large() -> HttpResponse {
+        HttpResponse::Ok().content_type("text/html; charset=utf-8").body("a".repeat(MAX_BODY_BYTES + 1024))
+    }
+
+    // Helper function to serve the fixture pages, reached with private hosts allowed.
+    fn fixtures() -> (HttpFetcher, String) {
+        let addr = serve(|cfg| {
+            cfg.service(page).service(moved).service(data).service(large);
+        });
+        (HttpFetcher::new(Duration::from_secs(5), true), format!("http://{}", addr))
+    }
+
+    #[actix_web::test]
+    async fn pages_are_fetched_after_redirects() {
+        let (fetcher, base) = fixtures();
+        let fetched = fetcher.fetch(&format!("{}/moved", base)).await.unwrap();
+        assert_eq!(fetched.url, format!("{}/page", base));
+        assert_eq!(fetched.status, 200);
+        assert!(fetched.body.contains("<title>Page</title>"));
+
+        let truncated = fetcher.fetch(&format!("{}/large", base)).await.unwrap();
+        assert_eq!(truncated.body.len(), MAX_BODY_BYTES);
+    }
+
+    #[actix_web::test]
+    async fn failed_fetches_are_errors() {
+        let (fetcher, base) = fixtures();
+        assert!(matches!(fetcher.fetch(&format!("{}/missing", base)).await, Err(FetchError::Status(404))));
+        assert!(matches!(fetcher.fetch(&format!("{}/data.json", base)).await, Err(FetchError::NotHtml(_))));
+        assert!(matches!(fetcher.fetch("ftp://example.com/page").await, Err(FetchError::InvalidUrl(_))));
+    }
+
+    #[test]
+    fn internal_addresses_are_not_public() {
+        for ip in [
+            "127.0.0.1", "10.1.2.3", "172.16.0.1", "192.168.1.1", "169.254.169.254", "0.0.0.0", "100.64.0.1",
+            "255.255.255.255", "::1", "::", "fd00::1", "fe80::1", "::ffff:127.0.0.1", "64:ff9b::a00:1",
+        ] {
+            assert!(!is_public(ip.parse().unwrap()), "{} should not be public", ip);
+        }
+        for ip in ["93.184.216.34", "1.1.1.1", "2606:4700:4700::1111", "::ffff:8.8.8.8"] {
+            assert!(is_public(ip.parse().unwrap()), "{} should be public", ip);
+        }
+    }
+
+    #[actix_web::test]
+    async fn private_hosts_are_refused() {
+        let addr = serve(|cfg| {
+            cfg.service(page);
+        });
+        let fetcher = HttpFetcher::new(Duration::from_secs(5), false);
+
+        let by_ip = fetcher.fetch(&format!("http://{}/page", addr)).await;
+        assert!(matches!(by_ip, Err(FetchError::InvalidUrl(_))));
+        let by_name = fetcher.check(&format!("http://localhost:{}/page", addr.port())).await;
+        assert!(matches!(by_name, Err(FetchError::HttpError(_))));
+
+        let allowed = HttpFetcher::new(Duration::from_secs(5), true);
+        assert_eq!(allowed.fetch(&format!("http://{}/page", addr)).await.unwrap().status, 200);
+    }
+}
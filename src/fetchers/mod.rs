@@ -0,0 +1,41 @@
+pub mod http_fetcher;
+
+use async_trait::async_trait;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum FetchError {
+    #[error("Invalid URL: {0}")]
+    InvalidUrl(String),
+    #[error("HTTP error: {0}")]
+    HttpError(#[from] reqwest::Error),
+    #[error("Unexpected status {0}")]
+    Status(u16),
+    #[error("Not an HTML page: {0}")]
+    NotHtml(String),
+}
+
+#[derive(Debug, Clone)]
+pub struct FetchedPage {
+    pub url: String, // After redirects
+    pub status: u16,
+    pub body: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct LinkStatus {
+    pub url: String, // After redirects
+    pub status: u16,
+}
+
+/// Anything that can download a web page. `main.rs` uses `HttpFetcher`, any local HTTP server
+/// serving fixture pages can stand in for the real sites.
+#[async_trait]
+pub trait PageFetcher: Send + Sync {
+    /// Get an HTML page, following redirects.
+    async fn fetch(&self, url: &str) -> Result<FetchedPage, FetchError>;
+
+    /// Get the status of a link without reading the page, following redirects. Any status
+    /// is returned, errors are only for requests that got no response.
+    async fn check(&self, url: &str) -> Result<LinkStatus, FetchError>;
+}
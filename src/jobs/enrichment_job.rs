@@ -0,0 +1,14 @@
+//! Background job filling the empty fields of existing courses from the metadata of their page.
+//! It is started on demand with `POST /courses/enrich/job`.
+
+use crate::extractors::audit_extractor::AuditContext;
+use crate::services::enrichment_service::ApiService as EnrichmentService;
+use actix_web::rt::spawn;
+
+/// Run the job in the background, `EnrichmentService::start_job` must have been called first.
+/// `ctx` is the context of the request that started it.
+pub fn spawn_enrichment(enrichment_service: EnrichmentService, overwrite: bool, ctx: AuditContext) {
+    spawn(async move {
+        enrichment_service.run_job(overwrite, &ctx).await;
+    });
+}
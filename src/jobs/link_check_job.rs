@@ -0,0 +1,25 @@
+//! Background job checking the link of every course. A link is marked broken after a
+//! number of consecutive failed checks, see `GET /courses/broken`.
+
+use crate::services::link_check_service::ApiService as LinkCheckService;
+use actix_web::rt::{spawn, time::interval};
+use std::time::Duration;
+
+/// Start the link check loop, it runs once at startup and then every `period`.
+pub fn spawn_link_check(link_check_service: LinkCheckService, period: Duration) {
+    spawn(async move {
+        let mut ticker = interval(period);
+        loop {
+            ticker.tick().await;
+
+            match link_check_service.check_all().await {
+                Ok(newly_broken) => {
+                    if newly_broken > 0 {
+                        eprintln!("Link check found {} newly broken courses", newly_broken);
+                    }
+                }
+                Err(e) => eprintln!("Error while checking course links: {:?}", e),
+            }
+        }
+    });
+}
@@ -0,0 +1,3 @@
+pub mod enrichment_job;
+pub mod link_check_job;
+pub mod purge_job;
@@ -0,0 +1,32 @@
+//! Background job permanently removing soft deleted courses and users once their
+//! retention window is over. The delete policy is applied to their watched records first.
+
+use crate::extractors::audit_extractor::AuditContext;
+use crate::services::integrity_service::ApiService as IntegrityService;
+use actix_web::rt::{spawn, time::interval};
+use bson::DateTime;
+use chrono::TimeDelta;
+use std::time::Duration;
+
+const PURGE_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// Start the purge loop, it runs once at startup and then every hour.
+/// `retention` is checked at startup to fit before the current date.
+pub fn spawn_purge(integrity_service: IntegrityService, retention: TimeDelta) {
+    spawn(async move {
+        let mut ticker = interval(PURGE_INTERVAL);
+        loop {
+            ticker.tick().await;
+
+            let cutoff = DateTime::from_chrono(chrono::Utc::now() - retention);
+            match integrity_service.purge_deleted(cutoff, &AuditContext::system()).await {
+                Ok(report) => {
+                    if !report.skipped.is_empty() {
+                        eprintln!("Purge kept {} referenced tombstones", report.skipped.len());
+                    }
+                }
+                Err(e) => eprintln!("Error while purging deleted documents: {:?}", e),
+            }
+        }
+    });
+}
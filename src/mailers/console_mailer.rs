@@ -0,0 +1,16 @@
+use super::{EmailMessage, Mailer, MailerError};
+use async_trait::async_trait;
+
+/// Mailer for local development that prints every email to stdout.
+pub struct ConsoleMailer;
+
+#[async_trait]
+impl Mailer for ConsoleMailer {
+    async fn send(&self, message: &EmailMessage) -> Result<(), MailerError> {
+        println!("----- Email to {} -----", message.to);
+        println!("Subject: {}", message.subject);
+        println!("{}", message.body);
+        println!("-----------------------");
+        Ok(())
+    }
+}
@@ -0,0 +1,27 @@
+use super::{EmailMessage, Mailer, MailerError};
+use async_trait::async_trait;
+use std::{fs::OpenOptions, io::Write, path::PathBuf};
+
+/// Mailer for local development that appends every email to a file.
+pub struct FileMailer {
+    path: PathBuf,
+}
+
+impl FileMailer {
+    pub fn new(path: PathBuf) -> FileMailer {
+        FileMailer { path }
+    }
+}
+
+#[async_trait]
+impl Mailer for FileMailer {
+    async fn send(&self, message: &EmailMessage) -> Result<(), MailerError> {
+        let mut file = OpenOptions::new().create(true).append(true).open(&self.path)?;
+        writeln!(file, "To: {}", message.to)?;
+        writeln!(file, "Subject: {}", message.subject)?;
+        writeln!(file)?;
+        writeln!(file, "{}", message.body)?;
+        writeln!(file, "-----")?;
+        Ok(())
+    }
+}
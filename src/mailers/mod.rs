@@ -0,0 +1,24 @@
+pub mod console_mailer;
+pub mod file_mailer;
+
+use async_trait::async_trait;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum MailerError {
+    #[error("Failed to write email: {0}")]
+    IoError(#[from] std::io::Error),
+}
+
+#[derive(Debug, Clone)]
+pub struct EmailMessage {
+    pub to: String,
+    pub subject: String,
+    pub body: String,
+}
+
+/// Anything that can deliver an email. Implementations are picked in `main.rs` through the `MAILER` variable.
+#[async_trait]
+pub trait Mailer: Send + Sync {
+    async fn send(&self, message: &EmailMessage) -> Result<(), MailerError>;
+}
@@ -1,25 +1,69 @@
+mod extractors;
+mod fetchers;
+mod jobs;
+mod mailers;
 mod models;
 mod routes;
 mod services;
+mod utils;
 
 use actix_cors::Cors;
 use actix_web::{http, middleware, App, HttpServer};
 use dotenv::dotenv;
+use fetchers::http_fetcher::HttpFetcher;
+use mailers::{console_mailer::ConsoleMailer, file_mailer::FileMailer, Mailer};
 use mongodb::{options::ClientOptions, Client};
-use std::env;
+use std::{env, net::IpAddr, sync::Arc, time::Duration};
 use services::{
+    api_key_service::ApiService as ApiKeyService,
+    audit_service::ApiService as AuditService,
     auth_service::ApiService as AuthService,
+    author_service::ApiService as AuthorService,
+    bookmark_service::ApiService as BookmarkService,
+    course_import_service::ApiService as CourseImportService,
     course_search_service::ApiService as CourseSearchService,
     course_service::ApiService as CourseService,
+    dashboard_service::ApiService as DashboardService,
+    enrichment_service::ApiService as EnrichmentService,
+    export_service::ApiService as ExportService,
+    integrity_service::{ApiService as IntegrityService, DeletePolicy},
+    learning_path_service::ApiService as LearningPathService,
+    link_check_service::ApiService as LinkCheckService,
+    mfa_service::ApiService as MfaService,
+    oidc_service::{ApiService as OidcService, OidcConfig},
+    platform_service::ApiService as PlatformService,
+    recommendation_service::ApiService as RecommendationService,
+    review_service::ApiService as ReviewService,
+    session_service::ApiService as SessionService,
+    suggest_service::ApiService as SuggestService,
+    topic_service::ApiService as TopicService,
     user_search_service::ApiService as UserSearchService,
     user_service::ApiService as UserService,
+    verification_service::{ApiService as VerificationService, VerificationPolicy},
     watched_service::ApiService as WatchedService
 };
 use routes::{ 
+    api_key_route,
+    audit_route,
     auth_route,
+    author_route,
+    bookmark_route,
+    course_import_route,
     course_route,
     course_search_route,
+    dashboard_route,
+    enrichment_route,
+    export_route,
     health_route,
+    integrity_route,
+    learning_path_route,
+    mfa_route,
+    oidc_route,
+    platform_route,
+    recommendation_route,
+    review_route,
+    suggest_route,
+    topic_route,
     user_route,
     user_search_route,
     watched_route
@@ -27,27 +71,88 @@ use routes::{
 
 #[derive(Clone)]
 pub struct ServiceManager {
+    pub api_key_service:        ApiKeyService,
+    pub audit_service:          AuditService,
     pub auth_service:           AuthService,
+    pub author_service:         AuthorService,
+    pub bookmark_service:       BookmarkService,
     pub course_service:         CourseService,
+    pub course_import_service:  CourseImportService,
     pub course_search_service:  CourseSearchService,
+    pub dashboard_service:      DashboardService,
+    pub enrichment_service:     EnrichmentService,
+    pub export_service:         ExportService,
+    pub integrity_service:      IntegrityService,
+    pub learning_path_service:  LearningPathService,
+    pub link_check_service:     LinkCheckService,
+    pub mfa_service:            MfaService,
+    pub oidc_service:           OidcService,
+    pub platform_service:       PlatformService,
+    pub recommendation_service: RecommendationService,
+    pub review_service:         ReviewService,
+    pub session_service:        SessionService,
+    pub suggest_service:        SuggestService,
+    pub topic_service:          TopicService,
     pub user_service:           UserService,
     pub user_search_service:    UserSearchService,
+    pub verification_service:   VerificationService,
     pub watched_service:        WatchedService,
 }
 
 impl ServiceManager {
-    pub fn new(auth_service:AuthService,
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(api_key_service: ApiKeyService,
+        audit_service: AuditService,
+        auth_service:AuthService,
+        author_service: AuthorService,
+        bookmark_service: BookmarkService,
         course_service: CourseService,
+        course_import_service: CourseImportService,
         course_search_service: CourseSearchService,
+        dashboard_service: DashboardService,
+        enrichment_service: EnrichmentService,
+        export_service: ExportService,
+        integrity_service: IntegrityService,
+        learning_path_service: LearningPathService,
+        link_check_service: LinkCheckService,
+        mfa_service: MfaService,
+        oidc_service: OidcService,
+        platform_service: PlatformService,
+        recommendation_service: RecommendationService,
+        review_service: ReviewService,
+        session_service: SessionService,
+        suggest_service: SuggestService,
+        topic_service: TopicService,
         user_service: UserService,
         user_search_service: UserSearchService,
+        verification_service: VerificationService,
         watched_service: WatchedService) -> Self {
         ServiceManager {
+            api_key_service,
+            audit_service,
             auth_service,
+            author_service,
+            bookmark_service,
             course_service,
+            course_import_service,
             course_search_service,
+            dashboard_service,
+            enrichment_service,
+            export_service,
+            integrity_service,
+            learning_path_service,
+            link_check_service,
+            mfa_service,
+            oidc_service,
+            platform_service,
+            recommendation_service,
+            review_service,
+            session_service,
+            suggest_service,
+            topic_service,
             user_service,
             user_search_service,
+            verification_service,
             watched_service,
         }
     }
@@ -71,28 +176,209 @@ async fn main() -> Result<(), Box<dyn std::error::Error>>{
     let database_name = env::var("DATABASE_NAME").expect("DATABASE_NAME is not in .env file");
     let db = client.database(&database_name);
 
+    // The course, user and watched collections must be named, the others have a default name
+    let api_key_collection_name = env::var("API_KEY_COLLECTION_NAME").unwrap_or_else(|_| "api_keys".to_string());
+    let audit_collection_name = env::var("AUDIT_COLLECTION_NAME").unwrap_or_else(|_| "audit_log".to_string());
     let auth_collection_name = env::var("USER_COLLECTION_NAME").expect("USER_COLLECTION_NAME is not set in .env file");
+    let author_collection_name = env::var("AUTHOR_COLLECTION_NAME").unwrap_or_else(|_| "authors".to_string());
+    let bookmark_collection_name = env::var("BOOKMARK_COLLECTION_NAME").unwrap_or_else(|_| "bookmarks".to_string());
     let course_collection_name = env::var("COURSE_COLLECTION_NAME").expect("COURSE_COLLECTION_NAME is not set in .env file");
     let course_search_collection_name = env::var("COURSE_COLLECTION_NAME").expect("COURSE_COLLECTION_NAME is not set in .env file");
+    let dashboard_collection_name = env::var("WATCHED_COLLECTION_NAME").expect("WATCHED_COLLECTION_NAME is not set in .env file");
+    let learning_path_collection_name = env::var("LEARNING_PATH_COLLECTION_NAME").unwrap_or_else(|_| "learning_paths".to_string());
+    let mfa_collection_name = env::var("MFA_COLLECTION_NAME").unwrap_or_else(|_| "mfa".to_string());
+    let oidc_state_collection_name = env::var("OIDC_STATE_COLLECTION_NAME").unwrap_or_else(|_| "oidc_states".to_string());
+    let identity_collection_name = env::var("IDENTITY_COLLECTION_NAME").unwrap_or_else(|_| "identities".to_string());
+    let platform_collection_name = env::var("PLATFORM_COLLECTION_NAME").unwrap_or_else(|_| "platforms".to_string());
+    let dismissed_collection_name = env::var("DISMISSED_RECOMMENDATION_COLLECTION_NAME").unwrap_or_else(|_| "dismissed_recommendations".to_string());
+    let review_collection_name = env::var("REVIEW_COLLECTION_NAME").unwrap_or_else(|_| "reviews".to_string());
+    let session_collection_name = env::var("SESSION_COLLECTION_NAME").unwrap_or_else(|_| "sessions".to_string());
+    let topic_collection_name = env::var("TOPIC_COLLECTION_NAME").unwrap_or_else(|_| "topics".to_string());
     let user_collection_name = env::var("USER_COLLECTION_NAME").expect("USER_COLLECTION_NAME is not set in .env file");
     let user_search_collection_name = env::var("USER_COLLECTION_NAME").expect("USER_COLLECTION_NAME is not set in .env file");
     let watched_collection_name = env::var("WATCHED_COLLECTION_NAME").expect("WATCHED_COLLECTION_NAME is not set in .env file");
 
+    let api_key_collection = db.collection(&api_key_collection_name);
+    let audit_collection = db.collection(&audit_collection_name);
     let auth_collection = db.collection(&auth_collection_name);
+    let author_collection = db.collection(&author_collection_name);
+    let bookmark_collection = db.collection(&bookmark_collection_name);
     let course_collection = db.collection(&course_collection_name);
     let course_search_collection = db.collection(&course_search_collection_name);
+    let dashboard_collection = db.collection(&dashboard_collection_name);
+    let learning_path_collection = db.collection(&learning_path_collection_name);
+    let mfa_collection = db.collection(&mfa_collection_name);
+    let oidc_state_collection = db.collection(&oidc_state_collection_name);
+    let identity_collection = db.collection(&identity_collection_name);
+    let platform_collection = db.collection(&platform_collection_name);
+    let dismissed_collection = db.collection(&dismissed_collection_name);
+    let review_collection = db.collection(&review_collection_name);
+    let session_collection = db.collection(&session_collection_name);
+    let topic_collection = db.collection(&topic_collection_name);
     let user_collection = db.collection(&user_collection_name);
     let user_search_collection = db.collection(&user_search_collection_name);
     let watched_collection = db.collection(&watched_collection_name);
 
+    let api_key_service = ApiKeyService::new(api_key_collection);
+    // Only connections from these addresses may set the client address with `X-Forwarded-For`
+    let trusted_proxies: Vec<IpAddr> = env::var("TRUSTED_PROXIES")
+        .unwrap_or_default()
+        .split(',')
+        .map(str::trim)
+        .filter(|proxy| !proxy.is_empty())
+        .map(str::parse)
+        .collect::<Result<_, _>>()?;
+    let audit_service = AuditService::new(audit_collection, trusted_proxies);
     let auth_service = AuthService::new(auth_collection);
-    let course_service = CourseService::new(course_collection);
-    let course_search_service = CourseSearchService::new(course_search_collection);
-    let user_service = UserService::new(user_collection);
+    let bookmark_service = BookmarkService::new(bookmark_collection, course_collection_name.clone());
+    let suggest_service = SuggestService::new();
+    let topic_service = TopicService::new(topic_collection, db.collection(&course_collection_name), audit_service.clone());
+    let platform_service = PlatformService::new(platform_collection, db.collection(&course_collection_name), suggest_service.clone(), audit_service.clone());
+    let author_service = AuthorService::new(author_collection, db.collection(&course_collection_name), suggest_service.clone(), audit_service.clone());
+    let course_service = CourseService::new(
+        course_collection,
+        audit_service.clone(),
+        suggest_service.clone(),
+        topic_service.clone(),
+        platform_service.clone(),
+        author_service.clone(),
+    );
+    let course_import_service = CourseImportService::new(course_service.clone());
+    let course_search_service = CourseSearchService::new(course_search_collection, topic_service.clone());
+    let dashboard_service = DashboardService::new(dashboard_collection, course_collection_name.clone());
+
+    // Course pages are fetched to prefill and enrich courses
+    let fetch_timeout_seconds: u64 = env::var("FETCH_TIMEOUT_SECONDS")
+        .unwrap_or_else(|_| "10".to_string())
+        .parse()?;
+    // Course pages are only fetched from public addresses unless explicitly allowed, e.g. for local tests
+    let fetch_allow_private_hosts: bool = env::var("FETCH_ALLOW_PRIVATE_HOSTS")
+        .unwrap_or_else(|_| "false".to_string())
+        .parse()?;
+    let fetcher = Arc::new(HttpFetcher::new(Duration::from_secs(fetch_timeout_seconds), fetch_allow_private_hosts));
+    let enrichment_service = EnrichmentService::new(fetcher.clone(), course_service.clone());
+    let link_check_threshold: i32 = env::var("LINK_CHECK_FAILURE_THRESHOLD")
+        .unwrap_or_else(|_| "3".to_string())
+        .parse()?;
+    let link_check_service = LinkCheckService::new(fetcher, course_service.clone(), link_check_threshold);
+
+    let export_service = ExportService::new(
+        db.collection(&course_collection_name),
+        db.collection(&user_collection_name),
+        db.collection(&watched_collection_name),
+        course_collection_name.clone(),
+        user_collection_name.clone(),
+        env::var("XAPI_HOME_PAGE").unwrap_or_else(|_| "http://localhost:8080".to_string()),
+    );
+    let learning_path_service = LearningPathService::new(learning_path_collection, audit_service.clone());
+    let mfa_issuer = env::var("MFA_ISSUER").unwrap_or_else(|_| "mylearning".to_string());
+    let mfa_service = MfaService::new(mfa_collection, mfa_issuer);
+
+    // Single sign-on is only enabled when an issuer is configured
+    let oidc_config = env::var("OIDC_ISSUER_URL").ok().map(|issuer_url| OidcConfig {
+        issuer_url,
+        client_id: env::var("OIDC_CLIENT_ID").expect("OIDC_CLIENT_ID is not set in .env file"),
+        client_secret: env::var("OIDC_CLIENT_SECRET").ok(),
+        redirect_url: env::var("OIDC_REDIRECT_URL").expect("OIDC_REDIRECT_URL is not set in .env file"),
+    });
+    let oidc_service = OidcService::new(oidc_state_collection, identity_collection, oidc_config);
+
+    let recommendation_service = RecommendationService::new(
+        db.collection(&course_collection_name),
+        db.collection(&user_collection_name),
+        db.collection(&watched_collection_name),
+        dismissed_collection,
+    );
+    let review_service = ReviewService::new(review_collection, course_service.clone(), audit_service.clone());
+    let session_service = SessionService::new(session_collection);
+    let user_service = UserService::new(user_collection, audit_service.clone());
     let user_search_service = UserSearchService::new(user_search_collection);
-    let watched_service = WatchedService::new(watched_collection);
+    let watched_service = WatchedService::new(watched_collection, audit_service.clone());
+
+    let mailer: Arc<dyn Mailer> = match env::var("MAILER").unwrap_or_else(|_| "console".to_string()).as_str() {
+        "file" => {
+            let path = env::var("MAILER_FILE_PATH").expect("MAILER_FILE_PATH is not set in .env file");
+            Arc::new(FileMailer::new(path.into()))
+        }
+        "console" => Arc::new(ConsoleMailer),
+        other => return Err(format!("Unknown mailer: {}", other).into()),
+    };
+    let verification_policy: VerificationPolicy = env::var("EMAIL_VERIFICATION_POLICY")
+        .unwrap_or_else(|_| "login".to_string())
+        .parse()?;
+    let verification_url = env::var("EMAIL_VERIFICATION_URL").expect("EMAIL_VERIFICATION_URL is not set in .env file");
+    let verification_service = VerificationService::new(mailer, verification_policy, verification_url);
+
+    if let Err(e) = author_service.ensure_indexes().await {
+        eprintln!("Error while creating author indexes: {:?}", e);
+    }
+
+    if let Err(e) = bookmark_service.ensure_indexes().await {
+        eprintln!("Error while creating bookmark indexes: {:?}", e);
+    }
+
+    // The autocomplete index lives in memory and is kept up to date by the course service
+    match course_service.get_all().await {
+        Ok(courses) => suggest_service.rebuild(&courses),
+        Err(e) => eprintln!("Error while loading the suggest index: {:?}", e),
+    }
+
+    // Logged instead of aborting, courses sharing a url must be merged first
+    if let Err(e) = course_service.ensure_indexes().await {
+        eprintln!("Error while creating course indexes: {:?}", e);
+    }
+
+    if let Err(e) = platform_service.ensure_indexes().await {
+        eprintln!("Error while creating platform indexes: {:?}", e);
+    }
+
+    if let Err(e) = review_service.ensure_indexes().await {
+        eprintln!("Error while creating review indexes: {:?}", e);
+    }
+
+    if let Err(e) = topic_service.ensure_indexes().await {
+        eprintln!("Error while creating topic indexes: {:?}", e);
+    }
+
+    // Logged instead of aborting, users sharing an email must be merged first
+    if let Err(e) = user_service.ensure_indexes().await {
+        eprintln!("Error while creating user indexes: {:?}", e);
+    }
+
+    // Logged instead of aborting, existing duplicates must be merged first with `POST /watched/merge-duplicates`
+    if let Err(e) = watched_service.ensure_indexes().await {
+        eprintln!("Error while creating watched indexes: {:?}", e);
+    }
+
+    let delete_policy: DeletePolicy = env::var("DELETE_POLICY")
+        .unwrap_or_else(|_| "archive".to_string())
+        .parse()?;
+    let integrity_service = IntegrityService::new(
+        course_service.clone(),
+        user_service.clone(),
+        watched_service.clone(),
+        delete_policy,
+    );
+
+    // Soft deleted courses and users are purged after the retention window
+    let retention_days: i64 = env::var("SOFT_DELETE_RETENTION_DAYS")
+        .unwrap_or_else(|_| "30".to_string())
+        .parse()?;
+    let retention = chrono::TimeDelta::try_days(retention_days)
+        .filter(|retention| retention_days >= 0 && chrono::Utc::now().checked_sub_signed(*retention).is_some())
+        .ok_or_else(|| format!("Invalid SOFT_DELETE_RETENTION_DAYS: {}", retention_days))?;
+    jobs::purge_job::spawn_purge(integrity_service.clone(), retention);
+
+    // Course links are checked on a schedule, `0` turns the checks off
+    let link_check_interval_hours: u64 = env::var("LINK_CHECK_INTERVAL_HOURS")
+        .unwrap_or_else(|_| "24".to_string())
+        .parse()?;
+    if link_check_interval_hours > 0 {
+        let period = Duration::from_secs(link_check_interval_hours * 60 * 60);
+        jobs::link_check_job::spawn_link_check(link_check_service.clone(), period);
+    }
 
-    let service_manager = ServiceManager::new(auth_service, course_service, course_search_service, user_service, user_search_service, watched_service);
+    let service_manager = ServiceManager::new(api_key_service, audit_service, auth_service, author_service, bookmark_service, course_service, course_import_service, course_search_service, dashboard_service, enrichment_service, export_service, integrity_service, learning_path_service, link_check_service, mfa_service, oidc_service, platform_service, recommendation_service, review_service, session_service, suggest_service, topic_service, user_service, user_search_service, verification_service, watched_service);
 
     let server_url = env::var("SERVER_URL").expect("SERVER_URL is not set in .env file");
 
@@ -111,12 +397,29 @@ async fn main() -> Result<(), Box<dyn std::error::Error>>{
             .app_data(actix_web::web::Data::new(AppState {
                 service_manager: service_manager.clone(),
             }))
+            .configure(api_key_route::init)
+            .configure(audit_route::init)
             .configure(auth_route::init)
+            .configure(author_route::init)
+            .configure(bookmark_route::init)
+            .configure(course_import_route::init)
+            .configure(course_search_route::init) // Before `course_route` so `GET /courses/search` is not taken for an id
+            .configure(suggest_route::init)       // Same for `GET /courses/suggest`
+            .configure(enrichment_route::init)
             .configure(course_route::init)
-            .configure(course_search_route::init)
+            .configure(dashboard_route::init)
+            .configure(export_route::init)
+            .configure(mfa_route::init)
+            .configure(oidc_route::init)
+            .configure(platform_route::init)
+            .configure(recommendation_route::init)
+            .configure(review_route::init)
+            .configure(topic_route::init)
             .configure(user_route::init)
             .configure(user_search_route::init)
             .configure(watched_route::init)
+            .configure(integrity_route::init)
+            .configure(learning_path_route::init)
             .configure(health_route::init)
     })
     .bind(server_url)?
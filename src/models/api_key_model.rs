@@ -0,0 +1,61 @@
+use bson::{oid::ObjectId, DateTime};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ApiScope {
+    CatalogRead,    // Read the catalog only, no access to the data of the user
+    WatchedWrite,   // Read the data of the user, create, update and delete their watched records
+    Admin,          // Everything, only for admin users
+}
+
+// Structure for DB
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ApiKey {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub _id          : Option<ObjectId>,
+    pub user_id      : ObjectId,
+    pub name         : String,
+    pub prefix       : String,        // First characters of the key, to recognize it in listings
+    pub key_hash     : String,        // SHA-256 of the key, the key itself is never stored
+    pub scopes       : Vec<ApiScope>,
+    pub expires_at   : Option<DateTime>,
+    pub last_used_at : Option<DateTime>,
+    pub revoked_at   : Option<DateTime>,
+    pub created_at   : DateTime,
+}
+
+// Structure returned by the API, without the hash
+#[derive(Debug, Serialize)]
+pub struct ApiKeySummary {
+    pub _id          : Option<ObjectId>,
+    pub name         : String,
+    pub prefix       : String,
+    pub scopes       : Vec<ApiScope>,
+    pub expires_at   : Option<DateTime>,
+    pub last_used_at : Option<DateTime>,
+    pub revoked_at   : Option<DateTime>,
+    pub created_at   : DateTime,
+}
+
+impl From<ApiKey> for ApiKeySummary {
+    fn from(key: ApiKey) -> Self {
+        ApiKeySummary {
+            _id: key._id,
+            name: key.name,
+            prefix: key.prefix,
+            scopes: key.scopes,
+            expires_at: key.expires_at,
+            last_used_at: key.last_used_at,
+            revoked_at: key.revoked_at,
+            created_at: key.created_at,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateApiKeyRequest {
+    pub name: String,
+    pub scopes: Vec<ApiScope>,
+    pub expires_at: Option<chrono::DateTime<chrono::Utc>>, // RFC 3339, never expires when omitted
+}
@@ -0,0 +1,43 @@
+use bson::{oid::ObjectId, DateTime, Document};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditAction {
+    Create,
+    Update,
+    Delete,     // Soft delete, the document is kept as a tombstone
+    Restore,
+    Purge,      // Permanent removal of a tombstone
+    Login,
+    LoginFailed,
+}
+
+// Structure for DB, entries are only ever inserted
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AuditEvent {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub _id         : Option<ObjectId>,
+    pub actor_id    : Option<ObjectId>, // None for anonymous requests and background jobs
+    pub action      : AuditAction,
+    pub target_type : String,           // "course", "user", "watched", ...
+    pub target_id   : Option<String>,
+    pub before      : Option<Document>, // Changed fields before the mutation
+    pub after       : Option<Document>, // Changed fields after the mutation
+    pub ip          : Option<String>,
+    pub request_id  : String,
+    pub created_at  : DateTime,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AuditQueryParams {
+    pub actor_id: Option<String>,
+    pub action: Option<AuditAction>,
+    pub target_type: Option<String>,
+    pub target_id: Option<String>,
+    pub request_id: Option<String>,
+    pub from: Option<chrono::DateTime<chrono::Utc>>, // RFC 3339, inclusive
+    pub to: Option<chrono::DateTime<chrono::Utc>>,   // RFC 3339, exclusive
+    pub limit: Option<i64>,
+    pub skip: Option<u64>,
+}
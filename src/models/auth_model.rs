@@ -5,3 +5,19 @@ pub struct LoginRequest {
     pub email: String,
     pub password: String,
 }
+
+#[derive(Debug, Deserialize)]
+pub struct MfaLoginRequest {
+    pub mfa_token: String, // Challenge token returned by `/auth/login`
+    pub code: String,      // TOTP code or one of the recovery codes
+}
+
+#[derive(Debug, Deserialize)]
+pub struct VerifyEmailRequest {
+    pub token: String, // Token from the verification email
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ResendVerificationRequest {
+    pub email: String,
+}
@@ -0,0 +1,23 @@
+use bson::{oid::ObjectId, DateTime};
+use serde::{Deserialize, Serialize};
+
+// Structure for DB
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Author {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub _id          : Option<ObjectId>,
+    pub name         : String,
+    pub slug         : String,         // Unique, derived from the name to catch other spellings
+    pub bio          : Option<String>,
+    pub homepage_url : Option<String>,
+    pub created_at   : DateTime,
+    pub updated_at   : DateTime,
+}
+
+// Body of the create and update routes
+#[derive(Debug, Deserialize)]
+pub struct AuthorRequest {
+    pub name: String,
+    pub bio: Option<String>,
+    pub homepage_url: Option<String>,
+}
@@ -0,0 +1,64 @@
+use crate::models::course_model::Course;
+use bson::{oid::ObjectId, DateTime};
+use serde::{Deserialize, Serialize};
+
+// A saved course, in the order chosen by the learner
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BookmarkItem {
+    pub course_id : ObjectId,
+    #[serde(default)]
+    pub note      : Option<String>,
+    pub added_at  : DateTime,
+}
+
+// Structure for DB, a named list of saved courses such as "Wishlist"
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BookmarkList {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub _id        : Option<ObjectId>,
+    pub user_id    : ObjectId,
+    pub name       : String,   // Unique per user
+    pub items      : Vec<BookmarkItem>,
+    pub created_at : DateTime,
+    pub updated_at : DateTime,
+}
+
+// A list with the details of its courses, as returned by the API
+#[derive(Debug, Serialize)]
+pub struct BookmarkListView {
+    pub _id        : ObjectId,
+    pub name       : String,
+    pub items      : Vec<BookmarkItemView>,
+    pub created_at : DateTime,
+    pub updated_at : DateTime,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BookmarkItemView {
+    pub course_id : ObjectId,
+    pub course    : Option<Course>,   // `None` once the course is deleted
+    pub note      : Option<String>,
+    pub added_at  : DateTime,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct BookmarkListRequest {
+    pub name : String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AddBookmarkRequest {
+    pub course_id : ObjectId,
+    pub note      : Option<String>,
+    pub position  : Option<u32>,   // Index to insert at, appended when missing
+}
+
+#[derive(Debug, Deserialize)]
+pub struct BookmarkNoteRequest {
+    pub note : Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ReorderBookmarksRequest {
+    pub course_ids : Vec<ObjectId>,   // Every course of the list, in the new order
+}
@@ -0,0 +1,76 @@
+use bson::oid::ObjectId;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ImportFormat {
+    Csv,
+    Ndjson,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ImportParams {
+    pub format  : Option<ImportFormat>,   // Taken from the `Content-Type` header when missing
+    #[serde(default)]
+    pub dry_run : bool,                   // Validate and report without writing anything
+}
+
+// A course as found in an NDJSON line
+#[derive(Debug, Deserialize)]
+pub struct CourseImportRow {
+    pub title       : String,
+    pub platform    : String,
+    pub author      : String,
+    pub duration    : i32,
+    #[serde(default)]
+    pub language    : String,
+    #[serde(default)]
+    pub description : String,
+    pub url         : String,
+    #[serde(default)]
+    pub topics      : Vec<String>,
+}
+
+// A course as found in a CSV row, topics are separated by `;`
+#[derive(Debug, Deserialize)]
+pub struct CsvCourseImportRow {
+    pub title       : String,
+    pub platform    : String,
+    pub author      : String,
+    pub duration    : i32,
+    #[serde(default)]
+    pub language    : String,
+    #[serde(default)]
+    pub description : String,
+    pub url         : String,
+    #[serde(default)]
+    pub topics      : String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ImportRowStatus {
+    Created,
+    Updated,
+    Unchanged,  // The course already matches the row
+    Failed,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ImportRowResult {
+    pub row       : usize,               // 1-based, CSV rows are counted without the header
+    pub status    : ImportRowStatus,
+    pub url       : Option<String>,
+    pub course_id : Option<ObjectId>,    // Missing for failed rows and rows created in a dry run
+    pub error     : Option<String>,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct ImportReport {
+    pub dry_run   : bool,
+    pub created   : usize,
+    pub updated   : usize,
+    pub unchanged : usize,
+    pub failed    : usize,
+    pub rows      : Vec<ImportRowResult>,
+}
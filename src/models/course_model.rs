@@ -2,13 +2,18 @@ use bson::{oid::ObjectId, DateTime};
 use serde::{Deserialize, Serialize};
 
 // Structure for DB
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Course {
     #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
     pub _id         : Option<ObjectId>, // Optional field
     pub title       : String,
-    pub platform    : String,
-    pub author      : String,
+    pub platform    : String,           // Name of the platform, copied from `platform_id`
+    pub author      : String,           // Name of the author, copied from `author_id`
+    // References to the platform and author collections, resolved from the names when missing
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub platform_id : Option<ObjectId>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub author_id   : Option<ObjectId>,
     pub duration    : i32,
     pub language    : String,
     pub description : String,
@@ -16,4 +21,47 @@ pub struct Course {
     pub topics      : Vec<String>,
     pub created_at  : DateTime,
     pub updated_at  : DateTime,
+    // Aggregate of the visible reviews, only changed by the review service
+    #[serde(default)]
+    pub rating_average : f64,
+    #[serde(default)]
+    pub rating_count   : i64,
+    #[serde(default)]
+    pub rating_sum     : i64,
+    // Link checker state, only changed by the link check job
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub link_checks    : Vec<LinkCheck>,  // Most recent last
+    #[serde(default)]
+    pub link_failures  : i32,             // Consecutive failed checks
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub link_broken_at : Option<DateTime>, // Set once the failures reach the threshold
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deleted_at  : Option<DateTime>, // Tombstone, hidden from listings and search
+}
+
+// One check of the course link
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LinkCheck {
+    pub checked_at : DateTime,
+    pub ok         : bool,
+    pub status     : Option<i32>,    // HTTP status after redirects, none when the request failed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub final_url  : Option<String>, // Where the redirects led
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error      : Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ReferenceMigrationParams {
+    #[serde(default)]
+    pub dry_run: bool, // Report the changes without writing them
+}
+
+// Result of turning the platform or author names of the courses into references
+#[derive(Debug, Default, Serialize)]
+pub struct ReferenceMigrationReport {
+    pub dry_run         : bool,
+    pub spellings       : u64,         // Distinct names found on courses
+    pub created         : Vec<String>, // Names of the platforms or authors added
+    pub courses_updated : u64,
 }
\ No newline at end of file
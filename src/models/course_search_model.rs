@@ -1,9 +1,164 @@
-use serde::Deserialize;
+use crate::models::course_model::Course;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
 
-#[derive(Deserialize)]
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CourseSearchSort {
+    Rating,    // Best rated first, ties broken by the number of ratings
+    Title,     // Alphabetical
+    Duration,  // Shortest first
+    CreatedAt, // Newest first
+    UpdatedAt, // Most recently updated first
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SortOrder {
+    Asc,
+    Desc,
+}
+
+// How several values or fields are combined
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MatchMode {
+    #[default]
+    Any, // `$or`
+    All, // `$and`
+}
+
+// One node of a search. The text fields (title, author, platform, topics) are combined
+// with `match`, any of them by default. The other filters always apply, and so do the
+// nested groups: every `all` group, at least one `any` group and none of the `not` groups.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct CourseSearchCriteria {
+    pub title: Option<String>,
+    pub author: Option<String>,
+    pub platform: Option<String>,
+    pub topics: Option<Vec<String>>,     // Topics is an optional array of strings
+    pub topics_match: Option<MatchMode>, // Any of the topics by default
+    #[serde(rename = "match")]
+    pub match_mode: Option<MatchMode>,
+    pub language: Option<String>,        // Exact, case insensitive
+    pub min_duration: Option<i32>,       // Minutes, inclusive
+    pub max_duration: Option<i32>,
+    pub min_rating: Option<f64>,
+    pub created_after: Option<DateTime<Utc>>,  // RFC 3339, inclusive
+    pub created_before: Option<DateTime<Utc>>, // RFC 3339, exclusive
+    pub updated_after: Option<DateTime<Utc>>,
+    pub updated_before: Option<DateTime<Utc>>,
+    pub all: Option<Vec<CourseSearchCriteria>>,
+    pub any: Option<Vec<CourseSearchCriteria>>,
+    pub not: Option<Vec<CourseSearchCriteria>>,
+}
+
+#[derive(Debug, Deserialize)]
 pub struct CourseSearchParams {
+    #[serde(flatten)]
+    pub criteria: CourseSearchCriteria,
+    pub sort: Option<CourseSearchSort>,
+    pub order: Option<SortOrder>, // Defaults to the natural order of the sort field
+    #[serde(default)]
+    pub facets: bool,             // Return facet counts with the hits, never streamed
+    pub skip: Option<u64>,        // Paging of the faceted hits
+    pub limit: Option<i64>,       // Hits per page of a faceted search, `SEARCH_HITS_LIMIT` by default
+}
+
+// Query string of `GET /courses/search`, the same fields without the nested groups.
+// Topics are separated by commas.
+#[derive(Debug, Deserialize)]
+pub struct CourseSearchQuery {
     pub title: Option<String>,
     pub author: Option<String>,
     pub platform: Option<String>,
-    pub topics: Option<Vec<String>>, // Topics is an optional array of strings
-}
\ No newline at end of file
+    pub topics: Option<String>,
+    pub topics_match: Option<MatchMode>,
+    #[serde(rename = "match")]
+    pub match_mode: Option<MatchMode>,
+    pub language: Option<String>,
+    pub min_duration: Option<i32>,
+    pub max_duration: Option<i32>,
+    pub min_rating: Option<f64>,
+    pub created_after: Option<DateTime<Utc>>,
+    pub created_before: Option<DateTime<Utc>>,
+    pub updated_after: Option<DateTime<Utc>>,
+    pub updated_before: Option<DateTime<Utc>>,
+    pub sort: Option<CourseSearchSort>,
+    pub order: Option<SortOrder>,
+    #[serde(default)]
+    pub facets: bool,
+    pub skip: Option<u64>,
+    pub limit: Option<i64>,
+}
+
+impl From<CourseSearchQuery> for CourseSearchParams {
+    fn from(q: CourseSearchQuery) -> Self {
+        let topics = q.topics.map(|topics| {
+            topics
+                .split(',')
+                .map(str::trim)
+                .filter(|topic| !topic.is_empty())
+                .map(str::to_string)
+                .collect()
+        });
+        CourseSearchParams {
+            criteria: CourseSearchCriteria {
+                title: q.title,
+                author: q.author,
+                platform: q.platform,
+                topics,
+                topics_match: q.topics_match,
+                match_mode: q.match_mode,
+                language: q.language,
+                min_duration: q.min_duration,
+                max_duration: q.max_duration,
+                min_rating: q.min_rating,
+                created_after: q.created_after,
+                created_before: q.created_before,
+                updated_after: q.updated_after,
+                updated_before: q.updated_before,
+                all: None,
+                any: None,
+                not: None,
+            },
+            sort: q.sort,
+            order: q.order,
+            facets: q.facets,
+            skip: q.skip,
+            limit: q.limit,
+        }
+    }
+}
+
+// Number of courses for one value of a facet
+#[derive(Debug, Serialize)]
+pub struct FacetCount {
+    pub value : String,
+    pub count : i64,
+}
+
+// Number of courses in a duration range, in minutes
+#[derive(Debug, Serialize)]
+pub struct DurationBucket {
+    pub min_duration : i32,
+    pub max_duration : Option<i32>, // Exclusive, open ended for the last bucket
+    pub count        : i64,
+}
+
+// Each facet counts the courses matching every active filter except its own
+#[derive(Debug, Serialize)]
+pub struct SearchFacets {
+    pub platform : Vec<FacetCount>,
+    pub author   : Vec<FacetCount>,
+    pub language : Vec<FacetCount>,
+    pub topics   : Vec<FacetCount>,
+    pub duration : Vec<DurationBucket>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct FacetedSearchResult {
+    pub hits   : Vec<Course>, // One page, see `skip` and `limit`
+    pub total  : i64,         // Courses matching the search, across all pages
+    pub facets : SearchFacets,
+}
@@ -0,0 +1,47 @@
+use bson::{oid::ObjectId, DateTime};
+use serde::{Deserialize, Serialize};
+
+// A course the learner started and has not finished yet
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CourseInProgress {
+    pub watched_id            : ObjectId,
+    pub course_id             : ObjectId,
+    pub title                 : String,
+    pub platform              : String,
+    pub duration              : i32,
+    pub percent_complete      : f64,
+    pub last_position_minutes : f64,
+    pub updated_at            : DateTime,
+}
+
+// Watched records grouped by topic or platform
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Breakdown {
+    pub name      : String,
+    pub courses   : i64,
+    pub completed : i64,
+    pub minutes   : f64,   // Minutes learned, see `Dashboard.total_minutes_learned`
+}
+
+// Latest watched records of the learner
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RecentActivity {
+    pub watched_id       : ObjectId,
+    pub course_id        : ObjectId,
+    pub title            : String,
+    pub percent_complete : f64,
+    pub finished_at      : Option<DateTime>,
+    pub updated_at       : DateTime,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Dashboard {
+    pub user_id               : ObjectId,
+    pub courses_in_progress   : Vec<CourseInProgress>,
+    pub completed_count       : i64,
+    pub total_minutes_learned : f64,   // `Course.duration` weighted by the percent complete of each active record
+    pub topics                : Vec<Breakdown>,
+    pub platforms             : Vec<Breakdown>,
+    pub current_streak_days   : i64,   // Consecutive days with activity, ending today or yesterday
+    pub recent_activity       : Vec<RecentActivity>,
+}
@@ -0,0 +1,46 @@
+use bson::DateTime;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Deserialize)]
+pub struct EnrichRequest {
+    pub url: String,
+}
+
+// Metadata found on the page of a course, JSON-LD first, then OpenGraph, then plain HTML
+#[derive(Debug, Default, Clone, Serialize)]
+pub struct CourseMetadata {
+    pub url         : String,         // After redirects
+    pub title       : Option<String>,
+    pub description : Option<String>,
+    pub author      : Option<String>,
+    pub duration    : Option<i32>,    // Minutes
+    pub language    : Option<String>, // Primary subtag of a language code, e.g. `en`
+}
+
+#[derive(Debug, Deserialize)]
+pub struct EnrichmentJobParams {
+    #[serde(default)]
+    pub overwrite: bool, // Replace the fields already filled, only empty fields are set by default
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct EnrichmentFailure {
+    pub course_id : String,
+    pub url       : String,
+    pub error     : String,
+}
+
+// Progress of the last enrichment job, kept in memory
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct EnrichmentJobStatus {
+    pub running     : bool,
+    pub overwrite   : bool,
+    pub started_at  : Option<DateTime>,
+    pub finished_at : Option<DateTime>,
+    pub total       : u64,
+    pub processed   : u64,
+    pub updated     : u64,
+    pub failed      : u64,
+    pub failures    : Vec<EnrichmentFailure>, // The most recent ones
+    pub error       : Option<String>,         // Why the job stopped before the end
+}
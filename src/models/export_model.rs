@@ -0,0 +1,48 @@
+use crate::models::user_model::{User, UserRole};
+use bson::{oid::ObjectId, DateTime};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    Csv,
+    Ndjson,
+    Xapi, // Only for watched records, one statement per completed record
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ExportParams {
+    pub format : ExportFormat,
+}
+
+// A user as exported, without the password hash
+#[derive(Debug, Serialize)]
+pub struct UserExport {
+    pub _id            : Option<ObjectId>,
+    pub name           : String,
+    pub lastname       : String,
+    pub major          : String,
+    pub email          : String,
+    pub watched_ids    : Vec<String>,
+    pub email_verified : bool,
+    pub role           : UserRole,
+    pub created_at     : DateTime,
+    pub updated_at     : DateTime,
+}
+
+impl From<User> for UserExport {
+    fn from(u: User) -> Self {
+        UserExport {
+            _id: u._id,
+            name: u.name,
+            lastname: u.lastname,
+            major: u.major,
+            email: u.email,
+            watched_ids: u.watched_ids.unwrap_or_default(),
+            email_verified: u.email_verified,
+            role: u.role,
+            created_at: u.created_at,
+            updated_at: u.updated_at,
+        }
+    }
+}
@@ -0,0 +1,25 @@
+use bson::oid::ObjectId;
+use serde::Serialize;
+
+// `watched_ids` of a user pointing to watched records that do not exist
+#[derive(Debug, Serialize)]
+pub struct DanglingWatchedIds {
+    pub user_id     : ObjectId,
+    pub watched_ids : Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct IntegrityReport {
+    pub watched_missing_course : Vec<ObjectId>, // Active watched records whose course does not exist
+    pub watched_missing_user   : Vec<ObjectId>, // Active watched records whose user does not exist
+    pub dangling_watched_ids   : Vec<DanglingWatchedIds>,
+    pub repaired               : bool,          // Orphans archived and dangling ids removed
+}
+
+// Outcome of a purge of soft deleted courses and users
+#[derive(Debug, Default, Serialize)]
+pub struct PurgeReport {
+    pub courses_purged : Vec<ObjectId>,
+    pub users_purged   : Vec<ObjectId>,
+    pub skipped        : Vec<ObjectId>, // Tombstones kept because the restrict policy refused the purge
+}
@@ -0,0 +1,61 @@
+use bson::{oid::ObjectId, DateTime};
+use serde::{Deserialize, Serialize};
+
+fn default_required() -> bool {
+    true
+}
+
+// A course of a path, in the order it should be taken
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PathCourse {
+    pub course_id     : ObjectId,
+    #[serde(default = "default_required")]
+    pub required      : bool,
+    #[serde(default)]
+    pub prerequisites : Vec<ObjectId>,   // Earlier courses of the path to finish first
+}
+
+// Structure for DB
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LearningPath {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub _id           : Option<ObjectId>,
+    pub title         : String,
+    pub description   : String,
+    pub courses       : Vec<PathCourse>,
+    #[serde(default)]
+    pub prerequisites : Vec<ObjectId>,   // Other paths to complete first
+    pub created_at    : DateTime,
+    pub updated_at    : DateTime,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PathCourseStatus {
+    NotStarted,
+    InProgress,
+    Completed,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PathCourseProgress {
+    pub course_id        : ObjectId,
+    pub required         : bool,
+    pub status           : PathCourseStatus,
+    pub percent_complete : f64,
+    pub locked           : bool,    // Some prerequisites are not completed yet
+}
+
+// Progress of a learner through a path, computed from their watched records
+#[derive(Debug, Serialize)]
+pub struct PathProgress {
+    pub path_id            : ObjectId,
+    pub user_id            : ObjectId,
+    pub courses            : Vec<PathCourseProgress>,
+    pub required_total     : usize,
+    pub required_completed : usize,
+    pub percent_complete   : f64,     // Share of the required courses completed
+    pub completed          : bool,    // Every required course is completed and the prerequisites are met
+    pub prerequisites_met  : bool,    // Every prerequisite path is completed, with its own prerequisites
+    pub next_course_id     : Option<ObjectId>, // First unlocked course not completed yet
+}
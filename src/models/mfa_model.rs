@@ -0,0 +1,21 @@
+use bson::{oid::ObjectId, DateTime};
+use serde::{Deserialize, Serialize};
+
+// Structure for DB
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MfaEnrollment {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub _id             : Option<ObjectId>,
+    pub user_id         : ObjectId,
+    pub secret          : String,       // Base32-encoded TOTP secret
+    pub enabled         : bool,         // Set once the first code has been confirmed
+    pub recovery_codes  : Vec<String>,  // SHA-256 hashes of the unused recovery codes
+    pub last_used_step  : Option<i64>,  // Last accepted TOTP time step, prevents replays
+    pub created_at      : DateTime,
+    pub updated_at      : DateTime,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct MfaCodeRequest {
+    pub code: String,
+}
@@ -1,6 +1,24 @@
+pub mod api_key_model;
+pub mod audit_model;
 pub mod auth_model;
+pub mod author_model;
+pub mod bookmark_model;
+pub mod course_import_model;
 pub mod course_model;
 pub mod course_search_model;
+pub mod dashboard_model;
+pub mod enrichment_model;
+pub mod export_model;
+pub mod integrity_model;
+pub mod learning_path_model;
+pub mod mfa_model;
+pub mod oidc_model;
+pub mod platform_model;
+pub mod recommendation_model;
+pub mod review_model;
+pub mod session_model;
+pub mod suggest_model;
+pub mod topic_model;
 pub mod user_model;
 pub mod user_search_model;
 pub mod watched_model;
\ No newline at end of file
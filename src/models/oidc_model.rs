@@ -0,0 +1,45 @@
+use bson::{oid::ObjectId, DateTime};
+use serde::{Deserialize, Serialize};
+
+// Structure for DB: an authorization request waiting for the identity provider callback
+#[derive(Debug, Serialize, Deserialize)]
+pub struct OidcLoginState {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub _id           : Option<ObjectId>,
+    pub state         : String,
+    pub nonce         : String,
+    pub code_verifier : String, // PKCE verifier, only its S256 challenge leaves the server
+    pub expires_at    : DateTime,
+    pub created_at    : DateTime,
+}
+
+// Structure for DB: links an identity at an external provider to a `User`
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ExternalIdentity {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub _id         : Option<ObjectId>,
+    pub user_id     : ObjectId,
+    pub issuer      : String,
+    pub subject     : String, // The `sub` claim, stable for a user at a given issuer
+    pub email       : Option<String>,
+    pub created_at  : DateTime,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct OidcCallbackParams {
+    pub code: Option<String>,
+    pub state: Option<String>,
+    pub error: Option<String>,
+}
+
+// Claims read from a validated ID token
+#[derive(Debug, Clone, Deserialize)]
+pub struct IdTokenClaims {
+    pub sub: String,
+    pub nonce: Option<String>,
+    pub email: Option<String>,
+    pub email_verified: Option<bool>,
+    pub name: Option<String>,
+    pub given_name: Option<String>,
+    pub family_name: Option<String>,
+}
@@ -0,0 +1,32 @@
+use crate::models::course_model::Course;
+use bson::{oid::ObjectId, DateTime};
+use serde::{Deserialize, Serialize};
+
+// Structure for DB, a course the learner does not want to be suggested again
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DismissedRecommendation {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub _id        : Option<ObjectId>,
+    pub user_id    : ObjectId,
+    pub course_id  : ObjectId,
+    pub created_at : DateTime,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Recommendation {
+    pub course  : Course,
+    pub score   : f64,           // Between 0 and 1
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub reasons : Vec<String>,   // Why the course was suggested, only with `explain=true`
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RecommendationParams {
+    pub limit   : Option<usize>,
+    pub explain : Option<bool>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DismissRecommendationRequest {
+    pub course_id : ObjectId,
+}
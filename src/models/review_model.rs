@@ -0,0 +1,51 @@
+use bson::{oid::ObjectId, DateTime};
+use serde::{Deserialize, Serialize};
+
+// Structure for DB, one review per learner and course
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Review {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub _id          : Option<ObjectId>,
+    pub course_id    : ObjectId,
+    pub user_id      : ObjectId,
+    pub rating       : i32,            // From 1 to 5
+    pub comment      : String,
+    pub hidden       : bool,           // Hidden by a moderator, left out of listings and the course rating
+    pub flagged      : bool,           // Reported for moderation
+    #[serde(default)]
+    pub flag_reasons : Vec<String>,
+    #[serde(default, skip_serializing)]
+    pub flagged_by   : Vec<ObjectId>,  // One report per user, the reporters are never returned
+    pub created_at   : DateTime,
+    pub updated_at   : DateTime,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ReviewRequest {
+    pub rating  : i32,
+    #[serde(default)]
+    pub comment : String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct FlagReviewRequest {
+    pub reason : Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReviewSort {
+    #[default]
+    Newest,
+    Oldest,
+    Highest,
+    Lowest,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ReviewListParams {
+    #[serde(default)]
+    pub sort           : ReviewSort,
+    #[serde(default)]
+    pub include_hidden : bool,   // Admin only
+}
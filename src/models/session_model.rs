@@ -0,0 +1,32 @@
+use bson::{oid::ObjectId, DateTime};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SessionKind {
+    Session,        // A fully authenticated session
+    MfaChallenge,   // Password verified, waiting for the second factor
+    EmailVerification, // Sent by email to confirm the address of a new account
+}
+
+impl SessionKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SessionKind::Session => "session",
+            SessionKind::MfaChallenge => "mfa_challenge",
+            SessionKind::EmailVerification => "email_verification",
+        }
+    }
+}
+
+// Structure for DB
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Session {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub _id         : Option<ObjectId>,
+    pub token_hash  : String, // SHA-256 of the token handed to the client
+    pub user_id     : ObjectId,
+    pub kind        : SessionKind,
+    pub expires_at  : DateTime,
+    pub created_at  : DateTime,
+}
@@ -0,0 +1,27 @@
+use bson::oid::ObjectId;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Deserialize)]
+pub struct SuggestParams {
+    pub q: String,
+    pub limit: Option<usize>, // 10 by default, at most 50
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SuggestKind {
+    Title,
+    Author,
+    Topic,
+}
+
+// A completion for the text typed by the learner
+#[derive(Debug, Serialize)]
+pub struct Suggestion {
+    pub text         : String,
+    pub kind         : SuggestKind,
+    pub score        : f64,
+    pub distance     : usize,            // Edit distance to the query, 0 for an exact prefix
+    pub course_count : usize,            // Courses with this title, author or topic
+    pub course_id    : Option<ObjectId>, // Set when a single course matches
+}
@@ -0,0 +1,54 @@
+use bson::{oid::ObjectId, DateTime};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+
+// Structure for DB
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Topic {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub _id        : Option<ObjectId>,
+    pub slug       : String,         // Canonical form stored in `Course.topics`, unique and never changed
+    pub name       : String,         // Display name
+    #[serde(default)]
+    pub synonyms   : Vec<String>,    // Slugs of other spellings, normalized to `slug` on course writes
+    #[serde(default)]
+    pub parent     : Option<String>, // Slug of the parent topic, searches include the descendants
+    pub created_at : DateTime,
+    pub updated_at : DateTime,
+}
+
+// Body of the create and update routes, the slug is derived from the name when missing
+#[derive(Debug, Deserialize)]
+pub struct TopicRequest {
+    pub name: String,
+    pub slug: Option<String>,
+    #[serde(default)]
+    pub synonyms: Vec<String>,
+    pub parent: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TopicMigrationParams {
+    #[serde(default)]
+    pub dry_run: bool, // Report the changes without writing them
+}
+
+// Optional body of the migration, other spellings to merge into managed topics,
+// e.g. `{ "synonyms": { "ML": "machine-learning" } }`
+#[derive(Debug, Default, Deserialize)]
+pub struct TopicMigrationRequest {
+    #[serde(default)]
+    pub synonyms: HashMap<String, String>, // Spelling to the slug of a managed topic
+}
+
+// Result of canonicalizing the topics of every course
+#[derive(Debug, Default, Serialize)]
+pub struct TopicMigrationReport {
+    pub dry_run          : bool,
+    pub courses_scanned  : u64,
+    pub courses_updated  : u64,
+    pub synonyms_added   : Vec<String>,          // Spellings of the request added as synonyms
+    pub unmatched_topics : BTreeMap<String, u64>, // Slugs matching no managed topic, with their number of courses
+    #[serde(skip)]
+    pub updated_course_ids : Vec<ObjectId>,      // Courses whose topics were rewritten
+}
@@ -1,6 +1,19 @@
 use bson::{oid::ObjectId, DateTime};
 use serde::{Deserialize, Serialize};
 
+// Accounts created before email verification existed have no flag and count as verified
+fn default_email_verified() -> bool {
+    true
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UserRole {
+    #[default]
+    Learner,
+    Admin, // Only granted directly in the database
+}
+
 // Structure for DB
 #[derive(Debug, Serialize, Deserialize)]
 pub struct User{
@@ -12,6 +25,12 @@ pub struct User{
     pub email       : String,
     pub password    : String,
     pub watched_ids : Option<Vec<String>>,
+    #[serde(default = "default_email_verified")]
+    pub email_verified : bool,
+    #[serde(default)]
+    pub role        : UserRole,
     pub created_at  : DateTime,
     pub updated_at  : DateTime,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deleted_at  : Option<DateTime>, // Tombstone, hidden from listings and search
 }
\ No newline at end of file
@@ -4,10 +4,60 @@ use serde::{Deserialize, Serialize};
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Watched {
     #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
-    pub _id         : Option<ObjectId>,
-    pub course_id   : ObjectId,
-    pub finished_at : Option<DateTime>,
-    pub created_at  : DateTime,
-    pub updated_at  : DateTime,
-    pub archived    : bool,
-}
\ No newline at end of file
+    pub _id                   : Option<ObjectId>,
+    #[serde(default)]
+    pub user_id               : Option<ObjectId>,   // Learner, missing on records created before it existed
+    pub course_id             : ObjectId,
+    pub finished_at           : Option<DateTime>,
+    pub created_at            : DateTime,
+    pub updated_at            : DateTime,
+    pub archived              : bool,
+    // Progress fields, only moved forward through `POST /watched/{id}/progress`
+    #[serde(default)]
+    pub percent_complete      : f64,
+    #[serde(default)]
+    pub last_position_minutes : f64,              // Compared to `Course.duration`
+    #[serde(default)]
+    pub time_spent_minutes    : f64,
+    #[serde(default)]
+    pub started_at            : Option<DateTime>,
+}
+
+// Heartbeat sent by the player while a course is being watched
+#[derive(Debug, Deserialize)]
+pub struct ProgressUpdate {
+    pub position_minutes: Option<f64>,  // Current position in the course
+    pub percent_complete: Option<f64>,  // Used when the position is unknown
+    pub elapsed_minutes: Option<f64>,   // Time spent since the previous heartbeat
+}
+
+// Result of merging the duplicate active records of a learner for one course
+#[derive(Debug, Serialize)]
+pub struct MergedDuplicates {
+    pub user_id   : ObjectId,
+    pub course_id : ObjectId,
+    pub kept      : ObjectId,      // The oldest record, which now holds the merged progress
+    pub removed   : Vec<ObjectId>,
+}
+
+// Which watched records a listing returns
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WatchedStatus {
+    #[default]
+    Active,
+    Archived,
+    All,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct WatchedListParams {
+    #[serde(default)]
+    pub status: WatchedStatus,
+}
+
+// Bulk archive of the records finished more than `older_than_days` ago
+#[derive(Debug, Deserialize)]
+pub struct ArchiveFinishedRequest {
+    pub older_than_days: i64,
+}
@@ -0,0 +1,90 @@
+use crate::extractors::auth_extractor::AuthenticatedUser;
+use crate::models::{
+    api_key_model::{ApiKeySummary, ApiScope, CreateApiKeyRequest},
+    user_model::UserRole,
+};
+use actix_web::{delete, get, post, web, HttpResponse, Responder};
+
+/// Route to create a personal API key, the key is only returned in this response
+#[post("/api-keys")]
+async fn add(
+    app_data: web::Data<crate::AppState>,
+    auth: AuthenticatedUser,
+    data: web::Json<CreateApiKeyRequest>,
+) -> impl Responder {
+    // Keys are managed from an interactive session, a leaked key cannot mint new ones
+    if auth.is_api_key() {
+        return HttpResponse::Forbidden().body("API keys cannot be managed with an API key");
+    }
+    if data.name.trim().is_empty() {
+        return HttpResponse::BadRequest().body("The key must have a name");
+    }
+    if data.scopes.is_empty() {
+        return HttpResponse::BadRequest().body("At least one scope must be provided");
+    }
+    if data.scopes.contains(&ApiScope::Admin) && auth.user.role != UserRole::Admin {
+        return HttpResponse::Forbidden().body("Only admins can create keys with the admin scope");
+    }
+    if matches!(data.expires_at, Some(expires_at) if expires_at <= chrono::Utc::now()) {
+        return HttpResponse::BadRequest().body("expires_at must be in the future");
+    }
+
+    match app_data.service_manager.api_key_service.create(auth.user_id, &data).await {
+        Ok((api_key, key)) => HttpResponse::Ok().json(serde_json::json!({
+            "key": key,
+            "api_key": ApiKeySummary::from(api_key),
+        })),
+        Err(e) => {
+            eprintln!("Error while creating API key: {:?}", e);
+            HttpResponse::InternalServerError().body("Failed to create the API key")
+        }
+    }
+}
+
+/// Route to list the API keys of the current user
+#[get("/api-keys")]
+async fn get_all(app_data: web::Data<crate::AppState>, auth: AuthenticatedUser) -> impl Responder {
+    match app_data.service_manager.api_key_service.get_by_user(auth.user_id).await {
+        Ok(keys) => {
+            let keys: Vec<ApiKeySummary> = keys.into_iter().map(ApiKeySummary::from).collect();
+            HttpResponse::Ok().json(keys)
+        }
+        Err(e) => {
+            eprintln!("Error while getting API keys: {:?}", e);
+            HttpResponse::InternalServerError().body("Failed to retrieve API keys")
+        }
+    }
+}
+
+/// Route to revoke one of the API keys of the current user
+#[delete("/api-keys/{id}")]
+async fn revoke(
+    app_data: web::Data<crate::AppState>,
+    auth: AuthenticatedUser,
+    key_id: web::Path<String>,
+) -> impl Responder {
+    if auth.is_api_key() {
+        return HttpResponse::Forbidden().body("API keys cannot be managed with an API key");
+    }
+
+    let id = key_id.into_inner();
+    match app_data.service_manager.api_key_service.revoke(auth.user_id, &id).await {
+        Ok(result) => {
+            if result.modified_count > 0 {
+                HttpResponse::Ok().json("API key revoked successfully")
+            } else {
+                HttpResponse::NotFound().body("API key not found or already revoked")
+            }
+        }
+        Err(e) => {
+            eprintln!("Error while revoking API key: {:?}", e);
+            HttpResponse::InternalServerError().body("Failed to revoke the API key")
+        }
+    }
+}
+
+pub fn init(cfg: &mut web::ServiceConfig) {
+    cfg.service(add);
+    cfg.service(get_all);
+    cfg.service(revoke);
+}
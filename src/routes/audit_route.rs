@@ -0,0 +1,66 @@
+use crate::extractors::auth_extractor::AuthenticatedUser;
+use crate::models::{api_key_model::ApiScope, audit_model::AuditQueryParams};
+use actix_web::{get, web, HttpResponse, Responder};
+use mongodb::bson::{doc, oid::ObjectId, DateTime, Document};
+
+const DEFAULT_LIMIT: i64 = 100;
+const MAX_LIMIT: i64 = 1000;
+
+/// Route to query the audit log, admin only
+#[get("/audit-events")]
+async fn search(
+    app_data: web::Data<crate::AppState>,
+    auth: AuthenticatedUser,
+    query: web::Query<AuditQueryParams>,
+) -> impl Responder {
+    if !auth.has_scope(ApiScope::Admin) {
+        return HttpResponse::Forbidden().body("Admin access required");
+    }
+
+    let mut filter = Document::new();
+
+    // Add filters only if they are provided
+    if let Some(ref actor_id) = query.actor_id {
+        match ObjectId::parse_str(actor_id) {
+            Ok(actor_id) => filter.insert("actor_id", actor_id),
+            Err(_) => return HttpResponse::BadRequest().body("Invalid actor_id"),
+        };
+    }
+    if let Some(action) = query.action {
+        filter.insert("action", mongodb::bson::to_bson(&action).unwrap_or_default());
+    }
+    if let Some(ref target_type) = query.target_type {
+        filter.insert("target_type", target_type);
+    }
+    if let Some(ref target_id) = query.target_id {
+        filter.insert("target_id", target_id);
+    }
+    if let Some(ref request_id) = query.request_id {
+        filter.insert("request_id", request_id);
+    }
+    let mut created_at = Document::new();
+    if let Some(from) = query.from {
+        created_at.insert("$gte", DateTime::from_chrono(from));
+    }
+    if let Some(to) = query.to {
+        created_at.insert("$lt", DateTime::from_chrono(to));
+    }
+    if !created_at.is_empty() {
+        filter.insert("created_at", created_at);
+    }
+
+    let limit = query.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT);
+    let skip = query.skip.unwrap_or(0);
+
+    match app_data.service_manager.audit_service.search(filter, limit, skip).await {
+        Ok(events) => HttpResponse::Ok().json(events),
+        Err(e) => {
+            eprintln!("Error while searching audit events: {:?}", e);
+            HttpResponse::InternalServerError().body("Failed to search audit events")
+        }
+    }
+}
+
+pub fn init(cfg: &mut web::ServiceConfig) {
+    cfg.service(search);
+}
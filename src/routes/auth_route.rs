@@ -1,10 +1,102 @@
-use crate::models::auth_model::LoginRequest;
+use crate::extractors::audit_extractor::AuditContext;
+use crate::models::{
+    audit_model::AuditAction,
+    auth_model::{LoginRequest, MfaLoginRequest, ResendVerificationRequest, VerifyEmailRequest},
+    session_model::SessionKind,
+    user_model::User,
+};
+use crate::services::{mfa_service::ApiServiceError as MfaError, verification_service::VerificationPolicy};
 use actix_web::{post, web, HttpResponse, Responder};
+use mongodb::bson::oid::ObjectId;
 use crate::AppState;
 
+// Helper function to open a session for a user and build the successful login response.
+async fn login_success(app_data: &AppState, user: User, audit: &AuditContext) -> HttpResponse {
+    let user_id = match user._id {
+        Some(id) => id,
+        None => return HttpResponse::InternalServerError().body("User has no _id"),
+    };
+
+    let session_token = match app_data
+        .service_manager
+        .session_service
+        .create(user_id, SessionKind::Session)
+        .await
+    {
+        Ok(token) => token,
+        Err(e) => {
+            eprintln!("Error while creating session: {:?}", e);
+            return HttpResponse::InternalServerError().body("Error occurred while processing login");
+        }
+    };
+
+    app_data
+        .service_manager
+        .audit_service
+        .record(&audit.with_actor(user_id), AuditAction::Login, "user", Some(user_id.to_hex()), None, None)
+        .await;
+
+    // Get the current timestamp in milliseconds and convert to string
+    let login_time = chrono::Utc::now().timestamp_millis().to_string();
+
+    // Build the response JSON
+    HttpResponse::Ok().json(serde_json::json!({
+        "message": "Login successful",
+        "login_time": login_time,  // Include the Unix timestamp as a string
+        "session_token": session_token,
+        "user": {
+            "_id": user._id,
+            "name": user.name,
+            "lastname": user.lastname,
+            "major": user.major,
+            "email": user.email,
+            "password": user.password,
+            "watched_ids": user.watched_ids,
+            "email_verified": user.email_verified,
+            "role": user.role,
+            "created_at": user.created_at,
+            "updated_at": user.updated_at,
+        }
+    }))
+}
+
+/// Finish a login once the first factor is verified: users with MFA enabled get a
+/// challenge token instead of a session.
+pub async fn complete_login(app_data: &AppState, user: User, audit: &AuditContext) -> HttpResponse {
+    let user_id = match user._id {
+        Some(id) => id,
+        None => return HttpResponse::InternalServerError().body("User has no _id"),
+    };
+
+    match app_data.service_manager.mfa_service.is_enabled(user_id).await {
+        Ok(true) => match app_data
+            .service_manager
+            .session_service
+            .create(user_id, SessionKind::MfaChallenge)
+            .await
+        {
+            Ok(mfa_token) => HttpResponse::Ok().json(serde_json::json!({
+                "message": "MFA code required",
+                "mfa_required": true,
+                "mfa_token": mfa_token,
+            })),
+            Err(e) => {
+                eprintln!("Error while creating MFA challenge: {:?}", e);
+                HttpResponse::InternalServerError().body("Error occurred while processing login")
+            }
+        },
+        Ok(false) => login_success(app_data, user, audit).await,
+        Err(e) => {
+            eprintln!("Error while checking MFA status: {:?}", e);
+            HttpResponse::InternalServerError().body("Error occurred while processing login")
+        }
+    }
+}
+
 #[post("/auth/login")]
 async fn login(
     app_data: web::Data<AppState>,
+    audit: AuditContext,
     credentials: web::Json<LoginRequest>,
 ) -> impl Responder {
     // Attempt to authenticate the user
@@ -16,32 +108,156 @@ async fn login(
 
     match result {
         Ok(Some(user)) => {
-            // Get the current timestamp in milliseconds and convert to string
-            let login_time = chrono::Utc::now().timestamp_millis().to_string();
-
-            // Build the response JSON
-            HttpResponse::Ok().json(serde_json::json!({
-                "message": "Login successful",
-                "login_time": login_time,  // Include the Unix timestamp as a string
-                "user": {
-                    "_id": user._id,
-                    "name": user.name,
-                    "lastname": user.lastname,
-                    "major": user.major,
-                    "email": user.email,
-                    "password": user.password,
-                    "watched_ids": user.watched_ids,
-                    "created_at": user.created_at,
-                    "updated_at": user.updated_at,
-                }
-            }))
+            if !user.email_verified
+                && app_data.service_manager.verification_service.policy() == VerificationPolicy::RestrictLogin
+            {
+                return HttpResponse::Forbidden().body("Email address is not verified");
+            }
+
+            complete_login(&app_data, user, &audit).await
+        },
+        Ok(None) => {
+            let attempt = mongodb::bson::doc! { "email": &credentials.email };
+            app_data
+                .service_manager
+                .audit_service
+                .record(&audit, AuditAction::LoginFailed, "user", None, None, Some(attempt))
+                .await;
+            HttpResponse::Unauthorized().body("Invalid email or password")
         },
-        Ok(None) => HttpResponse::Unauthorized().body("Invalid email or password"),
         Err(_) => HttpResponse::InternalServerError().body("Error occurred while processing login"),
     }
 }
 
+/// Second step of the login for users with MFA enabled: exchange the challenge token
+/// and a TOTP or recovery code for a session.
+#[post("/auth/login/mfa")]
+async fn login_mfa(
+    app_data: web::Data<AppState>,
+    audit: AuditContext,
+    body: web::Json<MfaLoginRequest>,
+) -> impl Responder {
+    let services = &app_data.service_manager;
+
+    // The challenge token is single use, a wrong code requires logging in again
+    let challenge = match services
+        .session_service
+        .consume(&body.mfa_token, SessionKind::MfaChallenge)
+        .await
+    {
+        Ok(Some(challenge)) => challenge,
+        Ok(None) => return HttpResponse::Unauthorized().body("Invalid or expired MFA token"),
+        Err(e) => {
+            eprintln!("Error while reading MFA challenge: {:?}", e);
+            return HttpResponse::InternalServerError().body("Error occurred while processing login");
+        }
+    };
+
+    match services.mfa_service.verify(challenge.user_id, &body.code).await {
+        Ok(()) => {}
+        Err(MfaError::InvalidCode) | Err(MfaError::NotEnrolled) => {
+            let target_id = Some(challenge.user_id.to_hex());
+            services.audit_service.record(&audit, AuditAction::LoginFailed, "user", target_id, None, None).await;
+            return HttpResponse::Unauthorized().body("Invalid MFA code");
+        }
+        Err(e) => {
+            eprintln!("Error while verifying MFA code: {:?}", e);
+            return HttpResponse::InternalServerError().body("Error occurred while processing login");
+        }
+    }
+
+    match services.user_service.get_by_id(&challenge.user_id.to_hex()).await {
+        Ok(Some(user)) => login_success(&app_data, user, &audit).await,
+        Ok(None) => HttpResponse::Unauthorized().body("Invalid email or password"),
+        Err(e) => {
+            eprintln!("Error while loading user: {:?}", e);
+            HttpResponse::InternalServerError().body("Error occurred while processing login")
+        }
+    }
+}
+
+/// Confirm the email address of an account with the token sent by email
+#[post("/auth/verify-email")]
+async fn verify_email(
+    app_data: web::Data<AppState>,
+    audit: AuditContext,
+    body: web::Json<VerifyEmailRequest>,
+) -> impl Responder {
+    let services = &app_data.service_manager;
+
+    let verification = match services
+        .session_service
+        .consume(&body.token, SessionKind::EmailVerification)
+        .await
+    {
+        Ok(Some(verification)) => verification,
+        Ok(None) => return HttpResponse::BadRequest().body("Invalid or expired verification token"),
+        Err(e) => {
+            eprintln!("Error while reading verification token: {:?}", e);
+            return HttpResponse::InternalServerError().body("Failed to verify the email address");
+        }
+    };
+
+    match services.user_service.mark_email_verified(verification.user_id, &audit.with_actor(verification.user_id)).await {
+        Ok(result) if result.matched_count > 0 => HttpResponse::Ok().json("Email verified successfully"),
+        Ok(_) => HttpResponse::NotFound().body("User not found"),
+        Err(e) => {
+            eprintln!("Error while verifying email: {:?}", e);
+            HttpResponse::InternalServerError().body("Failed to verify the email address")
+        }
+    }
+}
+
+/// Send a new verification email. The response does not reveal whether the account exists.
+#[post("/auth/verify-email/resend")]
+async fn resend_verification(
+    app_data: web::Data<AppState>,
+    body: web::Json<ResendVerificationRequest>,
+) -> impl Responder {
+    let services = &app_data.service_manager;
+
+    match services.user_service.get_by_email(&body.email).await {
+        Ok(Some(User { _id: Some(user_id), email, email_verified: false, .. })) => {
+            if let Err(e) = send_verification(&app_data, user_id, &email).await {
+                return e;
+            }
+        }
+        Ok(_) => {}
+        Err(e) => {
+            eprintln!("Error while looking up user: {:?}", e);
+            return HttpResponse::InternalServerError().body("Failed to send the verification email");
+        }
+    }
+
+    HttpResponse::Ok().json("If the account exists and is unverified, a verification email has been sent")
+}
+
+/// Create a verification token for a user and email it to them.
+pub async fn send_verification(app_data: &AppState, user_id: ObjectId, email: &str) -> Result<(), HttpResponse> {
+    let services = &app_data.service_manager;
+
+    let token = services
+        .session_service
+        .create(user_id, SessionKind::EmailVerification)
+        .await
+        .map_err(|e| {
+            eprintln!("Error while creating verification token: {:?}", e);
+            HttpResponse::InternalServerError().body("Failed to send the verification email")
+        })?;
+
+    services
+        .verification_service
+        .send_verification_email(email, &token)
+        .await
+        .map_err(|e| {
+            eprintln!("Error while sending verification email: {:?}", e);
+            HttpResponse::InternalServerError().body("Failed to send the verification email")
+        })
+}
 
 pub fn init(cfg: &mut web::ServiceConfig) {
     cfg.service(login);
+    cfg.service(login_mfa);
+    cfg.service(verify_email);
+    cfg.service(resend_verification);
 }
@@ -0,0 +1,142 @@
+use crate::extractors::{audit_extractor::AuditContext, auth_extractor::AuthenticatedUser};
+use crate::models::{api_key_model::ApiScope, author_model::AuthorRequest, course_model::ReferenceMigrationParams};
+use crate::services::reference_service::ApiServiceError;
+use actix_web::{delete, get, post, put, web, HttpResponse, Responder};
+
+// Helper function to map validation errors to responses, `None` for unexpected errors.
+fn client_error(e: &ApiServiceError) -> Option<HttpResponse> {
+    match e {
+        ApiServiceError::InvalidObjectId => Some(HttpResponse::BadRequest().body(e.to_string())),
+        ApiServiceError::InvalidRequest(message) => Some(HttpResponse::BadRequest().body(message.clone())),
+        ApiServiceError::Conflict(message) => Some(HttpResponse::Conflict().body(message.clone())),
+        ApiServiceError::InUse => Some(HttpResponse::Conflict().body("The author is still used by courses")),
+        ApiServiceError::DatabaseError(_) => None,
+    }
+}
+
+/// Route to get all authors
+#[get("/authors")]
+async fn get_all(app_data: web::Data<crate::AppState>) -> impl Responder {
+    match app_data.service_manager.author_service.get_all().await {
+        Ok(authors) => HttpResponse::Ok().json(authors),
+        Err(e) => {
+            eprintln!("Error while getting authors: {:?}", e);
+            HttpResponse::InternalServerError().body("Failed to retrieve authors")
+        }
+    }
+}
+
+/// Route to get an author by its MongoDB `_id`
+#[get("/authors/{id}")]
+async fn get_by_id(
+    app_data: web::Data<crate::AppState>,
+    author_id: web::Path<String>,
+) -> impl Responder {
+    let id = author_id.into_inner();
+    match app_data.service_manager.author_service.get_by_id(&id).await {
+        Ok(Some(author)) => HttpResponse::Ok().json(author),
+        Ok(None) => HttpResponse::NotFound().body("Author not found"),
+        Err(e) => {
+            eprintln!("Error while getting author: {:?}", e);
+            HttpResponse::InternalServerError().body("Failed to retrieve the author")
+        }
+    }
+}
+
+/// Route to add a new author, admin only
+#[post("/authors")]
+async fn add(
+    app_data: web::Data<crate::AppState>,
+    auth: AuthenticatedUser,
+    audit: AuditContext,
+    data: web::Json<AuthorRequest>,
+) -> impl Responder {
+    if !auth.has_scope(ApiScope::Admin) {
+        return HttpResponse::Forbidden().body("Admin access required");
+    }
+    match app_data.service_manager.author_service.create(&data, &audit).await {
+        Ok(result) => match result.inserted_id.as_object_id() {
+            Some(id) => HttpResponse::Ok().json(id.to_hex()),
+            None => HttpResponse::InternalServerError().body("Failed to extract inserted_id"),
+        },
+        Err(e) => client_error(&e).unwrap_or_else(|| {
+            eprintln!("Error while adding author: {:?}", e);
+            HttpResponse::InternalServerError().body("Failed to add the author")
+        }),
+    }
+}
+
+/// Route to update an author, a new name is copied onto its courses, admin only
+#[put("/authors/{id}")]
+async fn update(
+    app_data: web::Data<crate::AppState>,
+    auth: AuthenticatedUser,
+    audit: AuditContext,
+    data: web::Json<AuthorRequest>,
+    author_id: web::Path<String>,
+) -> impl Responder {
+    if !auth.has_scope(ApiScope::Admin) {
+        return HttpResponse::Forbidden().body("Admin access required");
+    }
+    let id = author_id.into_inner();
+    match app_data.service_manager.author_service.update(&data, &id, &audit).await {
+        Ok(Some(_)) => HttpResponse::Ok().json("Author updated successfully"),
+        Ok(None) => HttpResponse::NotFound().body("Author not found"),
+        Err(e) => client_error(&e).unwrap_or_else(|| {
+            eprintln!("Error while updating author: {:?}", e);
+            HttpResponse::InternalServerError().body("Failed to update the author")
+        }),
+    }
+}
+
+/// Route to delete an author no course uses, admin only
+#[delete("/authors/{id}")]
+async fn delete(
+    app_data: web::Data<crate::AppState>,
+    auth: AuthenticatedUser,
+    audit: AuditContext,
+    author_id: web::Path<String>,
+) -> impl Responder {
+    if !auth.has_scope(ApiScope::Admin) {
+        return HttpResponse::Forbidden().body("Admin access required");
+    }
+    let id = author_id.into_inner();
+    match app_data.service_manager.author_service.delete(&id, &audit).await {
+        Ok(result) if result.deleted_count > 0 => HttpResponse::Ok().json("Author deleted successfully"),
+        Ok(_) => HttpResponse::NotFound().body("Author not found"),
+        Err(e) => client_error(&e).unwrap_or_else(|| {
+            eprintln!("Error while deleting author: {:?}", e);
+            HttpResponse::InternalServerError().body("Failed to delete the author")
+        }),
+    }
+}
+
+/// Route to turn the author names of existing courses into references, merging spellings,
+/// `?dry_run=true` only reports the changes, admin only
+#[post("/authors/migrate")]
+async fn migrate(
+    app_data: web::Data<crate::AppState>,
+    auth: AuthenticatedUser,
+    audit: AuditContext,
+    params: web::Query<ReferenceMigrationParams>,
+) -> impl Responder {
+    if !auth.has_scope(ApiScope::Admin) {
+        return HttpResponse::Forbidden().body("Admin access required");
+    }
+    match app_data.service_manager.author_service.migrate(params.dry_run, &audit).await {
+        Ok(report) => HttpResponse::Ok().json(report),
+        Err(e) => {
+            eprintln!("Error while migrating authors: {:?}", e);
+            HttpResponse::InternalServerError().body("Failed to migrate authors")
+        }
+    }
+}
+
+pub fn init(cfg: &mut web::ServiceConfig) {
+    cfg.service(get_all);
+    cfg.service(get_by_id);
+    cfg.service(add);
+    cfg.service(update);
+    cfg.service(delete);
+    cfg.service(migrate);
+}
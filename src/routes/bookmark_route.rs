@@ -0,0 +1,242 @@
+use crate::extractors::auth_extractor::{check_user_access, AuthenticatedUser};
+use crate::models::bookmark_model::{
+    AddBookmarkRequest, BookmarkListRequest, BookmarkNoteRequest, ReorderBookmarksRequest,
+};
+use crate::services::bookmark_service::ApiServiceError;
+use actix_web::{delete, get, post, put, web, HttpResponse, Responder};
+use mongodb::bson::oid::ObjectId;
+
+// Helper function to map service errors to responses.
+fn error_response(e: ApiServiceError, action: &str) -> HttpResponse {
+    match e {
+        ApiServiceError::InvalidObjectId => HttpResponse::BadRequest().body("Invalid list id"),
+        ApiServiceError::DuplicateName => HttpResponse::Conflict().body("A list with this name already exists"),
+        ApiServiceError::AlreadyBookmarked => HttpResponse::Conflict().body("The course is already in the list"),
+        ApiServiceError::InvalidOrder => {
+            HttpResponse::BadRequest().body("course_ids must list every course of the list exactly once")
+        }
+        ApiServiceError::ListChanged => HttpResponse::Conflict().body("The list changed meanwhile, reload it and try again"),
+        e => {
+            eprintln!("Error while trying to {}: {:?}", action, e);
+            HttpResponse::InternalServerError().body(format!("Failed to {}", action))
+        }
+    }
+}
+
+/// Route to get the bookmark lists of a user, with the details of the saved courses
+#[get("/users/{id}/bookmarks")]
+async fn get_all(
+    app_data: web::Data<crate::AppState>,
+    auth: AuthenticatedUser,
+    user_id: web::Path<String>,
+) -> impl Responder {
+    let user_id = match check_user_access(&auth, &user_id) {
+        Ok(user_id) => user_id,
+        Err(response) => return *response,
+    };
+    match app_data.service_manager.bookmark_service.get_by_user(user_id).await {
+        Ok(lists) => HttpResponse::Ok().json(lists),
+        Err(e) => error_response(e, "retrieve the bookmark lists"),
+    }
+}
+
+/// Route to get one bookmark list of a user
+#[get("/users/{id}/bookmarks/{list_id}")]
+async fn get_by_id(
+    app_data: web::Data<crate::AppState>,
+    auth: AuthenticatedUser,
+    path: web::Path<(String, String)>,
+) -> impl Responder {
+    let (user_id, list_id) = path.into_inner();
+    let user_id = match check_user_access(&auth, &user_id) {
+        Ok(user_id) => user_id,
+        Err(response) => return *response,
+    };
+    match app_data.service_manager.bookmark_service.get_by_id(user_id, &list_id).await {
+        Ok(Some(list)) => HttpResponse::Ok().json(list),
+        Ok(None) => HttpResponse::NotFound().body("Bookmark list not found"),
+        Err(e) => error_response(e, "retrieve the bookmark list"),
+    }
+}
+
+/// Route to create a bookmark list
+#[post("/users/{id}/bookmarks")]
+async fn add(
+    app_data: web::Data<crate::AppState>,
+    auth: AuthenticatedUser,
+    user_id: web::Path<String>,
+    data: web::Json<BookmarkListRequest>,
+) -> impl Responder {
+    let user_id = match check_user_access(&auth, &user_id) {
+        Ok(user_id) => user_id,
+        Err(response) => return *response,
+    };
+    if data.name.trim().is_empty() {
+        return HttpResponse::BadRequest().body("name must not be empty");
+    }
+    match app_data.service_manager.bookmark_service.create(user_id, data.name.trim()).await {
+        Ok(result) => match result.inserted_id.as_object_id() {
+            Some(id) => HttpResponse::Ok().json(id.to_hex()),
+            None => HttpResponse::InternalServerError().body("Failed to extract inserted_id"),
+        },
+        Err(e) => error_response(e, "add the bookmark list"),
+    }
+}
+
+/// Route to rename a bookmark list
+#[put("/users/{id}/bookmarks/{list_id}")]
+async fn rename(
+    app_data: web::Data<crate::AppState>,
+    auth: AuthenticatedUser,
+    path: web::Path<(String, String)>,
+    data: web::Json<BookmarkListRequest>,
+) -> impl Responder {
+    let (user_id, list_id) = path.into_inner();
+    let user_id = match check_user_access(&auth, &user_id) {
+        Ok(user_id) => user_id,
+        Err(response) => return *response,
+    };
+    if data.name.trim().is_empty() {
+        return HttpResponse::BadRequest().body("name must not be empty");
+    }
+    match app_data.service_manager.bookmark_service.rename(user_id, &list_id, data.name.trim()).await {
+        Ok(result) if result.matched_count > 0 => HttpResponse::Ok().json("Bookmark list updated successfully"),
+        Ok(_) => HttpResponse::NotFound().body("Bookmark list not found"),
+        Err(e) => error_response(e, "update the bookmark list"),
+    }
+}
+
+/// Route to delete a bookmark list
+#[delete("/users/{id}/bookmarks/{list_id}")]
+async fn delete(
+    app_data: web::Data<crate::AppState>,
+    auth: AuthenticatedUser,
+    path: web::Path<(String, String)>,
+) -> impl Responder {
+    let (user_id, list_id) = path.into_inner();
+    let user_id = match check_user_access(&auth, &user_id) {
+        Ok(user_id) => user_id,
+        Err(response) => return *response,
+    };
+    match app_data.service_manager.bookmark_service.delete(user_id, &list_id).await {
+        Ok(result) if result.deleted_count > 0 => HttpResponse::Ok().json("Bookmark list deleted successfully"),
+        Ok(_) => HttpResponse::NotFound().body("Bookmark list not found"),
+        Err(e) => error_response(e, "delete the bookmark list"),
+    }
+}
+
+/// Route to save a course in a bookmark list
+#[post("/users/{id}/bookmarks/{list_id}/items")]
+async fn add_item(
+    app_data: web::Data<crate::AppState>,
+    auth: AuthenticatedUser,
+    path: web::Path<(String, String)>,
+    data: web::Json<AddBookmarkRequest>,
+) -> impl Responder {
+    let (user_id, list_id) = path.into_inner();
+    let user_id = match check_user_access(&auth, &user_id) {
+        Ok(user_id) => user_id,
+        Err(response) => return *response,
+    };
+    let services = &app_data.service_manager;
+    let data = data.into_inner();
+
+    match services.course_service.exists(data.course_id).await {
+        Ok(true) => {}
+        Ok(false) => return HttpResponse::BadRequest().body("Course not found"),
+        Err(e) => {
+            eprintln!("Error while checking course: {:?}", e);
+            return HttpResponse::InternalServerError().body("Failed to save the course");
+        }
+    }
+
+    match services
+        .bookmark_service
+        .add_item(user_id, &list_id, data.course_id, data.note, data.position)
+        .await
+    {
+        Ok(result) if result.matched_count > 0 => HttpResponse::Ok().json("Course saved successfully"),
+        Ok(_) => HttpResponse::NotFound().body("Bookmark list not found"),
+        Err(e) => error_response(e, "save the course"),
+    }
+}
+
+/// Route to change the note of a saved course
+#[put("/users/{id}/bookmarks/{list_id}/items/{course_id}")]
+async fn update_item(
+    app_data: web::Data<crate::AppState>,
+    auth: AuthenticatedUser,
+    path: web::Path<(String, String, String)>,
+    data: web::Json<BookmarkNoteRequest>,
+) -> impl Responder {
+    let (user_id, list_id, course_id) = path.into_inner();
+    let user_id = match check_user_access(&auth, &user_id) {
+        Ok(user_id) => user_id,
+        Err(response) => return *response,
+    };
+    let course_id = match ObjectId::parse_str(&course_id) {
+        Ok(course_id) => course_id,
+        Err(_) => return HttpResponse::BadRequest().body("Invalid course id"),
+    };
+    let note = data.into_inner().note;
+    match app_data.service_manager.bookmark_service.update_note(user_id, &list_id, course_id, note).await {
+        Ok(result) if result.matched_count > 0 => HttpResponse::Ok().json("Note updated successfully"),
+        Ok(_) => HttpResponse::NotFound().body("Saved course not found"),
+        Err(e) => error_response(e, "update the note"),
+    }
+}
+
+/// Route to remove a course from a bookmark list
+#[delete("/users/{id}/bookmarks/{list_id}/items/{course_id}")]
+async fn remove_item(
+    app_data: web::Data<crate::AppState>,
+    auth: AuthenticatedUser,
+    path: web::Path<(String, String, String)>,
+) -> impl Responder {
+    let (user_id, list_id, course_id) = path.into_inner();
+    let user_id = match check_user_access(&auth, &user_id) {
+        Ok(user_id) => user_id,
+        Err(response) => return *response,
+    };
+    let course_id = match ObjectId::parse_str(&course_id) {
+        Ok(course_id) => course_id,
+        Err(_) => return HttpResponse::BadRequest().body("Invalid course id"),
+    };
+    match app_data.service_manager.bookmark_service.remove_item(user_id, &list_id, course_id).await {
+        Ok(result) if result.matched_count > 0 => HttpResponse::Ok().json("Course removed successfully"),
+        Ok(_) => HttpResponse::NotFound().body("Saved course not found"),
+        Err(e) => error_response(e, "remove the course"),
+    }
+}
+
+/// Route to reorder the courses of a bookmark list
+#[put("/users/{id}/bookmarks/{list_id}/order")]
+async fn reorder(
+    app_data: web::Data<crate::AppState>,
+    auth: AuthenticatedUser,
+    path: web::Path<(String, String)>,
+    data: web::Json<ReorderBookmarksRequest>,
+) -> impl Responder {
+    let (user_id, list_id) = path.into_inner();
+    let user_id = match check_user_access(&auth, &user_id) {
+        Ok(user_id) => user_id,
+        Err(response) => return *response,
+    };
+    match app_data.service_manager.bookmark_service.reorder(user_id, &list_id, &data.course_ids).await {
+        Ok(Some(_)) => HttpResponse::Ok().json("Bookmark list reordered successfully"),
+        Ok(None) => HttpResponse::NotFound().body("Bookmark list not found"),
+        Err(e) => error_response(e, "reorder the bookmark list"),
+    }
+}
+
+pub fn init(cfg: &mut web::ServiceConfig) {
+    cfg.service(get_all);
+    cfg.service(get_by_id);
+    cfg.service(add);
+    cfg.service(rename);
+    cfg.service(delete);
+    cfg.service(add_item);
+    cfg.service(update_item);
+    cfg.service(remove_item);
+    cfg.service(reorder);
+}
@@ -0,0 +1,56 @@
+use crate::extractors::{audit_extractor::AuditContext, auth_extractor::AuthenticatedUser};
+use crate::models::{
+    api_key_model::ApiScope,
+    course_import_model::{ImportFormat, ImportParams},
+};
+use actix_web::{http::header, web, HttpRequest, HttpResponse, Responder};
+
+const IMPORT_MAX_BYTES: usize = 16 * 1024 * 1024;
+
+// Helper function to pick the format from the `Content-Type` header.
+fn format_from_content_type(req: &HttpRequest) -> Option<ImportFormat> {
+    let content_type = req.headers().get(header::CONTENT_TYPE)?.to_str().ok()?;
+    match content_type.split(';').next()?.trim() {
+        "text/csv" => Some(ImportFormat::Csv),
+        "application/x-ndjson" | "application/ndjson" | "application/jsonl" => Some(ImportFormat::Ndjson),
+        _ => None,
+    }
+}
+
+/// Route to import courses from a CSV or NDJSON body, upserted by `url`, admin only.
+/// `?dry_run=true` only validates the rows and reports what would happen.
+async fn import(
+    req: HttpRequest,
+    app_data: web::Data<crate::AppState>,
+    auth: AuthenticatedUser,
+    audit: AuditContext,
+    params: web::Query<ImportParams>,
+    body: web::Bytes,
+) -> impl Responder {
+    if !auth.has_scope(ApiScope::Admin) {
+        return HttpResponse::Forbidden().body("Admin access required");
+    }
+    let format = match params.format.or_else(|| format_from_content_type(&req)) {
+        Some(format) => format,
+        None => {
+            return HttpResponse::BadRequest()
+                .body("Unknown format, use ?format=csv|ndjson or a text/csv or application/x-ndjson body")
+        }
+    };
+
+    let report = app_data
+        .service_manager
+        .course_import_service
+        .import(format, &body, params.dry_run, &audit)
+        .await;
+    HttpResponse::Ok().json(report)
+}
+
+pub fn init(cfg: &mut web::ServiceConfig) {
+    // Import files are larger than the default payload limit
+    cfg.service(
+        web::resource("/courses/import")
+            .app_data(web::PayloadConfig::new(IMPORT_MAX_BYTES))
+            .route(web::post().to(import)),
+    );
+}
@@ -1,9 +1,29 @@
+use crate::extractors::{audit_extractor::AuditContext, auth_extractor::AuthenticatedUser};
+use crate::models::api_key_model::ApiScope;
 use crate::models::course_model::Course;
-use actix_web::{delete, get, post, put, web, HttpResponse, Responder};
+use crate::services::{course_service::ApiServiceError as CourseError, integrity_service::ApiServiceError as IntegrityError};
+use crate::utils::stream::{list_format, stream_list, ListFormat};
+use actix_web::{delete, get, post, put, web, HttpRequest, HttpResponse, Responder};
 
-/// Route to get all courses
+// Helper function to check whether the optional caller is an admin.
+fn is_admin(auth: &Option<AuthenticatedUser>) -> bool {
+    auth.as_ref().is_some_and(|auth| auth.has_scope(ApiScope::Admin))
+}
+
+/// Route to get all courses, streamed with `Accept: application/x-ndjson` or `application/json; stream=true`
 #[get("/courses")]
-async fn get_all(app_data: web::Data<crate::AppState>) -> impl Responder {
+async fn get_all(app_data: web::Data<crate::AppState>, req: HttpRequest) -> impl Responder {
+    let format = list_format(&req);
+    if format != ListFormat::Buffered {
+        return match app_data.service_manager.course_service.stream_all().await {
+            Ok(cursor) => stream_list(format, cursor),
+            Err(e) => {
+                eprintln!("Error while getting courses: {:?}", e);
+                HttpResponse::InternalServerError().body("Failed to retrieve courses")
+            }
+        };
+    }
+
     let result = app_data.service_manager.course_service.get_all().await;
     match result {
         Ok(courses) => HttpResponse::Ok().json(courses),
@@ -31,14 +51,33 @@ async fn get_by_id(
     }
 }
 
-/// Route to add a new course
+/// Route to add a new course. Only admins create the platforms and authors it names, other callers
+/// keep unknown names as they are.
 #[post("/courses")]
-async fn add(app_data: web::Data<crate::AppState>, data: web::Json<Course>) -> impl Responder {
-    match app_data.service_manager.course_service.create(&data).await {
+async fn add(
+    app_data: web::Data<crate::AppState>,
+    auth: Option<AuthenticatedUser>,
+    audit: AuditContext,
+    data: web::Json<Course>,
+) -> impl Responder {
+    // A course is never created deleted, rated or link checked
+    let mut course = data.into_inner();
+    course.deleted_at = None;
+    course.rating_average = 0.0;
+    course.rating_count = 0;
+    course.rating_sum = 0;
+    course.link_checks = vec![];
+    course.link_failures = 0;
+    course.link_broken_at = None;
+
+    let create_references = is_admin(&auth);
+    match app_data.service_manager.course_service.create(&course, create_references, &audit).await {
         Ok(result) => match result.inserted_id.as_object_id() {
             Some(id) => HttpResponse::Ok().json(id.to_hex()),
             None => HttpResponse::InternalServerError().body("Failed to extract inserted_id"),
         },
+        Err(CourseError::InvalidReference(message)) => HttpResponse::BadRequest().body(message),
+        Err(CourseError::DuplicateUrl) => HttpResponse::Conflict().body("A course with this url already exists"),
         Err(e) => {
             eprintln!("Error while adding course: {:?}", e);
             HttpResponse::InternalServerError().body("Failed to add the course")
@@ -46,15 +85,18 @@ async fn add(app_data: web::Data<crate::AppState>, data: web::Json<Course>) -> i
     }
 }
 
-/// Route to update an existing course by its MongoDB `_id`
+/// Route to update an existing course by its MongoDB `_id`, references are resolved like in `add`
 #[put("/courses/{id}")]
 async fn update(
     app_data: web::Data<crate::AppState>,
+    auth: Option<AuthenticatedUser>,
+    audit: AuditContext,
     data: web::Json<Course>,
     course_id: web::Path<String>,
 ) -> impl Responder {
     let id = course_id.into_inner(); // Extract `course_id` as a String
-    match app_data.service_manager.course_service.update(&data, &id).await {
+    let create_references = is_admin(&auth);
+    match app_data.service_manager.course_service.update(&data, &id, create_references, &audit).await {
         Ok(result) => {
             if result.modified_count > 0 {
                 HttpResponse::Ok().json("Course updated successfully")
@@ -62,6 +104,8 @@ async fn update(
                 HttpResponse::NotFound().body("Course not found or no changes made")
             }
         }
+        Err(CourseError::InvalidReference(message)) => HttpResponse::BadRequest().body(message),
+        Err(CourseError::DuplicateUrl) => HttpResponse::Conflict().body("A course with this url already exists"),
         Err(e) => {
             eprintln!("Error while updating course: {:?}", e);
             HttpResponse::InternalServerError().body("Failed to update the course")
@@ -73,12 +117,26 @@ async fn update(
 #[delete("/courses/{id}")]
 async fn delete(
     app_data: web::Data<crate::AppState>,
+    audit: AuditContext,
     course_id: web::Path<String>,
 ) -> impl Responder {
     let id = course_id.into_inner(); // Extract `course_id` as a String
-    match app_data.service_manager.course_service.delete(&id).await {
+
+    // Check the delete policy against the watched records first
+    match app_data.service_manager.integrity_service.before_course_delete(&id).await {
+        Ok(()) => {}
+        Err(IntegrityError::Referenced(count)) => {
+            return HttpResponse::Conflict().body(format!("Course is still referenced by {} watched records", count));
+        }
+        Err(e) => {
+            eprintln!("Error while applying the delete policy: {:?}", e);
+            return HttpResponse::InternalServerError().body("Failed to delete the course");
+        }
+    }
+
+    match app_data.service_manager.course_service.delete(&id, &audit).await {
         Ok(result) => {
-            if result.deleted_count > 0 {
+            if result.modified_count > 0 {
                 HttpResponse::Ok().json("Course deleted successfully")
             } else {
                 HttpResponse::NotFound().body("Course not found")
@@ -91,11 +149,106 @@ async fn delete(
     }
 }
 
+/// Route to list the soft deleted courses, admin only
+#[get("/courses/deleted")]
+async fn get_deleted(app_data: web::Data<crate::AppState>, auth: AuthenticatedUser) -> impl Responder {
+    if !auth.has_scope(ApiScope::Admin) {
+        return HttpResponse::Forbidden().body("Admin access required");
+    }
+    match app_data.service_manager.course_service.get_deleted(None).await {
+        Ok(courses) => HttpResponse::Ok().json(courses),
+        Err(e) => {
+            eprintln!("Error while getting deleted courses: {:?}", e);
+            HttpResponse::InternalServerError().body("Failed to retrieve deleted courses")
+        }
+    }
+}
+
+/// Route to list the courses whose link is broken, with their check history, admin only
+#[get("/courses/broken")]
+async fn get_broken(app_data: web::Data<crate::AppState>, auth: AuthenticatedUser) -> impl Responder {
+    if !auth.has_scope(ApiScope::Admin) {
+        return HttpResponse::Forbidden().body("Admin access required");
+    }
+    match app_data.service_manager.course_service.get_broken().await {
+        Ok(courses) => HttpResponse::Ok().json(courses),
+        Err(e) => {
+            eprintln!("Error while getting broken courses: {:?}", e);
+            HttpResponse::InternalServerError().body("Failed to retrieve broken courses")
+        }
+    }
+}
+
+/// Route to restore a soft deleted course by its MongoDB `_id`, admin only
+#[post("/courses/{id}/restore")]
+async fn restore(
+    app_data: web::Data<crate::AppState>,
+    auth: AuthenticatedUser,
+    audit: AuditContext,
+    course_id: web::Path<String>,
+) -> impl Responder {
+    if !auth.has_scope(ApiScope::Admin) {
+        return HttpResponse::Forbidden().body("Admin access required");
+    }
+    let id = course_id.into_inner();
+    match app_data.service_manager.course_service.restore(&id, &audit).await {
+        Ok(result) => {
+            if result.modified_count > 0 {
+                HttpResponse::Ok().json("Course restored successfully")
+            } else {
+                HttpResponse::NotFound().body("Deleted course not found")
+            }
+        }
+        Err(e) => {
+            eprintln!("Error while restoring course: {:?}", e);
+            HttpResponse::InternalServerError().body("Failed to restore the course")
+        }
+    }
+}
+
+/// Route to check the link of a course right away, e.g. after fixing it, admin only
+#[post("/courses/{id}/check-link")]
+async fn check_link(
+    app_data: web::Data<crate::AppState>,
+    auth: AuthenticatedUser,
+    course_id: web::Path<String>,
+) -> impl Responder {
+    if !auth.has_scope(ApiScope::Admin) {
+        return HttpResponse::Forbidden().body("Admin access required");
+    }
+    let id = course_id.into_inner();
+    let course = match app_data.service_manager.course_service.get_by_id(&id).await {
+        Ok(Some(course)) => course,
+        Ok(None) => return HttpResponse::NotFound().body("Course not found"),
+        Err(e) => {
+            eprintln!("Error while getting course: {:?}", e);
+            return HttpResponse::InternalServerError().body("Failed to check the course link");
+        }
+    };
+    let link_check_service = &app_data.service_manager.link_check_service;
+    if let Err(e) = link_check_service.check_course(&course).await {
+        eprintln!("Error while checking course link: {:?}", e);
+        return HttpResponse::InternalServerError().body("Failed to check the course link");
+    }
+    match app_data.service_manager.course_service.get_by_id(&id).await {
+        Ok(Some(course)) => HttpResponse::Ok().json(course),
+        Ok(None) => HttpResponse::NotFound().body("Course not found"),
+        Err(e) => {
+            eprintln!("Error while getting course: {:?}", e);
+            HttpResponse::InternalServerError().body("Failed to check the course link")
+        }
+    }
+}
+
 /// Initialize the routes for the application
 pub fn init(cfg: &mut web::ServiceConfig) {
     cfg.service(get_all);
+    cfg.service(get_deleted); // Before `get_by_id` so `deleted` is not taken for an id
+    cfg.service(get_broken);  // Same for `broken`
     cfg.service(get_by_id);
     cfg.service(add);
     cfg.service(update);
     cfg.service(delete);
+    cfg.service(restore);
+    cfg.service(check_link);
 }
@@ -1,39 +1,49 @@
-use crate::models::course_search_model::CourseSearchParams;
-use actix_web::{post, web, HttpResponse, Responder};
-use mongodb::bson::doc;
+use crate::models::course_search_model::{CourseSearchParams, CourseSearchQuery};
+use crate::services::course_search_service::{build_filter, build_sort, ApiServiceError};
+use crate::utils::stream::{list_format, stream_list, ListFormat};
+use actix_web::{get, post, web, HttpRequest, HttpResponse, Responder};
 
-#[post("/courses/search")]
-async fn search_courses(
-    app_data: web::Data<crate::AppState>,
-    body: web::Json<CourseSearchParams>,
-) -> impl Responder {
-    let mut filters = vec![];
-
-    // Add filters only if they are provided
-    if let Some(ref title) = body.title {
-        filters.push(doc! { "title": { "$regex": title, "$options": "i" } });
-    }
-    if let Some(ref author) = body.author {
-        filters.push(doc! { "author": { "$regex": author, "$options": "i" } });
-    }
-    if let Some(ref platform) = body.platform {
-        filters.push(doc! { "platform": { "$regex": platform, "$options": "i" } });
-    }
-    if let Some(ref topics) = body.topics {
-        // Ensure topics is an array of strings and match any topic
-        filters.push(doc! { "topics": { "$in": topics.clone() } });
+// Helper function to run a search from the body of a POST or the query string of a GET.
+async fn run_search(app_data: &crate::AppState, req: &HttpRequest, params: &CourseSearchParams) -> HttpResponse {
+    // Facets come with the hits in a single aggregation, they are never streamed
+    if params.facets {
+        return match app_data.service_manager.course_search_service.search_with_facets(params).await {
+            Ok(result) => HttpResponse::Ok().json(result),
+            Err(e @ ApiServiceError::InvalidSearch(_)) => HttpResponse::BadRequest().body(e.to_string()),
+            Err(e) => {
+                eprintln!("Error while searching courses: {:?}", e);
+                HttpResponse::InternalServerError().body("Failed to search courses")
+            }
+        };
     }
 
-    // Ensure at least one filter is provided
-    if filters.is_empty() {
-        return HttpResponse::BadRequest().body("At least one search parameter must be provided");
-    }
+    let expansion = match app_data.service_manager.course_search_service.topic_expansion(params).await {
+        Ok(expansion) => expansion,
+        Err(e) => {
+            eprintln!("Error while expanding topics: {:?}", e);
+            return HttpResponse::InternalServerError().body("Failed to search courses");
+        }
+    };
+    let filter_doc = match build_filter(params, &expansion) {
+        Ok(filter) => filter,
+        Err(e) => return HttpResponse::BadRequest().body(e.to_string()),
+    };
+    let sort = build_sort(params);
 
-    // Build the query using `$or` to combine conditions
-    let filter_doc = doc! { "$or": filters };
+    // Stream the results when the client asked for it
+    let format = list_format(req);
+    if format != ListFormat::Buffered {
+        return match app_data.service_manager.course_search_service.search_stream(filter_doc, sort).await {
+            Ok(cursor) => stream_list(format, cursor),
+            Err(e) => {
+                eprintln!("Error while searching courses: {:?}", e);
+                HttpResponse::InternalServerError().body("Failed to search courses")
+            }
+        };
+    }
 
     // Perform the search in the database
-    match app_data.service_manager.course_search_service.search(filter_doc).await {
+    match app_data.service_manager.course_search_service.search(filter_doc, sort).await {
         Ok(courses) => HttpResponse::Ok().json(courses),
         Err(e) => {
             eprintln!("Error while searching courses: {:?}", e);
@@ -42,6 +52,31 @@ async fn search_courses(
     }
 }
 
+/// Route to search courses, with nested `all`/`any`/`not` groups in the body.
+/// With `"facets": true` the response is `{ hits, total, facets }` instead of the list of courses,
+/// the hits paged with `skip` and `limit`.
+#[post("/courses/search")]
+async fn search_courses(
+    app_data: web::Data<crate::AppState>,
+    req: HttpRequest,
+    body: web::Json<CourseSearchParams>,
+) -> impl Responder {
+    run_search(&app_data, &req, &body).await
+}
+
+/// Route to search courses from the query string, so searches can be bookmarked,
+/// e.g. `/courses/search?topics=rust,async&topics_match=all&platform=udemy&match=all&sort=rating`
+#[get("/courses/search")]
+async fn search_courses_query(
+    app_data: web::Data<crate::AppState>,
+    req: HttpRequest,
+    query: web::Query<CourseSearchQuery>,
+) -> impl Responder {
+    let params = CourseSearchParams::from(query.into_inner());
+    run_search(&app_data, &req, &params).await
+}
+
 pub fn init(cfg: &mut web::ServiceConfig) {
     cfg.service(search_courses);
+    cfg.service(search_courses_query);
 }
\ No newline at end of file
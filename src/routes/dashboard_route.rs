@@ -0,0 +1,37 @@
+use crate::extractors::auth_extractor::AuthenticatedUser;
+use actix_web::{get, web, HttpResponse, Responder};
+
+/// Route to get the learner dashboard of a user, for the user themselves or an admin
+#[get("/users/{id}/dashboard")]
+async fn get_dashboard(
+    app_data: web::Data<crate::AppState>,
+    auth: AuthenticatedUser,
+    user_id: web::Path<String>,
+) -> impl Responder {
+    let id = user_id.into_inner();
+    if !auth.can_access_user(&id) {
+        return HttpResponse::Forbidden().body("Not allowed to access this user");
+    }
+    let services = &app_data.service_manager;
+
+    let user = match services.user_service.get_by_id(&id).await {
+        Ok(Some(user)) => user,
+        Ok(None) => return HttpResponse::NotFound().body("User not found"),
+        Err(e) => {
+            eprintln!("Error while getting user: {:?}", e);
+            return HttpResponse::InternalServerError().body("Failed to build the dashboard");
+        }
+    };
+
+    match services.dashboard_service.get(&user).await {
+        Ok(dashboard) => HttpResponse::Ok().json(dashboard),
+        Err(e) => {
+            eprintln!("Error while building dashboard: {:?}", e);
+            HttpResponse::InternalServerError().body("Failed to build the dashboard")
+        }
+    }
+}
+
+pub fn init(cfg: &mut web::ServiceConfig) {
+    cfg.service(get_dashboard);
+}
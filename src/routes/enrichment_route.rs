@@ -0,0 +1,75 @@
+use crate::extractors::{audit_extractor::AuditContext, auth_extractor::AuthenticatedUser};
+use crate::fetchers::FetchError;
+use crate::jobs::enrichment_job::spawn_enrichment;
+use crate::models::{
+    api_key_model::ApiScope,
+    enrichment_model::{EnrichRequest, EnrichmentJobParams},
+};
+use crate::services::enrichment_service::ApiServiceError;
+use actix_web::{get, post, web, HttpResponse, Responder};
+
+/// Route to get the metadata of a course page, to prefill a new course, admin only since the API
+/// fetches the page itself. Fetch failures are not detailed, they would tell which ports answer.
+#[post("/courses/enrich")]
+async fn enrich(
+    app_data: web::Data<crate::AppState>,
+    auth: AuthenticatedUser,
+    data: web::Json<EnrichRequest>,
+) -> impl Responder {
+    if !auth.has_scope(ApiScope::Admin) {
+        return HttpResponse::Forbidden().body("Admin access required");
+    }
+    match app_data.service_manager.enrichment_service.enrich(&data.url).await {
+        Ok(metadata) => HttpResponse::Ok().json(metadata),
+        Err(ApiServiceError::FetchError(FetchError::InvalidUrl(message))) => HttpResponse::BadRequest().body(message),
+        Err(ApiServiceError::FetchError(e)) => {
+            eprintln!("Error while fetching course page: {:?}", e);
+            HttpResponse::BadGateway().body("Failed to fetch the page")
+        }
+        Err(e) => {
+            eprintln!("Error while enriching course: {:?}", e);
+            HttpResponse::InternalServerError().body("Failed to enrich the course")
+        }
+    }
+}
+
+/// Route to start enriching every existing course in the background, admin only
+#[post("/courses/enrich/job")]
+async fn start_job(
+    app_data: web::Data<crate::AppState>,
+    auth: AuthenticatedUser,
+    audit: AuditContext,
+    params: web::Query<EnrichmentJobParams>,
+) -> impl Responder {
+    if !auth.has_scope(ApiScope::Admin) {
+        return HttpResponse::Forbidden().body("Admin access required");
+    }
+    let enrichment_service = &app_data.service_manager.enrichment_service;
+    match enrichment_service.start_job(params.overwrite) {
+        Ok(status) => {
+            // The job outlives the request, its changes are recorded as made by the admin who started it
+            spawn_enrichment(enrichment_service.clone(), params.overwrite, audit.with_actor(auth.user_id));
+            HttpResponse::Accepted().json(status)
+        }
+        Err(ApiServiceError::JobRunning) => HttpResponse::Conflict().json(enrichment_service.job_status()),
+        Err(e) => {
+            eprintln!("Error while starting the enrichment job: {:?}", e);
+            HttpResponse::InternalServerError().body("Failed to start the enrichment job")
+        }
+    }
+}
+
+/// Route to follow the progress of the last enrichment job, admin only
+#[get("/courses/enrich/job")]
+async fn job_status(app_data: web::Data<crate::AppState>, auth: AuthenticatedUser) -> impl Responder {
+    if !auth.has_scope(ApiScope::Admin) {
+        return HttpResponse::Forbidden().body("Admin access required");
+    }
+    HttpResponse::Ok().json(app_data.service_manager.enrichment_service.job_status())
+}
+
+pub fn init(cfg: &mut web::ServiceConfig) {
+    cfg.service(enrich);
+    cfg.service(start_job);
+    cfg.service(job_status);
+}
@@ -0,0 +1,83 @@
+use crate::extractors::auth_extractor::AuthenticatedUser;
+use crate::models::{api_key_model::ApiScope, export_model::{ExportFormat, ExportParams}};
+use crate::services::export_service::{ApiServiceError, ExportStream};
+use actix_web::{get, web, HttpResponse, Responder};
+use futures::stream::StreamExt;
+
+// Helper function to send an export as a streamed response, one encoded line at a time.
+fn stream_response(result: Result<ExportStream, ApiServiceError>, format: ExportFormat, name: &str) -> HttpResponse {
+    let stream = match result {
+        Ok(stream) => stream,
+        Err(e @ ApiServiceError::UnsupportedFormat(_)) => return HttpResponse::BadRequest().body(e.to_string()),
+        Err(e) => {
+            eprintln!("Error while starting {} export: {:?}", name, e);
+            return HttpResponse::InternalServerError().body(format!("Failed to export {}", name));
+        }
+    };
+    let (content_type, extension) = match format {
+        ExportFormat::Csv => ("text/csv; charset=utf-8", "csv"),
+        ExportFormat::Ndjson | ExportFormat::Xapi => ("application/x-ndjson", "ndjson"),
+    };
+
+    // Errors after the first line can only be logged, the response has already started
+    let owned_name = name.to_string();
+    let body = stream.map(move |line| {
+        line.map(web::Bytes::from).map_err(|e| {
+            eprintln!("Error while exporting {}: {:?}", owned_name, e);
+            e
+        })
+    });
+
+    HttpResponse::Ok()
+        .content_type(content_type)
+        .insert_header(("Content-Disposition", format!("attachment; filename=\"{}.{}\"", name, extension)))
+        .streaming(body)
+}
+
+/// Route to export all courses as CSV or NDJSON, admin only
+#[get("/export/courses")]
+async fn export_courses(
+    app_data: web::Data<crate::AppState>,
+    auth: AuthenticatedUser,
+    params: web::Query<ExportParams>,
+) -> impl Responder {
+    if !auth.has_scope(ApiScope::Admin) {
+        return HttpResponse::Forbidden().body("Admin access required");
+    }
+    let result = app_data.service_manager.export_service.courses(params.format).await;
+    stream_response(result, params.format, "courses")
+}
+
+/// Route to export all users as CSV or NDJSON, without password hashes, admin only
+#[get("/export/users")]
+async fn export_users(
+    app_data: web::Data<crate::AppState>,
+    auth: AuthenticatedUser,
+    params: web::Query<ExportParams>,
+) -> impl Responder {
+    if !auth.has_scope(ApiScope::Admin) {
+        return HttpResponse::Forbidden().body("Admin access required");
+    }
+    let result = app_data.service_manager.export_service.users(params.format).await;
+    stream_response(result, params.format, "users")
+}
+
+/// Route to export the watched records as CSV or NDJSON, or the completed ones as xAPI statements, admin only
+#[get("/export/watched")]
+async fn export_watched(
+    app_data: web::Data<crate::AppState>,
+    auth: AuthenticatedUser,
+    params: web::Query<ExportParams>,
+) -> impl Responder {
+    if !auth.has_scope(ApiScope::Admin) {
+        return HttpResponse::Forbidden().body("Admin access required");
+    }
+    let result = app_data.service_manager.export_service.watched(params.format).await;
+    stream_response(result, params.format, "watched")
+}
+
+pub fn init(cfg: &mut web::ServiceConfig) {
+    cfg.service(export_courses);
+    cfg.service(export_users);
+    cfg.service(export_watched);
+}
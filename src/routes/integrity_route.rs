@@ -0,0 +1,34 @@
+use crate::extractors::{audit_extractor::AuditContext, auth_extractor::AuthenticatedUser};
+use crate::models::api_key_model::ApiScope;
+use actix_web::{get, post, web, HttpResponse, Responder};
+
+// Helper function shared by the report and repair routes.
+async fn run_check(app_data: &crate::AppState, auth: &AuthenticatedUser, repair: bool, audit: &AuditContext) -> HttpResponse {
+    if !auth.has_scope(ApiScope::Admin) {
+        return HttpResponse::Forbidden().body("Admin access required");
+    }
+    match app_data.service_manager.integrity_service.check(repair, audit).await {
+        Ok(report) => HttpResponse::Ok().json(report),
+        Err(e) => {
+            eprintln!("Error while checking integrity: {:?}", e);
+            HttpResponse::InternalServerError().body("Failed to check integrity")
+        }
+    }
+}
+
+/// Route to report orphaned watched records and dangling `watched_ids`, admin only
+#[get("/integrity-check")]
+async fn check_integrity(app_data: web::Data<crate::AppState>, auth: AuthenticatedUser, audit: AuditContext) -> impl Responder {
+    run_check(&app_data, &auth, false, &audit).await
+}
+
+/// Route to archive orphaned watched records and remove dangling `watched_ids`, admin only
+#[post("/integrity-check/repair")]
+async fn repair_integrity(app_data: web::Data<crate::AppState>, auth: AuthenticatedUser, audit: AuditContext) -> impl Responder {
+    run_check(&app_data, &auth, true, &audit).await
+}
+
+pub fn init(cfg: &mut web::ServiceConfig) {
+    cfg.service(check_integrity);
+    cfg.service(repair_integrity);
+}
@@ -0,0 +1,211 @@
+use crate::extractors::{audit_extractor::AuditContext, auth_extractor::AuthenticatedUser};
+use crate::models::{api_key_model::ApiScope, learning_path_model::LearningPath};
+use crate::services::learning_path_service::ApiServiceError;
+use actix_web::{delete, get, post, put, web, HttpResponse, Responder};
+use mongodb::bson::oid::ObjectId;
+
+// Helper function to check that the courses and prerequisite paths of a learning path exist.
+// Returns the error response when they do not.
+async fn check_references(app_data: &crate::AppState, path: &LearningPath) -> Option<HttpResponse> {
+    let services = &app_data.service_manager;
+    for course in &path.courses {
+        match services.course_service.exists(course.course_id).await {
+            Ok(true) => {}
+            Ok(false) => return Some(HttpResponse::BadRequest().body(format!("Course {} not found", course.course_id))),
+            Err(e) => {
+                eprintln!("Error while checking course: {:?}", e);
+                return Some(HttpResponse::InternalServerError().body("Failed to check the courses"));
+            }
+        }
+    }
+    for prerequisite in &path.prerequisites {
+        match services.learning_path_service.exists(*prerequisite).await {
+            Ok(true) => {}
+            Ok(false) => return Some(HttpResponse::BadRequest().body(format!("Learning path {} not found", prerequisite))),
+            Err(e) => {
+                eprintln!("Error while checking learning path: {:?}", e);
+                return Some(HttpResponse::InternalServerError().body("Failed to check the prerequisites"));
+            }
+        }
+    }
+    None
+}
+
+/// Route to get all learning paths
+#[get("/learning-paths")]
+async fn get_all(app_data: web::Data<crate::AppState>) -> impl Responder {
+    let result = app_data.service_manager.learning_path_service.get_all().await;
+    match result {
+        Ok(paths) => HttpResponse::Ok().json(paths),
+        Err(e) => {
+            eprintln!("Error while getting learning paths: {:?}", e);
+            HttpResponse::InternalServerError().body("Failed to retrieve learning paths")
+        }
+    }
+}
+
+/// Route to get a learning path by its MongoDB `_id`
+#[get("/learning-paths/{id}")]
+async fn get_by_id(
+    app_data: web::Data<crate::AppState>,
+    path_id: web::Path<String>,
+) -> impl Responder {
+    let id = path_id.into_inner();
+    match app_data.service_manager.learning_path_service.get_by_id(&id).await {
+        Ok(Some(path)) => HttpResponse::Ok().json(path),
+        Ok(None) => HttpResponse::NotFound().body("Learning path not found"),
+        Err(e) => {
+            eprintln!("Error while getting learning path: {:?}", e);
+            HttpResponse::InternalServerError().body("Failed to retrieve the learning path")
+        }
+    }
+}
+
+/// Route to add a new learning path, admin only
+#[post("/learning-paths")]
+async fn add(
+    app_data: web::Data<crate::AppState>,
+    auth: AuthenticatedUser,
+    audit: AuditContext,
+    data: web::Json<LearningPath>,
+) -> impl Responder {
+    if !auth.has_scope(ApiScope::Admin) {
+        return HttpResponse::Forbidden().body("Admin access required");
+    }
+    if let Some(response) = check_references(&app_data, &data).await {
+        return response;
+    }
+    match app_data.service_manager.learning_path_service.create(&data, &audit).await {
+        Ok(result) => match result.inserted_id.as_object_id() {
+            Some(id) => HttpResponse::Ok().json(id.to_hex()),
+            None => HttpResponse::InternalServerError().body("Failed to extract inserted_id"),
+        },
+        Err(ApiServiceError::InvalidPath(message)) => HttpResponse::BadRequest().body(message),
+        Err(e) => {
+            eprintln!("Error while adding learning path: {:?}", e);
+            HttpResponse::InternalServerError().body("Failed to add the learning path")
+        }
+    }
+}
+
+/// Route to update an existing learning path by its MongoDB `_id`, admin only
+#[put("/learning-paths/{id}")]
+async fn update(
+    app_data: web::Data<crate::AppState>,
+    auth: AuthenticatedUser,
+    audit: AuditContext,
+    data: web::Json<LearningPath>,
+    path_id: web::Path<String>,
+) -> impl Responder {
+    if !auth.has_scope(ApiScope::Admin) {
+        return HttpResponse::Forbidden().body("Admin access required");
+    }
+    if let Some(response) = check_references(&app_data, &data).await {
+        return response;
+    }
+    let id = path_id.into_inner();
+    match app_data.service_manager.learning_path_service.update(&data, &id, &audit).await {
+        Ok(result) => {
+            if result.modified_count > 0 {
+                HttpResponse::Ok().json("Learning path updated successfully")
+            } else {
+                HttpResponse::NotFound().body("Learning path not found or no changes made")
+            }
+        }
+        Err(ApiServiceError::InvalidPath(message)) => HttpResponse::BadRequest().body(message),
+        Err(e) => {
+            eprintln!("Error while updating learning path: {:?}", e);
+            HttpResponse::InternalServerError().body("Failed to update the learning path")
+        }
+    }
+}
+
+/// Route to delete a learning path by its MongoDB `_id`, admin only
+#[delete("/learning-paths/{id}")]
+async fn delete(
+    app_data: web::Data<crate::AppState>,
+    auth: AuthenticatedUser,
+    audit: AuditContext,
+    path_id: web::Path<String>,
+) -> impl Responder {
+    if !auth.has_scope(ApiScope::Admin) {
+        return HttpResponse::Forbidden().body("Admin access required");
+    }
+    let id = path_id.into_inner();
+    match app_data.service_manager.learning_path_service.delete(&id, &audit).await {
+        Ok(result) => {
+            if result.deleted_count > 0 {
+                HttpResponse::Ok().json("Learning path deleted successfully")
+            } else {
+                HttpResponse::NotFound().body("Learning path not found")
+            }
+        }
+        Err(ApiServiceError::InvalidObjectId) => HttpResponse::BadRequest().body("Invalid learning path id"),
+        Err(ApiServiceError::InUse) => {
+            HttpResponse::Conflict().body("The learning path is a prerequisite of other paths, remove it from them first")
+        }
+        Err(e) => {
+            eprintln!("Error while deleting learning path: {:?}", e);
+            HttpResponse::InternalServerError().body("Failed to delete the learning path")
+        }
+    }
+}
+
+/// Route to get the progress of a user through a learning path, for the user themselves or an admin
+#[get("/users/{user_id}/learning-paths/{id}/progress")]
+async fn get_progress(
+    app_data: web::Data<crate::AppState>,
+    auth: AuthenticatedUser,
+    path: web::Path<(String, String)>,
+) -> impl Responder {
+    let (user_id, path_id) = path.into_inner();
+    if !auth.can_access_user(&user_id) {
+        return HttpResponse::Forbidden().body("Not allowed to access this user");
+    }
+    let services = &app_data.service_manager;
+
+    let user = match services.user_service.get_by_id(&user_id).await {
+        Ok(Some(user)) => user,
+        Ok(None) => return HttpResponse::NotFound().body("User not found"),
+        Err(e) => {
+            eprintln!("Error while getting user: {:?}", e);
+            return HttpResponse::InternalServerError().body("Failed to compute the progress");
+        }
+    };
+    let learning_path = match services.learning_path_service.get_by_id(&path_id).await {
+        Ok(Some(learning_path)) => learning_path,
+        Ok(None) => return HttpResponse::NotFound().body("Learning path not found"),
+        Err(e) => {
+            eprintln!("Error while getting learning path: {:?}", e);
+            return HttpResponse::InternalServerError().body("Failed to compute the progress");
+        }
+    };
+    let watched = match services.watched_service.get_by_user(&user).await {
+        Ok(watched) => watched,
+        Err(e) => {
+            eprintln!("Error while getting watched: {:?}", e);
+            return HttpResponse::InternalServerError().body("Failed to compute the progress");
+        }
+    };
+
+    let user_id: ObjectId = match user._id {
+        Some(id) => id,
+        None => return HttpResponse::InternalServerError().body("User has no _id"),
+    };
+    match services.learning_path_service.progress(&learning_path, user_id, &watched).await {
+        Ok(progress) => HttpResponse::Ok().json(progress),
+        Err(e) => {
+            eprintln!("Error while computing learning path progress: {:?}", e);
+            HttpResponse::InternalServerError().body("Failed to compute the progress")
+        }
+    }
+}
+
+pub fn init(cfg: &mut web::ServiceConfig) {
+    cfg.service(get_all);
+    cfg.service(get_by_id);
+    cfg.service(add);
+    cfg.service(update);
+    cfg.service(delete);
+    cfg.service(get_progress);
+}
@@ -0,0 +1,53 @@
+use crate::extractors::auth_extractor::AuthenticatedUser;
+use crate::models::mfa_model::MfaCodeRequest;
+use crate::services::mfa_service::ApiServiceError;
+use actix_web::{post, web, HttpResponse, Responder};
+
+/// Route to start a TOTP enrollment for the current user
+#[post("/auth/mfa/enroll")]
+async fn enroll(app_data: web::Data<crate::AppState>, auth: AuthenticatedUser) -> impl Responder {
+    // MFA is managed from an interactive session, a leaked key cannot replace the second factor
+    if auth.is_api_key() {
+        return HttpResponse::Forbidden().body("MFA cannot be managed with an API key");
+    }
+    match app_data.service_manager.mfa_service.enroll(&auth.user, auth.user_id).await {
+        Ok((secret, provisioning_uri)) => HttpResponse::Ok().json(serde_json::json!({
+            "secret": secret,
+            "provisioning_uri": provisioning_uri,
+        })),
+        Err(ApiServiceError::AlreadyEnabled) => HttpResponse::Conflict().body("MFA is already enabled"),
+        Err(e) => {
+            eprintln!("Error while enrolling MFA: {:?}", e);
+            HttpResponse::InternalServerError().body("Failed to start MFA enrollment")
+        }
+    }
+}
+
+/// Route to confirm the enrollment with a first code, returns the recovery codes once
+#[post("/auth/mfa/confirm")]
+async fn confirm(
+    app_data: web::Data<crate::AppState>,
+    auth: AuthenticatedUser,
+    body: web::Json<MfaCodeRequest>,
+) -> impl Responder {
+    if auth.is_api_key() {
+        return HttpResponse::Forbidden().body("MFA cannot be managed with an API key");
+    }
+    match app_data.service_manager.mfa_service.confirm(auth.user_id, &body.code).await {
+        Ok(recovery_codes) => HttpResponse::Ok().json(serde_json::json!({
+            "message": "MFA enabled",
+            "recovery_codes": recovery_codes,
+        })),
+        Err(ApiServiceError::NotEnrolled) => HttpResponse::NotFound().body("No pending MFA enrollment"),
+        Err(ApiServiceError::InvalidCode) => HttpResponse::BadRequest().body("Invalid MFA code"),
+        Err(e) => {
+            eprintln!("Error while confirming MFA: {:?}", e);
+            HttpResponse::InternalServerError().body("Failed to confirm MFA enrollment")
+        }
+    }
+}
+
+pub fn init(cfg: &mut web::ServiceConfig) {
+    cfg.service(enroll);
+    cfg.service(confirm);
+}
@@ -0,0 +1,175 @@
+//! A minimal OpenID Connect provider served under `/mock-idp`, so the single sign-on flow
+//! can be tested without a campus identity provider. Every authorization request is
+//! approved immediately for the `login_hint` email address, so it is only compiled for
+//! tests and never part of the server.
+
+use crate::utils::token::generate_token;
+use actix_web::{get, http::header, post, web, HttpRequest, HttpResponse, Responder};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use jsonwebtoken::{encode, EncodingKey, Header};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::{collections::HashMap, sync::Mutex};
+
+const DEFAULT_EMAIL: &str = "student@campus.test";
+const ID_TOKEN_TTL_SECONDS: i64 = 300;
+
+// An issued authorization code waiting to be redeemed
+struct PendingCode {
+    client_id: String,
+    redirect_uri: String,
+    code_challenge: String,
+    nonce: Option<String>,
+    email: String,
+}
+
+/// Shared state of the mock provider. Create it once per test server so every worker sees the same codes.
+pub struct MockIdp {
+    client_secret: String,
+    codes: Mutex<HashMap<String, PendingCode>>,
+}
+
+impl MockIdp {
+    pub fn new(client_secret: String) -> MockIdp {
+        MockIdp { client_secret, codes: Mutex::new(HashMap::new()) }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct AuthorizeParams {
+    response_type: String,
+    client_id: String,
+    redirect_uri: String,
+    state: Option<String>,
+    nonce: Option<String>,
+    code_challenge: Option<String>,
+    code_challenge_method: Option<String>,
+    login_hint: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TokenForm {
+    grant_type: String,
+    code: String,
+    redirect_uri: String,
+    client_id: String,
+    client_secret: Option<String>,
+    code_verifier: String,
+}
+
+// Helper function to build the issuer URL from the request, e.g. `http://localhost:8080/mock-idp`.
+fn issuer(req: &HttpRequest) -> String {
+    let info = req.connection_info();
+    format!("{}://{}/mock-idp", info.scheme(), info.host())
+}
+
+#[get("/mock-idp/.well-known/openid-configuration")]
+async fn discovery(req: HttpRequest) -> impl Responder {
+    let issuer = issuer(&req);
+    HttpResponse::Ok().json(serde_json::json!({
+        "issuer": issuer,
+        "authorization_endpoint": format!("{}/authorize", issuer),
+        "token_endpoint": format!("{}/token", issuer),
+        "jwks_uri": format!("{}/jwks", issuer),
+        "response_types_supported": ["code"],
+        "subject_types_supported": ["public"],
+        "id_token_signing_alg_values_supported": ["HS256"],
+        "code_challenge_methods_supported": ["S256"],
+    }))
+}
+
+#[get("/mock-idp/authorize")]
+async fn authorize(mock: web::Data<MockIdp>, params: web::Query<AuthorizeParams>) -> impl Responder {
+    let params = params.into_inner();
+    if params.response_type != "code" {
+        return HttpResponse::BadRequest().body("Only the authorization code flow is supported");
+    }
+    let code_challenge = match (params.code_challenge, params.code_challenge_method.as_deref()) {
+        (Some(challenge), Some("S256")) => challenge,
+        _ => return HttpResponse::BadRequest().body("PKCE with S256 is required"),
+    };
+
+    let code = generate_token(32);
+    mock.codes.lock().unwrap().insert(
+        code.clone(),
+        PendingCode {
+            client_id: params.client_id,
+            redirect_uri: params.redirect_uri.clone(),
+            code_challenge,
+            nonce: params.nonce,
+            email: params.login_hint.unwrap_or_else(|| DEFAULT_EMAIL.to_string()),
+        },
+    );
+
+    let mut query = vec![("code", code)];
+    if let Some(state) = params.state {
+        query.push(("state", state));
+    }
+    match url::Url::parse_with_params(&params.redirect_uri, &query) {
+        Ok(location) => HttpResponse::Found().insert_header((header::LOCATION, location.to_string())).finish(),
+        Err(_) => HttpResponse::BadRequest().body("Invalid redirect_uri"),
+    }
+}
+
+#[post("/mock-idp/token")]
+async fn token(req: HttpRequest, mock: web::Data<MockIdp>, form: web::Form<TokenForm>) -> impl Responder {
+    let invalid_grant = || HttpResponse::BadRequest().json(serde_json::json!({ "error": "invalid_grant" }));
+
+    if form.grant_type != "authorization_code" {
+        return HttpResponse::BadRequest().json(serde_json::json!({ "error": "unsupported_grant_type" }));
+    }
+    if form.client_secret.as_deref() != Some(mock.client_secret.as_str()) {
+        return HttpResponse::Unauthorized().json(serde_json::json!({ "error": "invalid_client" }));
+    }
+
+    // Codes are single use
+    let pending = match mock.codes.lock().unwrap().remove(&form.code) {
+        Some(pending) => pending,
+        None => return invalid_grant(),
+    };
+    let challenge = URL_SAFE_NO_PAD.encode(Sha256::digest(form.code_verifier.as_bytes()));
+    if pending.client_id != form.client_id || pending.redirect_uri != form.redirect_uri || pending.code_challenge != challenge {
+        return invalid_grant();
+    }
+
+    let now = chrono::Utc::now().timestamp();
+    let given_name = pending.email.split('@').next().unwrap_or_default().to_string();
+    let claims = serde_json::json!({
+        "iss": issuer(&req),
+        "aud": pending.client_id,
+        "sub": format!("mock|{}", pending.email),
+        "iat": now,
+        "exp": now + ID_TOKEN_TTL_SECONDS,
+        "nonce": pending.nonce,
+        "email": pending.email,
+        "email_verified": true,
+        "given_name": given_name,
+        "family_name": "Mock",
+    });
+
+    match encode(&Header::default(), &claims, &EncodingKey::from_secret(mock.client_secret.as_bytes())) {
+        Ok(id_token) => HttpResponse::Ok().json(serde_json::json!({
+            "access_token": generate_token(32),
+            "token_type": "Bearer",
+            "expires_in": ID_TOKEN_TTL_SECONDS,
+            "id_token": id_token,
+        })),
+        Err(e) => {
+            eprintln!("Error while signing mock ID token: {:?}", e);
+            HttpResponse::InternalServerError().body("Failed to sign the ID token")
+        }
+    }
+}
+
+#[get("/mock-idp/jwks")]
+async fn jwks() -> impl Responder {
+    // Tokens are HMAC-signed with the client secret, so there are no public keys to publish
+    HttpResponse::Ok().json(serde_json::json!({ "keys": [] }))
+}
+
+pub fn init(cfg: &mut web::ServiceConfig) {
+    cfg.service(discovery);
+    cfg.service(authorize);
+    cfg.service(token);
+    cfg.service(jwks);
+}
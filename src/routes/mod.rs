@@ -1,7 +1,26 @@
+pub mod api_key_route;
+pub mod audit_route;
 pub mod auth_route;
+pub mod author_route;
+pub mod bookmark_route;
+pub mod course_import_route;
 pub mod course_route;
 pub mod course_search_route;
+pub mod dashboard_route;
+pub mod enrichment_route;
+pub mod export_route;
 pub mod health_route;
+pub mod integrity_route;
+pub mod learning_path_route;
+pub mod mfa_route;
+#[cfg(test)]
+pub mod mock_idp_route;
+pub mod oidc_route;
+pub mod platform_route;
+pub mod recommendation_route;
+pub mod review_route;
+pub mod suggest_route;
+pub mod topic_route;
 pub mod user_route;
 pub mod user_search_route;
 pub mod watched_route;
\ No newline at end of file
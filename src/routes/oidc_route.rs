@@ -0,0 +1,171 @@
+use crate::extractors::audit_extractor::AuditContext;
+use crate::models::{oidc_model::{IdTokenClaims, OidcCallbackParams}, user_model::{User, UserRole}};
+use crate::routes::auth_route::complete_login;
+use crate::services::oidc_service::ApiServiceError;
+use crate::utils::token::generate_token;
+use crate::AppState;
+use actix_web::{
+    cookie::{time::Duration as CookieDuration, Cookie, SameSite},
+    get,
+    http::header,
+    web, HttpRequest, HttpResponse, Responder,
+};
+use bson::DateTime;
+use serde::Deserialize;
+
+// Cookie holding the state of the login started by the browser
+const STATE_COOKIE: &str = "oidc_state";
+
+#[derive(Debug, Deserialize)]
+struct LoginParams {
+    login_hint: Option<String>, // Forwarded to the provider to prefill the username
+}
+
+// Helper function to map service errors to responses.
+fn error_response(e: ApiServiceError) -> HttpResponse {
+    match e {
+        ApiServiceError::NotConfigured => HttpResponse::NotFound().body("Single sign-on is not configured"),
+        ApiServiceError::InvalidState => HttpResponse::BadRequest().body("Invalid or expired login state"),
+        ApiServiceError::InvalidIdToken(_) | ApiServiceError::ProviderError(_) => {
+            eprintln!("Error while completing single sign-on: {:?}", e);
+            HttpResponse::Unauthorized().body("Single sign-on failed")
+        }
+        e => {
+            eprintln!("Error while completing single sign-on: {:?}", e);
+            HttpResponse::InternalServerError().body("Single sign-on failed")
+        }
+    }
+}
+
+// Helper function to find the user for an external identity, linking or creating it on first login.
+async fn resolve_user(app_data: &AppState, claims: &IdTokenClaims, audit: &AuditContext) -> Result<User, HttpResponse> {
+    let services = &app_data.service_manager;
+    let internal_error = |e: &dyn std::fmt::Debug| {
+        eprintln!("Error while resolving single sign-on user: {:?}", e);
+        HttpResponse::InternalServerError().body("Single sign-on failed")
+    };
+
+    // Already linked
+    if let Some(user_id) = services.oidc_service.find_linked_user(&claims.sub).await.map_err(error_response)? {
+        return match services.user_service.get_by_id(&user_id.to_hex()).await {
+            Ok(Some(user)) => Ok(user),
+            Ok(None) => Err(HttpResponse::Unauthorized().body("The linked account no longer exists")),
+            Err(e) => Err(internal_error(&e)),
+        };
+    }
+
+    let email = claims
+        .email
+        .clone()
+        .ok_or_else(|| HttpResponse::BadRequest().body("The identity provider did not return an email address"))?;
+
+    // Existing account with the same email, only linked when both the provider and the account
+    // verified the address. Otherwise whoever registered the address first would get the identity.
+    if let Some(user) = services.user_service.get_by_email(&email).await.map_err(|e| internal_error(&e))? {
+        if claims.email_verified != Some(true) || !user.email_verified {
+            return Err(HttpResponse::Conflict().body(
+                "An account with this email already exists, sign in with its password and verify the address first",
+            ));
+        }
+        let user_id = user._id.ok_or_else(|| HttpResponse::InternalServerError().body("User has no _id"))?;
+        services.oidc_service.link(user_id, claims).await.map_err(error_response)?;
+        return Ok(user);
+    }
+
+    // First login, create the account. It gets an unusable random password and
+    // counts as verified because the provider authenticated the user.
+    let password = bcrypt::hash(generate_token(48), bcrypt::DEFAULT_COST).map_err(|e| internal_error(&e))?;
+    let mut user = User {
+        _id: None,
+        name: claims.given_name.clone().or_else(|| claims.name.clone()).unwrap_or_default(),
+        lastname: claims.family_name.clone().unwrap_or_default(),
+        major: String::new(),
+        email,
+        password,
+        watched_ids: None,
+        email_verified: true,
+        role: UserRole::Learner,
+        created_at: DateTime::now(),
+        updated_at: DateTime::now(),
+        deleted_at: None,
+    };
+    let result = services.user_service.create(&user, audit).await.map_err(|e| internal_error(&e))?;
+    let user_id = result
+        .inserted_id
+        .as_object_id()
+        .ok_or_else(|| HttpResponse::InternalServerError().body("Failed to extract inserted_id as ObjectId"))?;
+    services.oidc_service.link(user_id, claims).await.map_err(error_response)?;
+    user._id = Some(user_id);
+
+    Ok(user)
+}
+
+// Helper function to build the cookie binding a login state to the browser that started it.
+// An empty value with no lifetime removes it.
+fn state_cookie(req: &HttpRequest, state: &str, max_age: CookieDuration) -> Cookie<'static> {
+    Cookie::build(STATE_COOKIE, state.to_string())
+        .path("/auth/oidc")
+        .http_only(true)
+        .secure(req.connection_info().scheme() == "https")
+        .same_site(SameSite::Lax) // Sent on the top-level redirect back from the provider
+        .max_age(max_age)
+        .finish()
+}
+
+/// Route to start a single sign-on login, redirects to the identity provider
+#[get("/auth/oidc/login")]
+async fn login(app_data: web::Data<AppState>, req: HttpRequest, params: web::Query<LoginParams>) -> impl Responder {
+    let oidc_service = &app_data.service_manager.oidc_service;
+    match oidc_service.authorization_url(params.login_hint.as_deref()).await {
+        Ok((url, state)) => {
+            let max_age = CookieDuration::seconds(oidc_service.login_state_ttl().num_seconds());
+            HttpResponse::Found()
+                .insert_header((header::LOCATION, url))
+                .cookie(state_cookie(&req, &state, max_age))
+                .finish()
+        }
+        Err(e) => error_response(e),
+    }
+}
+
+/// Route the identity provider redirects back to with the authorization code
+#[get("/auth/oidc/callback")]
+async fn callback(
+    app_data: web::Data<AppState>,
+    req: HttpRequest,
+    audit: AuditContext,
+    params: web::Query<OidcCallbackParams>,
+) -> impl Responder {
+    if let Some(ref error) = params.error {
+        return HttpResponse::Unauthorized().body(format!("Identity provider returned an error: {}", error));
+    }
+    let (code, state) = match (&params.code, &params.state) {
+        (Some(code), Some(state)) => (code, state),
+        _ => return HttpResponse::BadRequest().body("Missing code or state"),
+    };
+    // The state must come back to the browser that started the login, or an attacker could log
+    // the victim into the attacker's account with their own callback URL
+    if req.cookie(STATE_COOKIE).is_none_or(|cookie| cookie.value() != state.as_str()) {
+        return HttpResponse::BadRequest().body("Invalid or expired login state");
+    }
+
+    let claims = match app_data.service_manager.oidc_service.authenticate(code, state).await {
+        Ok(claims) => claims,
+        Err(e) => return error_response(e),
+    };
+
+    let mut response = match resolve_user(&app_data, &claims, &audit).await {
+        Ok(user) => complete_login(&app_data, user, &audit).await,
+        Err(response) => response,
+    };
+    // The state is single use
+    if let Err(e) = response.add_removal_cookie(&state_cookie(&req, "", CookieDuration::ZERO)) {
+        eprintln!("Error while removing the login state cookie: {:?}", e);
+    }
+    response
+}
+
+pub fn init(cfg: &mut web::ServiceConfig) {
+    cfg.service(login);
+    cfg.service(callback);
+}
@@ -0,0 +1,142 @@
+use crate::extractors::{audit_extractor::AuditContext, auth_extractor::AuthenticatedUser};
+use crate::models::{api_key_model::ApiScope, course_model::ReferenceMigrationParams, platform_model::PlatformRequest};
+use crate::services::reference_service::ApiServiceError;
+use actix_web::{delete, get, post, put, web, HttpResponse, Responder};
+
+// Helper function to map validation errors to responses, `None` for unexpected errors.
+fn client_error(e: &ApiServiceError) -> Option<HttpResponse> {
+    match e {
+        ApiServiceError::InvalidObjectId => Some(HttpResponse::BadRequest().body(e.to_string())),
+        ApiServiceError::InvalidRequest(message) => Some(HttpResponse::BadRequest().body(message.clone())),
+        ApiServiceError::Conflict(message) => Some(HttpResponse::Conflict().body(message.clone())),
+        ApiServiceError::InUse => Some(HttpResponse::Conflict().body("The platform is still used by courses")),
+        ApiServiceError::DatabaseError(_) => None,
+    }
+}
+
+/// Route to get all platforms
+#[get("/platforms")]
+async fn get_all(app_data: web::Data<crate::AppState>) -> impl Responder {
+    match app_data.service_manager.platform_service.get_all().await {
+        Ok(platforms) => HttpResponse::Ok().json(platforms),
+        Err(e) => {
+            eprintln!("Error while getting platforms: {:?}", e);
+            HttpResponse::InternalServerError().body("Failed to retrieve platforms")
+        }
+    }
+}
+
+/// Route to get a platform by its MongoDB `_id`
+#[get("/platforms/{id}")]
+async fn get_by_id(
+    app_data: web::Data<crate::AppState>,
+    platform_id: web::Path<String>,
+) -> impl Responder {
+    let id = platform_id.into_inner();
+    match app_data.service_manager.platform_service.get_by_id(&id).await {
+        Ok(Some(platform)) => HttpResponse::Ok().json(platform),
+        Ok(None) => HttpResponse::NotFound().body("Platform not found"),
+        Err(e) => {
+            eprintln!("Error while getting platform: {:?}", e);
+            HttpResponse::InternalServerError().body("Failed to retrieve the platform")
+        }
+    }
+}
+
+/// Route to add a new platform, admin only
+#[post("/platforms")]
+async fn add(
+    app_data: web::Data<crate::AppState>,
+    auth: AuthenticatedUser,
+    audit: AuditContext,
+    data: web::Json<PlatformRequest>,
+) -> impl Responder {
+    if !auth.has_scope(ApiScope::Admin) {
+        return HttpResponse::Forbidden().body("Admin access required");
+    }
+    match app_data.service_manager.platform_service.create(&data, &audit).await {
+        Ok(result) => match result.inserted_id.as_object_id() {
+            Some(id) => HttpResponse::Ok().json(id.to_hex()),
+            None => HttpResponse::InternalServerError().body("Failed to extract inserted_id"),
+        },
+        Err(e) => client_error(&e).unwrap_or_else(|| {
+            eprintln!("Error while adding platform: {:?}", e);
+            HttpResponse::InternalServerError().body("Failed to add the platform")
+        }),
+    }
+}
+
+/// Route to update a platform, a new name is copied onto its courses, admin only
+#[put("/platforms/{id}")]
+async fn update(
+    app_data: web::Data<crate::AppState>,
+    auth: AuthenticatedUser,
+    audit: AuditContext,
+    data: web::Json<PlatformRequest>,
+    platform_id: web::Path<String>,
+) -> impl Responder {
+    if !auth.has_scope(ApiScope::Admin) {
+        return HttpResponse::Forbidden().body("Admin access required");
+    }
+    let id = platform_id.into_inner();
+    match app_data.service_manager.platform_service.update(&data, &id, &audit).await {
+        Ok(Some(_)) => HttpResponse::Ok().json("Platform updated successfully"),
+        Ok(None) => HttpResponse::NotFound().body("Platform not found"),
+        Err(e) => client_error(&e).unwrap_or_else(|| {
+            eprintln!("Error while updating platform: {:?}", e);
+            HttpResponse::InternalServerError().body("Failed to update the platform")
+        }),
+    }
+}
+
+/// Route to delete a platform no course uses, admin only
+#[delete("/platforms/{id}")]
+async fn delete(
+    app_data: web::Data<crate::AppState>,
+    auth: AuthenticatedUser,
+    audit: AuditContext,
+    platform_id: web::Path<String>,
+) -> impl Responder {
+    if !auth.has_scope(ApiScope::Admin) {
+        return HttpResponse::Forbidden().body("Admin access required");
+    }
+    let id = platform_id.into_inner();
+    match app_data.service_manager.platform_service.delete(&id, &audit).await {
+        Ok(result) if result.deleted_count > 0 => HttpResponse::Ok().json("Platform deleted successfully"),
+        Ok(_) => HttpResponse::NotFound().body("Platform not found"),
+        Err(e) => client_error(&e).unwrap_or_else(|| {
+            eprintln!("Error while deleting platform: {:?}", e);
+            HttpResponse::InternalServerError().body("Failed to delete the platform")
+        }),
+    }
+}
+
+/// Route to turn the platform names of existing courses into references, merging spellings,
+/// `?dry_run=true` only reports the changes, admin only
+#[post("/platforms/migrate")]
+async fn migrate(
+    app_data: web::Data<crate::AppState>,
+    auth: AuthenticatedUser,
+    audit: AuditContext,
+    params: web::Query<ReferenceMigrationParams>,
+) -> impl Responder {
+    if !auth.has_scope(ApiScope::Admin) {
+        return HttpResponse::Forbidden().body("Admin access required");
+    }
+    match app_data.service_manager.platform_service.migrate(params.dry_run, &audit).await {
+        Ok(report) => HttpResponse::Ok().json(report),
+        Err(e) => {
+            eprintln!("Error while migrating platforms: {:?}", e);
+            HttpResponse::InternalServerError().body("Failed to migrate platforms")
+        }
+    }
+}
+
+pub fn init(cfg: &mut web::ServiceConfig) {
+    cfg.service(get_all);
+    cfg.service(get_by_id);
+    cfg.service(add);
+    cfg.service(update);
+    cfg.service(delete);
+    cfg.service(migrate);
+}
@@ -0,0 +1,94 @@
+use crate::extractors::auth_extractor::{check_user_access, AuthenticatedUser};
+use crate::models::recommendation_model::{DismissRecommendationRequest, RecommendationParams};
+use actix_web::{delete, get, post, web, HttpResponse, Responder};
+use mongodb::bson::oid::ObjectId;
+
+const DEFAULT_LIMIT: usize = 10;
+const MAX_LIMIT: usize = 100;
+
+/// Route to get the courses suggested to a user, `?limit=` and `?explain=true` to include the reasons
+#[get("/users/{id}/recommendations")]
+async fn get_recommendations(
+    app_data: web::Data<crate::AppState>,
+    auth: AuthenticatedUser,
+    user_id: web::Path<String>,
+    params: web::Query<RecommendationParams>,
+) -> impl Responder {
+    let id = user_id.into_inner();
+    if let Err(response) = check_user_access(&auth, &id) {
+        return *response;
+    }
+    let services = &app_data.service_manager;
+
+    let user = match services.user_service.get_by_id(&id).await {
+        Ok(Some(user)) => user,
+        Ok(None) => return HttpResponse::NotFound().body("User not found"),
+        Err(e) => {
+            eprintln!("Error while getting user: {:?}", e);
+            return HttpResponse::InternalServerError().body("Failed to compute recommendations");
+        }
+    };
+
+    let limit = params.limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT);
+    let explain = params.explain.unwrap_or(false);
+    match services.recommendation_service.recommend(&user, limit, explain).await {
+        Ok(recommendations) => HttpResponse::Ok().json(recommendations),
+        Err(e) => {
+            eprintln!("Error while computing recommendations: {:?}", e);
+            HttpResponse::InternalServerError().body("Failed to compute recommendations")
+        }
+    }
+}
+
+/// Route to stop suggesting a course to a user
+#[post("/users/{id}/recommendations/dismissed")]
+async fn dismiss(
+    app_data: web::Data<crate::AppState>,
+    auth: AuthenticatedUser,
+    user_id: web::Path<String>,
+    data: web::Json<DismissRecommendationRequest>,
+) -> impl Responder {
+    let user_id = match check_user_access(&auth, &user_id) {
+        Ok(user_id) => user_id,
+        Err(response) => return *response,
+    };
+    match app_data.service_manager.recommendation_service.dismiss(user_id, data.course_id).await {
+        Ok(_) => HttpResponse::Ok().json("Recommendation dismissed"),
+        Err(e) => {
+            eprintln!("Error while dismissing recommendation: {:?}", e);
+            HttpResponse::InternalServerError().body("Failed to dismiss the recommendation")
+        }
+    }
+}
+
+/// Route to allow a dismissed course to be suggested again
+#[delete("/users/{id}/recommendations/dismissed/{course_id}")]
+async fn undismiss(
+    app_data: web::Data<crate::AppState>,
+    auth: AuthenticatedUser,
+    path: web::Path<(String, String)>,
+) -> impl Responder {
+    let (user_id, course_id) = path.into_inner();
+    let user_id = match check_user_access(&auth, &user_id) {
+        Ok(user_id) => user_id,
+        Err(response) => return *response,
+    };
+    let course_id = match ObjectId::parse_str(&course_id) {
+        Ok(course_id) => course_id,
+        Err(_) => return HttpResponse::BadRequest().body("Invalid course id"),
+    };
+    match app_data.service_manager.recommendation_service.undismiss(user_id, course_id).await {
+        Ok(result) if result.deleted_count > 0 => HttpResponse::Ok().json("Recommendation restored"),
+        Ok(_) => HttpResponse::NotFound().body("Course was not dismissed"),
+        Err(e) => {
+            eprintln!("Error while restoring recommendation: {:?}", e);
+            HttpResponse::InternalServerError().body("Failed to restore the recommendation")
+        }
+    }
+}
+
+pub fn init(cfg: &mut web::ServiceConfig) {
+    cfg.service(get_recommendations);
+    cfg.service(dismiss);
+    cfg.service(undismiss);
+}
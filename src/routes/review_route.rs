@@ -0,0 +1,203 @@
+use crate::extractors::{audit_extractor::AuditContext, auth_extractor::AuthenticatedUser};
+use crate::models::{
+    api_key_model::ApiScope,
+    review_model::{FlagReviewRequest, ReviewListParams, ReviewRequest},
+};
+use crate::services::review_service::ApiServiceError;
+use actix_web::{delete, get, post, web, HttpResponse, Responder};
+use mongodb::bson::oid::ObjectId;
+
+// Helper function to map service errors to responses.
+fn error_response(e: ApiServiceError, action: &str) -> HttpResponse {
+    match e {
+        ApiServiceError::InvalidObjectId => HttpResponse::BadRequest().body("Invalid id"),
+        ApiServiceError::InvalidRating => HttpResponse::BadRequest().body("Rating must be between 1 and 5"),
+        e => {
+            eprintln!("Error while trying to {}: {:?}", action, e);
+            HttpResponse::InternalServerError().body(format!("Failed to {}", action))
+        }
+    }
+}
+
+/// Route to list the reviews of a course, `?sort=newest|oldest|highest|lowest`.
+/// Admins can add `include_hidden=true` to see the hidden ones.
+#[get("/courses/{id}/reviews")]
+async fn get_by_course(
+    app_data: web::Data<crate::AppState>,
+    auth: Option<AuthenticatedUser>,
+    course_id: web::Path<String>,
+    params: web::Query<ReviewListParams>,
+) -> impl Responder {
+    let is_admin = matches!(auth, Some(ref auth) if auth.has_scope(ApiScope::Admin));
+    if params.include_hidden && !is_admin {
+        return HttpResponse::Forbidden().body("Admin access required");
+    }
+    match app_data
+        .service_manager
+        .review_service
+        .get_by_course(&course_id, params.sort, params.include_hidden)
+        .await
+    {
+        Ok(reviews) => HttpResponse::Ok().json(reviews),
+        Err(e) => error_response(e, "retrieve the reviews"),
+    }
+}
+
+/// Route to rate and review a course, or change the existing review of the current user.
+/// Only learners with a watched record for the course can review it.
+#[post("/courses/{id}/reviews")]
+async fn add(
+    app_data: web::Data<crate::AppState>,
+    auth: AuthenticatedUser,
+    audit: AuditContext,
+    course_id: web::Path<String>,
+    data: web::Json<ReviewRequest>,
+) -> impl Responder {
+    if !auth.can_act_as_user() {
+        return HttpResponse::Forbidden().body("The API key only has the catalog_read scope");
+    }
+    let services = &app_data.service_manager;
+    let course_id = match ObjectId::parse_str(course_id.as_str()) {
+        Ok(course_id) => course_id,
+        Err(_) => return HttpResponse::BadRequest().body("Invalid course id"),
+    };
+
+    match services.course_service.exists(course_id).await {
+        Ok(true) => {}
+        Ok(false) => return HttpResponse::NotFound().body("Course not found"),
+        Err(e) => {
+            eprintln!("Error while checking course: {:?}", e);
+            return HttpResponse::InternalServerError().body("Failed to save the review");
+        }
+    }
+    match services.watched_service.exists_for_user(&auth.user, course_id).await {
+        Ok(true) => {}
+        Ok(false) => return HttpResponse::Forbidden().body("Only learners who watched the course can review it"),
+        Err(e) => {
+            eprintln!("Error while checking watched: {:?}", e);
+            return HttpResponse::InternalServerError().body("Failed to save the review");
+        }
+    }
+
+    match services
+        .review_service
+        .upsert(course_id, auth.user_id, data.rating, &data.comment, &audit)
+        .await
+    {
+        Ok((review, true)) => HttpResponse::Created().json(review),
+        Ok((review, false)) => HttpResponse::Ok().json(review),
+        Err(e) => error_response(e, "save the review"),
+    }
+}
+
+/// Route to delete a review, by its author or an admin
+#[delete("/reviews/{id}")]
+async fn delete(
+    app_data: web::Data<crate::AppState>,
+    auth: AuthenticatedUser,
+    audit: AuditContext,
+    review_id: web::Path<String>,
+) -> impl Responder {
+    if !auth.can_act_as_user() {
+        return HttpResponse::Forbidden().body("The API key only has the catalog_read scope");
+    }
+    let services = &app_data.service_manager;
+    let id = review_id.into_inner();
+
+    match services.review_service.get_by_id(&id).await {
+        Ok(Some(review)) if review.user_id == auth.user_id || auth.has_scope(ApiScope::Admin) => {}
+        Ok(Some(_)) => return HttpResponse::Forbidden().body("Not allowed to delete this review"),
+        Ok(None) => return HttpResponse::NotFound().body("Review not found"),
+        Err(e) => return error_response(e, "delete the review"),
+    }
+
+    match services.review_service.delete(&id, &audit).await {
+        Ok(Some(_)) => HttpResponse::Ok().json("Review deleted successfully"),
+        Ok(None) => HttpResponse::NotFound().body("Review not found"),
+        Err(e) => error_response(e, "delete the review"),
+    }
+}
+
+/// Route to report a review for moderation
+#[post("/reviews/{id}/flag")]
+async fn flag(
+    app_data: web::Data<crate::AppState>,
+    auth: AuthenticatedUser,
+    audit: AuditContext,
+    review_id: web::Path<String>,
+    data: web::Json<FlagReviewRequest>,
+) -> impl Responder {
+    if !auth.can_act_as_user() {
+        return HttpResponse::Forbidden().body("The API key only has the catalog_read scope");
+    }
+    match app_data.service_manager.review_service.flag(&review_id, auth.user_id, data.reason.as_deref(), &audit).await {
+        Ok(Some(_)) => HttpResponse::Ok().json("Review reported"),
+        Ok(None) => HttpResponse::NotFound().body("Review not found"),
+        Err(e) => error_response(e, "report the review"),
+    }
+}
+
+// Helper function shared by the hide and unhide routes.
+async fn set_hidden(
+    app_data: &crate::AppState,
+    auth: &AuthenticatedUser,
+    audit: &AuditContext,
+    review_id: &str,
+    hidden: bool,
+) -> HttpResponse {
+    if !auth.has_scope(ApiScope::Admin) {
+        return HttpResponse::Forbidden().body("Admin access required");
+    }
+    match app_data.service_manager.review_service.set_hidden(review_id, hidden, audit).await {
+        Ok(Some(review)) => HttpResponse::Ok().json(review),
+        Ok(None) => HttpResponse::NotFound().body("Review not found"),
+        Err(e) => error_response(e, "moderate the review"),
+    }
+}
+
+/// Route to hide a review from listings and the course rating, admin only
+#[post("/reviews/{id}/hide")]
+async fn hide(
+    app_data: web::Data<crate::AppState>,
+    auth: AuthenticatedUser,
+    audit: AuditContext,
+    review_id: web::Path<String>,
+) -> impl Responder {
+    set_hidden(&app_data, &auth, &audit, &review_id, true).await
+}
+
+/// Route to show a hidden review again, admin only
+#[post("/reviews/{id}/unhide")]
+async fn unhide(
+    app_data: web::Data<crate::AppState>,
+    auth: AuthenticatedUser,
+    audit: AuditContext,
+    review_id: web::Path<String>,
+) -> impl Responder {
+    set_hidden(&app_data, &auth, &audit, &review_id, false).await
+}
+
+/// Route to list the reviews waiting for moderation, admin only
+#[get("/reviews/flagged")]
+async fn get_flagged(app_data: web::Data<crate::AppState>, auth: AuthenticatedUser) -> impl Responder {
+    if !auth.has_scope(ApiScope::Admin) {
+        return HttpResponse::Forbidden().body("Admin access required");
+    }
+    match app_data.service_manager.review_service.get_flagged().await {
+        Ok(reviews) => HttpResponse::Ok().json(reviews),
+        Err(e) => {
+            eprintln!("Error while getting flagged reviews: {:?}", e);
+            HttpResponse::InternalServerError().body("Failed to retrieve flagged reviews")
+        }
+    }
+}
+
+pub fn init(cfg: &mut web::ServiceConfig) {
+    cfg.service(get_by_course);
+    cfg.service(add);
+    cfg.service(delete);
+    cfg.service(flag);
+    cfg.service(hide);
+    cfg.service(unhide);
+    cfg.service(get_flagged);
+}
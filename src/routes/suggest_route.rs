@@ -0,0 +1,17 @@
+use crate::models::suggest_model::SuggestParams;
+use actix_web::{get, web, HttpResponse, Responder};
+
+const DEFAULT_LIMIT: usize = 10;
+const MAX_LIMIT: usize = 50;
+
+/// Route to autocomplete course titles, authors and topics, tolerating typos
+#[get("/courses/suggest")]
+async fn suggest(app_data: web::Data<crate::AppState>, params: web::Query<SuggestParams>) -> impl Responder {
+    let limit = params.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT);
+    let suggestions = app_data.service_manager.suggest_service.suggest(&params.q, limit);
+    HttpResponse::Ok().json(suggestions)
+}
+
+pub fn init(cfg: &mut web::ServiceConfig) {
+    cfg.service(suggest);
+}
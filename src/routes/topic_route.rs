@@ -0,0 +1,165 @@
+use crate::extractors::{audit_extractor::AuditContext, auth_extractor::AuthenticatedUser};
+use crate::models::{api_key_model::ApiScope, topic_model::{TopicMigrationParams, TopicMigrationRequest, TopicRequest}};
+use crate::services::topic_service::ApiServiceError;
+use actix_web::{delete, get, post, put, web, HttpResponse, Responder};
+
+// Helper function to map validation errors to responses, `None` for unexpected errors.
+fn client_error(e: &ApiServiceError) -> Option<HttpResponse> {
+    match e {
+        ApiServiceError::InvalidObjectId => Some(HttpResponse::BadRequest().body(e.to_string())),
+        ApiServiceError::InvalidTopic(message) => Some(HttpResponse::BadRequest().body(message.clone())),
+        ApiServiceError::Conflict(message) => Some(HttpResponse::Conflict().body(message.clone())),
+        ApiServiceError::HasChildren => Some(HttpResponse::Conflict().body("Move or delete the child topics first")),
+        ApiServiceError::DatabaseError(_) => None,
+    }
+}
+
+/// Route to get the whole topic taxonomy
+#[get("/topics")]
+async fn get_all(app_data: web::Data<crate::AppState>) -> impl Responder {
+    match app_data.service_manager.topic_service.get_all().await {
+        Ok(topics) => HttpResponse::Ok().json(topics),
+        Err(e) => {
+            eprintln!("Error while getting topics: {:?}", e);
+            HttpResponse::InternalServerError().body("Failed to retrieve topics")
+        }
+    }
+}
+
+/// Route to get a topic by its MongoDB `_id`
+#[get("/topics/{id}")]
+async fn get_by_id(
+    app_data: web::Data<crate::AppState>,
+    topic_id: web::Path<String>,
+) -> impl Responder {
+    let id = topic_id.into_inner();
+    match app_data.service_manager.topic_service.get_by_id(&id).await {
+        Ok(Some(topic)) => HttpResponse::Ok().json(topic),
+        Ok(None) => HttpResponse::NotFound().body("Topic not found"),
+        Err(e) => {
+            eprintln!("Error while getting topic: {:?}", e);
+            HttpResponse::InternalServerError().body("Failed to retrieve the topic")
+        }
+    }
+}
+
+/// Route to add a new topic, admin only
+#[post("/topics")]
+async fn add(
+    app_data: web::Data<crate::AppState>,
+    auth: AuthenticatedUser,
+    audit: AuditContext,
+    data: web::Json<TopicRequest>,
+) -> impl Responder {
+    if !auth.has_scope(ApiScope::Admin) {
+        return HttpResponse::Forbidden().body("Admin access required");
+    }
+    match app_data.service_manager.topic_service.create(&data, &audit).await {
+        Ok(result) => match result.inserted_id.as_object_id() {
+            Some(id) => HttpResponse::Ok().json(id.to_hex()),
+            None => HttpResponse::InternalServerError().body("Failed to extract inserted_id"),
+        },
+        Err(e) => client_error(&e).unwrap_or_else(|| {
+            eprintln!("Error while adding topic: {:?}", e);
+            HttpResponse::InternalServerError().body("Failed to add the topic")
+        }),
+    }
+}
+
+/// Route to update the name, synonyms and parent of a topic, admin only
+#[put("/topics/{id}")]
+async fn update(
+    app_data: web::Data<crate::AppState>,
+    auth: AuthenticatedUser,
+    audit: AuditContext,
+    data: web::Json<TopicRequest>,
+    topic_id: web::Path<String>,
+) -> impl Responder {
+    if !auth.has_scope(ApiScope::Admin) {
+        return HttpResponse::Forbidden().body("Admin access required");
+    }
+    let id = topic_id.into_inner();
+    match app_data.service_manager.topic_service.update(&data, &id, &audit).await {
+        Ok(Some(_)) => HttpResponse::Ok().json("Topic updated successfully"),
+        Ok(None) => HttpResponse::NotFound().body("Topic not found"),
+        Err(e) => client_error(&e).unwrap_or_else(|| {
+            eprintln!("Error while updating topic: {:?}", e);
+            HttpResponse::InternalServerError().body("Failed to update the topic")
+        }),
+    }
+}
+
+/// Route to delete a topic without children, admin only
+#[delete("/topics/{id}")]
+async fn delete(
+    app_data: web::Data<crate::AppState>,
+    auth: AuthenticatedUser,
+    audit: AuditContext,
+    topic_id: web::Path<String>,
+) -> impl Responder {
+    if !auth.has_scope(ApiScope::Admin) {
+        return HttpResponse::Forbidden().body("Admin access required");
+    }
+    let id = topic_id.into_inner();
+    match app_data.service_manager.topic_service.delete(&id, &audit).await {
+        Ok(result) if result.deleted_count > 0 => HttpResponse::Ok().json("Topic deleted successfully"),
+        Ok(_) => HttpResponse::NotFound().body("Topic not found"),
+        Err(e) => client_error(&e).unwrap_or_else(|| {
+            eprintln!("Error while deleting topic: {:?}", e);
+            HttpResponse::InternalServerError().body("Failed to delete the topic")
+        }),
+    }
+}
+
+/// Route to canonicalize the topics of existing courses, `?dry_run=true` only reports the changes, admin only.
+/// An optional body maps other spellings to managed topics, they are added as synonyms first.
+#[post("/topics/migrate")]
+async fn migrate(
+    app_data: web::Data<crate::AppState>,
+    auth: AuthenticatedUser,
+    audit: AuditContext,
+    params: web::Query<TopicMigrationParams>,
+    body: web::Bytes,
+) -> impl Responder {
+    if !auth.has_scope(ApiScope::Admin) {
+        return HttpResponse::Forbidden().body("Admin access required");
+    }
+    let request: TopicMigrationRequest = if body.is_empty() {
+        TopicMigrationRequest::default()
+    } else {
+        match serde_json::from_slice(&body) {
+            Ok(request) => request,
+            Err(e) => return HttpResponse::BadRequest().body(format!("Invalid body: {}", e)),
+        }
+    };
+    let services = &app_data.service_manager;
+
+    let report = match services.topic_service.migrate(params.dry_run, &request.synonyms, &audit).await {
+        Ok(report) => report,
+        Err(e) => {
+            return client_error(&e).unwrap_or_else(|| {
+                eprintln!("Error while migrating topics: {:?}", e);
+                HttpResponse::InternalServerError().body("Failed to migrate topics")
+            })
+        }
+    };
+
+    // The courses were rewritten behind the course service, refresh their autocomplete terms
+    for course_id in &report.updated_course_ids {
+        match services.course_service.get_by_id(&course_id.to_hex()).await {
+            Ok(Some(course)) => services.suggest_service.upsert(*course_id, &course),
+            Ok(None) => {} // Deleted courses are not suggested
+            Err(e) => eprintln!("Error while reloading course {} in the suggest index: {:?}", course_id, e),
+        }
+    }
+    HttpResponse::Ok().json(report)
+}
+
+pub fn init(cfg: &mut web::ServiceConfig) {
+    cfg.service(get_all);
+    cfg.service(get_by_id);
+    cfg.service(add);
+    cfg.service(update);
+    cfg.service(delete);
+    cfg.service(migrate);
+}
@@ -1,12 +1,34 @@
-use crate::models::user_model::User;
-use actix_web::{delete, get, post, put, web, HttpResponse, Responder};
+use crate::extractors::{audit_extractor::AuditContext, auth_extractor::AuthenticatedUser};
+use crate::models::api_key_model::ApiScope;
+use crate::models::{export_model::UserExport, session_model::SessionKind, user_model::{User, UserRole}};
+use crate::services::{integrity_service::ApiServiceError as IntegrityError, user_service::ApiServiceError as UserError};
+use crate::utils::mongo::is_duplicate_key;
+use crate::utils::stream::{list_format, stream_list, ListFormat};
+use actix_web::{delete, get, post, put, web, HttpRequest, HttpResponse, Responder};
+use futures::stream::StreamExt;
+use mongodb::bson::oid::ObjectId;
 
-/// Route to get all users
+/// Route to get all users without password hashes, admin only like the streamed user search.
+/// Streamed with `Accept: application/x-ndjson` or `application/json; stream=true`
 #[get("/users")]
-async fn get_all(app_data: web::Data<crate::AppState>) -> impl Responder {
+async fn get_all(app_data: web::Data<crate::AppState>, auth: AuthenticatedUser, req: HttpRequest) -> impl Responder {
+    if !auth.has_scope(ApiScope::Admin) {
+        return HttpResponse::Forbidden().body("Admin access required");
+    }
+    let format = list_format(&req);
+    if format != ListFormat::Buffered {
+        return match app_data.service_manager.user_service.stream_all().await {
+            Ok(cursor) => stream_list(format, cursor.map(|user| user.map(UserExport::from))),
+            Err(e) => {
+                eprintln!("Error while getting users: {:?}", e);
+                HttpResponse::InternalServerError().body("Failed to retrieve users")
+            }
+        };
+    }
+
     let result = app_data.service_manager.user_service.get_all().await;
     match result {
-        Ok(users) => HttpResponse::Ok().json(users),
+        Ok(users) => HttpResponse::Ok().json(users.into_iter().map(UserExport::from).collect::<Vec<_>>()),
         Err(e) => {
             eprintln!("Error while getting users: {:?}", e);
             HttpResponse::InternalServerError().body("Failed to retrieve users")
@@ -33,22 +55,36 @@ async fn get_by_id(
 
 /// Route to add a new user
 #[post("/users")]
-async fn add(app_data: web::Data<crate::AppState>, data: web::Json<User>) -> impl Responder {
+async fn add(
+    app_data: web::Data<crate::AppState>,
+    audit: AuditContext,
+    data: web::Json<User>,
+) -> impl Responder {
     // Clone the user data and hash the password
     let mut user = data.into_inner();
 
+    // New accounts start unverified until the emailed token is confirmed
+    user.email_verified = false;
+    user.role = UserRole::Learner;
+    user.deleted_at = None;
+
     // Hash the password before saving
     match bcrypt::hash(user.password, bcrypt::DEFAULT_COST) {
         Ok(hashed_password) => {
             user.password = hashed_password; // Replace the plain-text password with the hash
 
             // Attempt to insert the user into the database
-            match app_data.service_manager.user_service.create(&user).await {
+            match app_data.service_manager.user_service.create(&user, &audit).await {
                 Ok(result) => match result.inserted_id.as_object_id(){
                     // Safely extract and return the MongoDB-generated `_id`
-                    Some(id) => HttpResponse::Ok().json(id.to_hex()),
+                    Some(id) => {
+                        // The account exists even if the email fails, it can be resent later
+                        let _ = crate::routes::auth_route::send_verification(&app_data, id, &user.email).await;
+                        HttpResponse::Ok().json(id.to_hex())
+                    }
                     None => HttpResponse::InternalServerError().body("Failed to extract inserted_id as ObjectId"),
                 },
+                Err(e) if is_duplicate_key(&e) => HttpResponse::Conflict().body("Another user already has this email"),
                 Err(e) => {
                     eprintln!("Error while adding user: {:?}", e);
                     HttpResponse::InternalServerError().body("Failed to add the user")
@@ -62,22 +98,37 @@ async fn add(app_data: web::Data<crate::AppState>, data: web::Json<User>) -> imp
     }
 }
 
-/// Route to update an existing user by its MongoDB `_id`
+/// Route to update an existing user by its MongoDB `_id`, the user themselves or an admin, from a session
 #[put("/users/{id}")]
 async fn update(
     app_data: web::Data<crate::AppState>,
+    auth: AuthenticatedUser,
+    audit: AuditContext,
     data: web::Json<User>,
     user_id: web::Path<String>,
 ) -> impl Responder {
+    // The email leads to password resets and verification, a leaked key must not be able to change it
+    if auth.is_api_key() {
+        return HttpResponse::Forbidden().body("Users cannot be updated with an API key");
+    }
     let id = user_id.into_inner();
-    match app_data.service_manager.user_service.update(&data, &id).await {
-        Ok(result) => {
+    if !auth.can_access_user(&id) {
+        return HttpResponse::Forbidden().body("Not allowed to access this user");
+    }
+    match app_data.service_manager.user_service.update(&data, &id, &audit).await {
+        Ok((result, email_changed)) => {
             if result.modified_count > 0 {
+                if email_changed {
+                    if let Err(response) = reverify_email(&app_data, &id, &data.email).await {
+                        return response;
+                    }
+                }
                 HttpResponse::Ok().json("User updated successfully")
             } else {
                 HttpResponse::NotFound().body("User not found or no changes made")
             }
         }
+        Err(UserError::EmailTaken) => HttpResponse::Conflict().body("Another user already has this email"),
         Err(e) => {
             eprintln!("Error while updating user: {:?}", e);
             HttpResponse::InternalServerError().body("Failed to update the user")
@@ -85,15 +136,43 @@ async fn update(
     }
 }
 
+// Helper function to drop the verification tokens sent to the old address and send one to the new address.
+async fn reverify_email(app_data: &crate::AppState, user_id: &str, email: &str) -> Result<(), HttpResponse> {
+    let Ok(user_id) = ObjectId::parse_str(user_id) else {
+        return Ok(());
+    };
+    if let Err(e) = app_data.service_manager.session_service.revoke_all(user_id, SessionKind::EmailVerification).await {
+        eprintln!("Error while revoking verification tokens: {:?}", e);
+        return Err(HttpResponse::InternalServerError().body("Failed to update the user"));
+    }
+    // The change is saved even if the email fails, it can be resent later
+    let _ = crate::routes::auth_route::send_verification(app_data, user_id, email).await;
+    Ok(())
+}
+
 #[delete("/users/{id}")]
 async fn delete(
     app_data: web::Data<crate::AppState>,
+    audit: AuditContext,
     user_id: web::Path<String>,
 ) -> impl Responder {
     let id = user_id.into_inner();
-    match app_data.service_manager.user_service.delete(&id).await {
+
+    // Check the delete policy against the watched records first
+    match app_data.service_manager.integrity_service.before_user_delete(&id).await {
+        Ok(()) => {}
+        Err(IntegrityError::Referenced(count)) => {
+            return HttpResponse::Conflict().body(format!("User is still referenced by {} watched records", count));
+        }
+        Err(e) => {
+            eprintln!("Error while applying the delete policy: {:?}", e);
+            return HttpResponse::InternalServerError().body("Failed to delete the user");
+        }
+    }
+
+    match app_data.service_manager.user_service.delete(&id, &audit).await {
         Ok(result) => {
-            if result.deleted_count > 0 {
+            if result.modified_count > 0 {
                 HttpResponse::Ok().json("User deleted successfully")
             } else {
                 HttpResponse::NotFound().body("User not found")
@@ -106,10 +185,54 @@ async fn delete(
     }
 }
 
+/// Route to list the soft deleted users, admin only
+#[get("/users/deleted")]
+async fn get_deleted(app_data: web::Data<crate::AppState>, auth: AuthenticatedUser) -> impl Responder {
+    if !auth.has_scope(ApiScope::Admin) {
+        return HttpResponse::Forbidden().body("Admin access required");
+    }
+    match app_data.service_manager.user_service.get_deleted(None).await {
+        Ok(users) => HttpResponse::Ok().json(users),
+        Err(e) => {
+            eprintln!("Error while getting deleted users: {:?}", e);
+            HttpResponse::InternalServerError().body("Failed to retrieve deleted users")
+        }
+    }
+}
+
+/// Route to restore a soft deleted user by its MongoDB `_id`, admin only
+#[post("/users/{id}/restore")]
+async fn restore(
+    app_data: web::Data<crate::AppState>,
+    auth: AuthenticatedUser,
+    audit: AuditContext,
+    user_id: web::Path<String>,
+) -> impl Responder {
+    if !auth.has_scope(ApiScope::Admin) {
+        return HttpResponse::Forbidden().body("Admin access required");
+    }
+    let id = user_id.into_inner();
+    match app_data.service_manager.user_service.restore(&id, &audit).await {
+        Ok(result) => {
+            if result.modified_count > 0 {
+                HttpResponse::Ok().json("User restored successfully")
+            } else {
+                HttpResponse::NotFound().body("Deleted user not found")
+            }
+        }
+        Err(e) => {
+            eprintln!("Error while restoring user: {:?}", e);
+            HttpResponse::InternalServerError().body("Failed to restore the user")
+        }
+    }
+}
+
 pub fn init(cfg: &mut web::ServiceConfig) {
     cfg.service(get_all);
+    cfg.service(get_deleted); // Before `get_by_id` so `deleted` is not taken for an id
     cfg.service(get_by_id);
     cfg.service(add);
     cfg.service(update);
     cfg.service(delete);
+    cfg.service(restore);
 }
\ No newline at end of file
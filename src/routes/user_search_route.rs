@@ -1,15 +1,37 @@
-use crate::models::user_search_model::UserSearchParams;
-use actix_web::{post, web, HttpResponse, Responder};
+use crate::extractors::auth_extractor::AuthenticatedUser;
+use crate::models::{api_key_model::ApiScope, export_model::UserExport, user_search_model::UserSearchParams};
+use crate::utils::mongo::escape_regex;
+use crate::utils::stream::{list_format, stream_list, ListFormat};
+use actix_web::{post, web, HttpRequest, HttpResponse, Responder};
+use futures::stream::StreamExt;
 use mongodb::bson::doc;
 
 #[post("/users/search")]
 async fn search_users(
     app_data: web::Data<crate::AppState>, // AppState to access services
+    auth: Option<AuthenticatedUser>,      // Required to stream the matching users
+    req: HttpRequest,                     // Streamed results are requested through `Accept`
     body: web::Json<UserSearchParams>,    // Request body for email search
 ) -> impl Responder {
     // Validate that the email field is provided
     if let Some(ref email) = body.email {
-        let filter = doc! { "email": { "$regex": email, "$options": "i" } };
+        // The email is matched literally, a pattern could probe every address
+        let filter = doc! { "email": { "$regex": escape_regex(email), "$options": "i" } };
+
+        // Streamed searches return the matching users, without password hashes, instead of a boolean, admin only
+        let format = list_format(&req);
+        if format != ListFormat::Buffered {
+            if !auth.is_some_and(|auth| auth.has_scope(ApiScope::Admin)) {
+                return HttpResponse::Forbidden().body("Admin access required");
+            }
+            return match app_data.service_manager.user_search_service.search_stream(filter).await {
+                Ok(cursor) => stream_list(format, cursor.map(|user| user.map(UserExport::from))),
+                Err(e) => {
+                    eprintln!("Error while searching users: {:?}", e);
+                    HttpResponse::InternalServerError().body("An error occurred while searching for users")
+                }
+            };
+        }
 
         // Perform the search in the database
         match app_data.service_manager.user_search_service.search(filter).await {
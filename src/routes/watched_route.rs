@@ -1,9 +1,82 @@
-use crate::models::watched_model::Watched;
-use actix_web::{delete, get, post, put, web, HttpResponse, Responder};
+use crate::extractors::{audit_extractor::AuditContext, auth_extractor::AuthenticatedUser};
+use crate::models::{api_key_model::ApiScope, watched_model::{ArchiveFinishedRequest, ProgressUpdate, Watched, WatchedListParams}};
+use crate::services::{
+    integrity_service::ApiServiceError as IntegrityError,
+    verification_service::VerificationPolicy,
+    watched_service::ApiServiceError,
+};
+use crate::utils::stream::{list_format, stream_list, ListFormat};
+use actix_web::{delete, get, post, put, web, HttpRequest, HttpResponse, Responder};
+use mongodb::bson::{oid::ObjectId, DateTime};
 
+// Helper function to check that the course and user of a watched record exist.
+// Returns the error response when they do not.
+async fn check_references(app_data: &crate::AppState, watched: &Watched) -> Option<HttpResponse> {
+    match app_data.service_manager.integrity_service.check_watched_references(watched).await {
+        Ok(()) => None,
+        Err(IntegrityError::MissingCourse) => Some(HttpResponse::BadRequest().body("Course not found")),
+        Err(IntegrityError::MissingUser) => Some(HttpResponse::BadRequest().body("User not found")),
+        Err(e) => {
+            eprintln!("Error while checking watched references: {:?}", e);
+            Some(HttpResponse::InternalServerError().body("Failed to check the course and user"))
+        }
+    }
+}
+
+// Helper function to enforce API key scopes and the email verification policy on watched writes.
+// Writes always require credentials, so leaving out a restricted key never widens what a caller can do.
+// Returns the error response when the write is not allowed.
+fn check_write_allowed(app_data: &crate::AppState, auth: &AuthenticatedUser) -> Option<HttpResponse> {
+    if !auth.has_scope(ApiScope::WatchedWrite) {
+        return Some(HttpResponse::Forbidden().body("The API key does not have the watched_write scope"));
+    }
+    let policy = app_data.service_manager.verification_service.policy();
+    if policy == VerificationPolicy::RestrictWatchedWrites && !auth.user.email_verified {
+        return Some(HttpResponse::Forbidden().body("Email address is not verified"));
+    }
+    None
+}
+
+// Helper function to load a watched record the caller may change: their own, or any with the admin scope.
+// Returns the error response when it is missing or belongs to another learner.
+async fn get_owned(app_data: &crate::AppState, auth: &AuthenticatedUser, watched_id: &str) -> Result<Watched, Box<HttpResponse>> {
+    let watched = match app_data.service_manager.watched_service.get_by_id(watched_id).await {
+        Ok(Some(watched)) => watched,
+        Ok(None) => return Err(Box::new(HttpResponse::NotFound().body("Watched not found"))),
+        Err(ApiServiceError::InvalidObjectId) => return Err(Box::new(HttpResponse::BadRequest().body("Invalid watched id"))),
+        Err(e) => {
+            eprintln!("Error while getting watched: {:?}", e);
+            return Err(Box::new(HttpResponse::InternalServerError().body("Failed to retrieve the watched")));
+        }
+    };
+    // Records without a learner predate `user_id` and are only handled by admins
+    let owner = watched.user_id.map(|user_id| user_id.to_hex()).unwrap_or_default();
+    if !auth.can_access_user(&owner) {
+        return Err(Box::new(HttpResponse::Forbidden().body("Not allowed to change this watched record")));
+    }
+    Ok(watched)
+}
+
+/// Route to list watched records, `?status=active|archived|all` (active by default).
+/// Streamed with `Accept: application/x-ndjson` or `application/json; stream=true`.
 #[get("/watched")]
-async fn get_all(app_data: web::Data<crate::AppState>) -> impl Responder {
-    let result = app_data.service_manager.watched_service.get_all().await;
+async fn get_all(
+    app_data: web::Data<crate::AppState>,
+    req: HttpRequest,
+    params: web::Query<WatchedListParams>,
+) -> impl Responder {
+    let format = list_format(&req);
+    if format != ListFormat::Buffered {
+        return match app_data.service_manager.watched_service.stream_all(params.status).await {
+            Ok(cursor) => stream_list(format, cursor),
+            Err(e) => {
+                eprintln!("Error while getting watcheds: {:?}", e);
+                HttpResponse::InternalServerError().body("Failed to retrieve watcheds")
+            }
+        };
+    }
+
+    let result = app_data.service_manager.watched_service.get_all(params.status).await;
     match result {
         Ok(watcheds) => HttpResponse::Ok().json(watcheds),
         Err(e) => {
@@ -30,10 +103,28 @@ async fn get_by_id(
 }
 
 
+/// Start watching a course, or resume the active record the learner already has for it
 #[post("/watched")]
-async fn add(app_data: web::Data<crate::AppState>, data: web::Json<Watched>) -> impl Responder {
-    match app_data.service_manager.watched_service.create(&data).await {
-        Ok(result) => match result.inserted_id.as_object_id() {
+async fn add(
+    app_data: web::Data<crate::AppState>,
+    auth: AuthenticatedUser,
+    audit: AuditContext,
+    data: web::Json<Watched>,
+) -> impl Responder {
+    if let Some(response) = check_write_allowed(&app_data, &auth) {
+        return response;
+    }
+
+    // Authenticated learners always write their own records
+    let mut watched = data.into_inner();
+    watched.user_id = Some(auth.user_id);
+    if let Some(response) = check_references(&app_data, &watched).await {
+        return response;
+    }
+
+    // Responds with the id of the record, as before resuming existed
+    match app_data.service_manager.watched_service.start_or_resume(&watched, &audit).await {
+        Ok((watched, _)) => match watched._id {
             Some(id) => HttpResponse::Ok().json(id.to_hex()),
             None => HttpResponse::InternalServerError().body("Failed to extract inserted_id"),
         },
@@ -47,11 +138,23 @@ async fn add(app_data: web::Data<crate::AppState>, data: web::Json<Watched>) ->
 #[put("/watched/{id}")]
 async fn update(
     app_data: web::Data<crate::AppState>,
+    auth: AuthenticatedUser,
+    audit: AuditContext,
     data: web::Json<Watched>,
     watched_id: web::Path<String>,
 ) -> impl Responder {
+    if let Some(response) = check_write_allowed(&app_data, &auth) {
+        return response;
+    }
     let id = watched_id.into_inner();
-    match app_data.service_manager.watched_service.update(&data, &id).await {
+    if let Err(response) = get_owned(&app_data, &auth, &id).await {
+        return *response;
+    }
+    if let Some(response) = check_references(&app_data, &data).await {
+        return response;
+    }
+
+    match app_data.service_manager.watched_service.update(&data, &id, &audit).await {
         Ok(result) => {
             if result.modified_count > 0 {
                 HttpResponse::Ok().json("Watched updated successfully")
@@ -59,6 +162,9 @@ async fn update(
                 HttpResponse::NotFound().body("Watched not found or no changes made")
             }
         }
+        Err(ApiServiceError::ActiveRecordExists) => {
+            HttpResponse::Conflict().body("An active record already exists for this course")
+        }
         Err(e) => {
             eprintln!("Error while updating watched: {:?}", e);
             HttpResponse::InternalServerError().body("Failed to update the watched")
@@ -69,10 +175,18 @@ async fn update(
 #[delete("/watched/{id}")]
 async fn delete(
     app_data: web::Data<crate::AppState>,
+    auth: AuthenticatedUser,
+    audit: AuditContext,
     watched_id: web::Path<String>,
 ) -> impl Responder {
+    if let Some(response) = check_write_allowed(&app_data, &auth) {
+        return response;
+    }
     let id = watched_id.into_inner();
-    match app_data.service_manager.watched_service.delete(&id).await {
+    if let Err(response) = get_owned(&app_data, &auth, &id).await {
+        return *response;
+    }
+    match app_data.service_manager.watched_service.delete(&id, &audit).await {
         Ok(result) => {
             if result.deleted_count > 0 {
                 HttpResponse::Ok().json("Watched deleted successfully")
@@ -87,10 +201,219 @@ async fn delete(
     }
 }
 
+/// Route for the progress heartbeat sent while a course is being watched
+#[post("/watched/{id}/progress")]
+async fn progress(
+    app_data: web::Data<crate::AppState>,
+    auth: AuthenticatedUser,
+    audit: AuditContext,
+    data: web::Json<ProgressUpdate>,
+    watched_id: web::Path<String>,
+) -> impl Responder {
+    if let Some(response) = check_write_allowed(&app_data, &auth) {
+        return response;
+    }
+
+    let id = watched_id.into_inner();
+    let services = &app_data.service_manager;
+
+    // The course duration turns the position into a percentage
+    let watched = match get_owned(&app_data, &auth, &id).await {
+        Ok(watched) => watched,
+        Err(response) => return *response,
+    };
+    let duration = match services.course_service.get_by_id(&watched.course_id.to_hex()).await {
+        Ok(course) => course.map(|course| course.duration),
+        Err(e) => {
+            eprintln!("Error while getting course: {:?}", e);
+            return HttpResponse::InternalServerError().body("Failed to record progress");
+        }
+    };
+
+    match services.watched_service.record_progress(&id, &data, duration, &audit).await {
+        Ok(Some(watched)) => HttpResponse::Ok().json(watched),
+        Ok(None) => HttpResponse::NotFound().body("Watched not found"),
+        Err(ApiServiceError::InvalidProgress(message)) => HttpResponse::BadRequest().body(message),
+        Err(e) => {
+            eprintln!("Error while recording progress: {:?}", e);
+            HttpResponse::InternalServerError().body("Failed to record progress")
+        }
+    }
+}
+
+// Helper function shared by the archive and unarchive routes.
+async fn set_archived(
+    app_data: &crate::AppState,
+    auth: &AuthenticatedUser,
+    audit: &AuditContext,
+    watched_id: &str,
+    archived: bool,
+) -> HttpResponse {
+    if let Some(response) = check_write_allowed(app_data, auth) {
+        return response;
+    }
+    if let Err(response) = get_owned(app_data, auth, watched_id).await {
+        return *response;
+    }
+    match app_data.service_manager.watched_service.set_archived(watched_id, archived, audit).await {
+        Ok(Some(watched)) => HttpResponse::Ok().json(watched),
+        Ok(None) => HttpResponse::NotFound().body("Watched not found"),
+        Err(ApiServiceError::InvalidObjectId) => HttpResponse::BadRequest().body("Invalid watched id"),
+        Err(ApiServiceError::ActiveRecordExists) => {
+            HttpResponse::Conflict().body("An active record already exists for this course")
+        }
+        Err(e) => {
+            eprintln!("Error while archiving watched: {:?}", e);
+            HttpResponse::InternalServerError().body("Failed to update the watched")
+        }
+    }
+}
+
+/// Route to archive a watched record
+#[post("/watched/{id}/archive")]
+async fn archive(
+    app_data: web::Data<crate::AppState>,
+    auth: AuthenticatedUser,
+    audit: AuditContext,
+    watched_id: web::Path<String>,
+) -> impl Responder {
+    set_archived(&app_data, &auth, &audit, &watched_id, true).await
+}
+
+/// Route to bring an archived watched record back to the active ones
+#[post("/watched/{id}/unarchive")]
+async fn unarchive(
+    app_data: web::Data<crate::AppState>,
+    auth: AuthenticatedUser,
+    audit: AuditContext,
+    watched_id: web::Path<String>,
+) -> impl Responder {
+    set_archived(&app_data, &auth, &audit, &watched_id, false).await
+}
+
+// Helper function to get the date `days` days ago, `None` when it is negative or out of range.
+fn days_ago(days: i64) -> Option<DateTime> {
+    let age = chrono::TimeDelta::try_days(days).filter(|_| days >= 0)?;
+    chrono::Utc::now().checked_sub_signed(age).map(DateTime::from_chrono)
+}
+
+/// Archive every record finished more than `older_than_days` ago.
+/// Admins archive the records of every learner, other users only their own.
+#[post("/watched/archive-finished")]
+async fn archive_finished(
+    app_data: web::Data<crate::AppState>,
+    auth: AuthenticatedUser,
+    audit: AuditContext,
+    data: web::Json<ArchiveFinishedRequest>,
+) -> impl Responder {
+    let user_id = (!auth.has_scope(ApiScope::Admin)).then_some(auth.user_id);
+    if let Some(response) = check_write_allowed(&app_data, &auth) {
+        return response;
+    }
+    let Some(cutoff) = days_ago(data.older_than_days) else {
+        return HttpResponse::BadRequest().body("older_than_days must be a non-negative number of days");
+    };
+    match app_data.service_manager.watched_service.archive_finished_before(cutoff, user_id, &audit).await {
+        Ok(archived) => HttpResponse::Ok().json(serde_json::json!({ "archived": archived })),
+        Err(e) => {
+            eprintln!("Error while archiving finished watched: {:?}", e);
+            HttpResponse::InternalServerError().body("Failed to archive finished watched")
+        }
+    }
+}
+
+/// Admin tool merging duplicate active records per learner and course.
+/// Records created before `user_id` existed are first assigned to the user listing them in `watched_ids`.
+#[post("/watched/merge-duplicates")]
+async fn merge_duplicates(
+    app_data: web::Data<crate::AppState>,
+    auth: AuthenticatedUser,
+    audit: AuditContext,
+) -> impl Responder {
+    if !auth.has_scope(ApiScope::Admin) {
+        return HttpResponse::Forbidden().body("Admin access required");
+    }
+    let services = &app_data.service_manager;
+
+    let users = match services.user_service.get_all().await {
+        Ok(users) => users,
+        Err(e) => {
+            eprintln!("Error while getting users: {:?}", e);
+            return HttpResponse::InternalServerError().body("Failed to merge duplicates");
+        }
+    };
+    let mut assigned = 0;
+    for user in &users {
+        let (Some(user_id), Some(watched_ids)) = (user._id, &user.watched_ids) else {
+            continue;
+        };
+        let watched_ids: Vec<ObjectId> = watched_ids.iter().filter_map(|id| ObjectId::parse_str(id).ok()).collect();
+        match services.watched_service.assign_user(user_id, &watched_ids).await {
+            Ok(count) => assigned += count,
+            Err(e) => {
+                eprintln!("Error while assigning watched records: {:?}", e);
+                return HttpResponse::InternalServerError().body("Failed to merge duplicates");
+            }
+        }
+    }
+
+    let merged = match services.watched_service.merge_duplicates(&audit).await {
+        Ok(merged) => merged,
+        Err(e) => {
+            eprintln!("Error while merging duplicates: {:?}", e);
+            return HttpResponse::InternalServerError().body("Failed to merge duplicates");
+        }
+    };
+    for group in &merged {
+        if let Err(e) = services
+            .user_service
+            .replace_watched_ids(group.user_id, &group.removed, group.kept, &audit)
+            .await
+        {
+            eprintln!("Error while updating watched_ids: {:?}", e);
+            return HttpResponse::InternalServerError().body("Failed to merge duplicates");
+        }
+    }
+
+    // Without duplicates left, the unique index can be built
+    let index_created = match services.watched_service.ensure_indexes().await {
+        Ok(()) => true,
+        Err(e) => {
+            eprintln!("Error while creating watched indexes: {:?}", e);
+            false
+        }
+    };
+
+    HttpResponse::Ok().json(serde_json::json!({
+        "assigned_user_ids": assigned,
+        "merged": merged,
+        "index_created": index_created,
+    }))
+}
+
 pub fn init(cfg: &mut web::ServiceConfig) {
     cfg.service(get_all);
     cfg.service(get_by_id);
     cfg.service(add);
     cfg.service(update);
     cfg.service(delete);
-}
\ No newline at end of file
+    cfg.service(progress);
+    cfg.service(archive);
+    cfg.service(unarchive);
+    cfg.service(archive_finished);
+    cfg.service(merge_duplicates);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn days_ago_rejects_negative_and_out_of_range_values() {
+        assert!(days_ago(0).is_some());
+        assert!(days_ago(365).is_some());
+        assert!(days_ago(-1).is_none());
+        assert!(days_ago(i64::MAX).is_none());
+        assert!(days_ago(200_000_000).is_none());
+    }
+}
@@ -0,0 +1,95 @@
+use crate::models::api_key_model::{ApiKey, CreateApiKeyRequest};
+use crate::utils::token::{generate_token, hash_token};
+use mongodb::{
+    bson::{doc, oid::ObjectId, DateTime},
+    error::Error as MongoError,
+    results::UpdateResult,
+    Collection,
+};
+use futures::stream::StreamExt;
+use thiserror::Error;
+
+const KEY_PREFIX: &str = "mlk_";
+const KEY_LENGTH: usize = 40;
+const DISPLAY_PREFIX_LENGTH: usize = 12;
+
+#[derive(Debug, Error)]
+pub enum ApiServiceError {
+    #[error("Invalid ObjectId format")]
+    InvalidObjectId,
+    #[error("Database error: {0}")]
+    DatabaseError(#[from] MongoError),
+}
+
+#[derive(Clone)]
+pub struct ApiService {
+    collection: Collection<ApiKey>,
+}
+
+impl ApiService {
+    pub fn new(collection: Collection<ApiKey>) -> ApiService {
+        ApiService { collection }
+    }
+
+    /// Create a new API key for a user.
+    /// Returns the stored key and the plain-text key, which is only available now.
+    pub async fn create(&self, user_id: ObjectId, request: &CreateApiKeyRequest) -> Result<(ApiKey, String), MongoError> {
+        let key = format!("{}{}", KEY_PREFIX, generate_token(KEY_LENGTH));
+
+        let mut api_key = ApiKey {
+            _id: None,
+            user_id,
+            name: request.name.clone(),
+            prefix: key[..DISPLAY_PREFIX_LENGTH].to_string(),
+            key_hash: hash_token(&key),
+            scopes: request.scopes.clone(),
+            expires_at: request.expires_at.map(DateTime::from_chrono),
+            last_used_at: None,
+            revoked_at: None,
+            created_at: DateTime::now(),
+        };
+        let result = self.collection.insert_one(&api_key, None).await?;
+        api_key._id = result.inserted_id.as_object_id();
+
+        Ok((api_key, key))
+    }
+
+    /// Get all API keys of a user, including revoked and expired ones.
+    pub async fn get_by_user(&self, user_id: ObjectId) -> Result<Vec<ApiKey>, MongoError> {
+        let mut cursor = self.collection.find(doc! { "user_id": user_id }, None).await?;
+        let mut docs = Vec::new();
+
+        while let Some(result) = cursor.next().await {
+            match result {
+                Ok(api_key) => docs.push(api_key),
+                Err(err) => return Err(err),
+            }
+        }
+
+        Ok(docs)
+    }
+
+    /// Revoke one of the API keys of a user.
+    pub async fn revoke(&self, user_id: ObjectId, key_id: &str) -> Result<UpdateResult, ApiServiceError> {
+        let object_id = ObjectId::parse_str(key_id).map_err(|_| ApiServiceError::InvalidObjectId)?;
+        let filter = doc! { "_id": object_id, "user_id": user_id, "revoked_at": null };
+        let update = doc! { "$set": { "revoked_at": DateTime::now() } };
+        let result = self.collection.update_one(filter, update, None).await?;
+        Ok(result)
+    }
+
+    /// Find the active (not revoked, not expired) API key matching a plain-text key and record its use.
+    pub async fn authenticate(&self, key: &str) -> Result<Option<ApiKey>, MongoError> {
+        let now = DateTime::now();
+        let filter = doc! {
+            "key_hash": hash_token(key),
+            "revoked_at": null,
+            "$or": [
+                { "expires_at": null },
+                { "expires_at": { "$gt": now } },
+            ],
+        };
+        let update = doc! { "$set": { "last_used_at": now } };
+        self.collection.find_one_and_update(filter, update, None).await
+    }
+}
@@ -0,0 +1,117 @@
+use crate::extractors::audit_extractor::AuditContext;
+use crate::models::audit_model::{AuditAction, AuditEvent};
+use mongodb::{
+    bson::{doc, DateTime, Document},
+    error::Error as MongoError,
+    options::FindOptions,
+    Collection,
+};
+use futures::stream::StreamExt;
+use std::{net::IpAddr, sync::Arc};
+
+// Fields that are never copied into the audit log
+const REDACTED_FIELDS: [&str; 1] = ["password"];
+
+#[derive(Clone)]
+pub struct ApiService {
+    collection: Collection<AuditEvent>,
+    trusted_proxies: Arc<Vec<IpAddr>>, // Proxies whose `X-Forwarded-For` header gives the client address
+}
+
+// Helper function to hide sensitive values before they are stored.
+fn redact(mut document: Document) -> Document {
+    for field in REDACTED_FIELDS {
+        if document.contains_key(field) {
+            document.insert(field, "[redacted]");
+        }
+    }
+    document
+}
+
+/// Keep only the fields that differ between two versions of a document.
+/// Returns the `(before, after)` values of those fields.
+pub fn diff(before: &Document, after: &Document) -> (Document, Document) {
+    let mut changed_before = Document::new();
+    let mut changed_after = Document::new();
+
+    for (key, value) in after {
+        if key != "_id" && before.get(key) != Some(value) {
+            if let Some(old) = before.get(key) {
+                changed_before.insert(key, old.clone());
+            }
+            changed_after.insert(key, value.clone());
+        }
+    }
+    for (key, value) in before {
+        if key != "_id" && !after.contains_key(key) {
+            changed_before.insert(key, value.clone());
+        }
+    }
+
+    (changed_before, changed_after)
+}
+
+impl ApiService {
+    pub fn new(collection: Collection<AuditEvent>, trusted_proxies: Vec<IpAddr>) -> ApiService {
+        ApiService { collection, trusted_proxies: Arc::new(trusted_proxies) }
+    }
+
+    pub fn trusted_proxies(&self) -> &[IpAddr] {
+        &self.trusted_proxies
+    }
+
+    /// Append an entry to the audit log. Failures are logged and never abort the audited operation.
+    pub async fn record(
+        &self,
+        ctx: &AuditContext,
+        action: AuditAction,
+        target_type: &str,
+        target_id: Option<String>,
+        before: Option<Document>,
+        after: Option<Document>,
+    ) {
+        let event = AuditEvent {
+            _id: None,
+            actor_id: ctx.actor_id(),
+            action,
+            target_type: target_type.to_string(),
+            target_id,
+            before: before.map(redact),
+            after: after.map(redact),
+            ip: ctx.ip.clone(),
+            request_id: ctx.request_id.clone(),
+            created_at: DateTime::now(),
+        };
+
+        if let Err(e) = self.collection.insert_one(event, None).await {
+            eprintln!("Error while writing audit event: {:?}", e);
+        }
+    }
+
+    /// Record an update, storing only the fields that changed.
+    pub async fn record_update(&self, ctx: &AuditContext, target_type: &str, target_id: &str, before: &Document, after: &Document) {
+        let (before, after) = diff(before, after);
+        self.record(ctx, AuditAction::Update, target_type, Some(target_id.to_string()), Some(before), Some(after))
+            .await;
+    }
+
+    /// Query the audit log, newest entries first.
+    pub async fn search(&self, filter: Document, limit: i64, skip: u64) -> Result<Vec<AuditEvent>, MongoError> {
+        let options = FindOptions::builder()
+            .sort(doc! { "created_at": -1 })
+            .limit(limit)
+            .skip(skip)
+            .build();
+        let mut cursor = self.collection.find(filter, options).await?;
+        let mut results = Vec::new();
+
+        while let Some(doc) = cursor.next().await {
+            match doc {
+                Ok(event) => results.push(event),
+                Err(err) => return Err(err),
+            }
+        }
+
+        Ok(results)
+    }
+}
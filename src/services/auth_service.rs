@@ -17,7 +17,7 @@ impl ApiService {
         // Find the user by email
         if let Some(user) = self
             .collection
-            .find_one(doc! { "email": &credentials.email }, None)
+            .find_one(doc! { "email": &credentials.email, "deleted_at": null }, None)
             .await?
         {
             // Verify the provided password against the hashed password
@@ -0,0 +1,56 @@
+use crate::models::author_model::{Author, AuthorRequest};
+use crate::services::reference_service::{
+    name_and_slug, validate_url, ApiService as ReferenceService, ApiServiceError, ReferenceEntity,
+};
+use mongodb::bson::{doc, oid::ObjectId, DateTime, Document};
+
+/// Authors are managed by the shared reference service.
+pub type ApiService = ReferenceService<Author>;
+
+impl ReferenceEntity for Author {
+    type Request = AuthorRequest;
+
+    const KIND: &'static str = "author";
+    const ID_FIELD: &'static str = "author_id";
+
+    fn id(&self) -> Option<ObjectId> {
+        self._id
+    }
+
+    fn set_id(&mut self, id: Option<ObjectId>) {
+        self._id = id;
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn named(name: String, slug: String, now: DateTime) -> Author {
+        Author { _id: None, name, slug, bio: None, homepage_url: None, created_at: now, updated_at: now }
+    }
+
+    fn from_request(req: &AuthorRequest, existing: Option<&Author>) -> Result<Author, ApiServiceError> {
+        let (name, slug) = name_and_slug(&req.name)?;
+        let now = DateTime::now();
+        Ok(Author {
+            _id: existing.and_then(|existing| existing._id),
+            name,
+            slug,
+            bio: req.bio.as_deref().map(str::trim).filter(|bio| !bio.is_empty()).map(str::to_string),
+            homepage_url: validate_url("homepage_url", &req.homepage_url)?,
+            created_at: existing.map_or(now, |existing| existing.created_at),
+            updated_at: now,
+        })
+    }
+
+    fn to_document(&self) -> Document {
+        doc! {
+            "name"         : self.name.clone(),
+            "slug"         : self.slug.clone(),
+            "bio"          : self.bio.clone(),
+            "homepage_url" : self.homepage_url.clone(),
+            "created_at"   : self.created_at,
+            "updated_at"   : self.updated_at,
+        }
+    }
+}
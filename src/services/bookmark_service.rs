@@ -0,0 +1,257 @@
+use crate::models::{
+    bookmark_model::{BookmarkItem, BookmarkItemView, BookmarkList, BookmarkListView},
+    course_model::Course,
+};
+use crate::utils::mongo::is_duplicate_key;
+use futures::stream::StreamExt;
+use mongodb::{
+    bson::{doc, from_document, oid::ObjectId, Bson, DateTime, Document},
+    error::Error as MongoError,
+    options::IndexOptions,
+    results::{DeleteResult, InsertOneResult, UpdateResult},
+    Collection, IndexModel,
+};
+use serde::Deserialize;
+use std::collections::{HashMap, HashSet};
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum ApiServiceError {
+    #[error("Invalid ObjectId format")]
+    InvalidObjectId,
+    #[error("A list with this name already exists")]
+    DuplicateName,
+    #[error("The course is already in the list")]
+    AlreadyBookmarked,
+    #[error("The new order must list every course of the list exactly once")]
+    InvalidOrder,
+    #[error("The list changed while it was being reordered")]
+    ListChanged,
+    #[error("Invalid aggregation result: {0}")]
+    InvalidResult(#[from] bson::de::Error),
+    #[error("Database error: {0}")]
+    DatabaseError(#[from] MongoError),
+}
+
+// A list joined with its courses by `$lookup`
+#[derive(Debug, Deserialize)]
+struct BookmarkListWithCourses {
+    courses: Vec<Course>,
+}
+
+#[derive(Clone)]
+pub struct ApiService {
+    collection: Collection<BookmarkList>,
+    course_collection_name: String, // Joined with `$lookup`
+}
+
+// Helper function to put the joined courses back in the order of the list items.
+fn to_view(list: BookmarkList, joined: BookmarkListWithCourses) -> Option<BookmarkListView> {
+    let mut courses: HashMap<ObjectId, Course> = joined
+        .courses
+        .into_iter()
+        .filter(|course| course.deleted_at.is_none())
+        .filter_map(|course| course._id.map(|id| (id, course)))
+        .collect();
+    let items = list
+        .items
+        .into_iter()
+        .map(|item| BookmarkItemView {
+            course_id: item.course_id,
+            course: courses.remove(&item.course_id),
+            note: item.note,
+            added_at: item.added_at,
+        })
+        .collect();
+
+    Some(BookmarkListView {
+        _id: list._id?,
+        name: list.name,
+        items,
+        created_at: list.created_at,
+        updated_at: list.updated_at,
+    })
+}
+
+impl ApiService {
+    pub fn new(collection: Collection<BookmarkList>, course_collection_name: String) -> ApiService {
+        ApiService { collection, course_collection_name }
+    }
+
+    /// Create the unique index on the list names of each user.
+    pub async fn ensure_indexes(&self) -> Result<(), MongoError> {
+        let options = IndexOptions::builder().name("unique_user_list_name".to_string()).unique(true).build();
+        let index = IndexModel::builder()
+            .keys(doc! { "user_id": 1, "name": 1 })
+            .options(options)
+            .build();
+        self.collection.create_index(index, None).await?;
+        Ok(())
+    }
+
+    // Run the `$lookup` joining lists with their courses.
+    async fn get_views(&self, filter: Document) -> Result<Vec<BookmarkListView>, ApiServiceError> {
+        let pipeline = vec![
+            doc! { "$match": filter },
+            doc! { "$sort": { "created_at": 1 } },
+            doc! { "$lookup": {
+                "from": &self.course_collection_name,
+                "localField": "items.course_id",
+                "foreignField": "_id",
+                "as": "courses",
+            } },
+        ];
+        let mut cursor = self.collection.aggregate(pipeline, None).await?;
+        let mut views = Vec::new();
+
+        while let Some(result) = cursor.next().await {
+            let document = result?;
+            let list: BookmarkList = from_document(document.clone())?;
+            let joined: BookmarkListWithCourses = from_document(document)?;
+            views.extend(to_view(list, joined));
+        }
+
+        Ok(views)
+    }
+
+    /// Get the lists of a user with the details of their courses.
+    pub async fn get_by_user(&self, user_id: ObjectId) -> Result<Vec<BookmarkListView>, ApiServiceError> {
+        self.get_views(doc! { "user_id": user_id }).await
+    }
+
+    /// Get a list of a user with the details of its courses.
+    pub async fn get_by_id(&self, user_id: ObjectId, list_id: &str) -> Result<Option<BookmarkListView>, ApiServiceError> {
+        let object_id = ObjectId::parse_str(list_id).map_err(|_| ApiServiceError::InvalidObjectId)?;
+        let views = self.get_views(doc! { "_id": object_id, "user_id": user_id }).await?;
+        Ok(views.into_iter().next())
+    }
+
+    /// Create an empty list.
+    pub async fn create(&self, user_id: ObjectId, name: &str) -> Result<InsertOneResult, ApiServiceError> {
+        let now = DateTime::now();
+        let list = BookmarkList {
+            _id: None,
+            user_id,
+            name: name.to_string(),
+            items: Vec::new(),
+            created_at: now,
+            updated_at: now,
+        };
+        match self.collection.insert_one(list, None).await {
+            Ok(result) => Ok(result),
+            Err(e) if is_duplicate_key(&e) => Err(ApiServiceError::DuplicateName),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Rename a list.
+    pub async fn rename(&self, user_id: ObjectId, list_id: &str, name: &str) -> Result<UpdateResult, ApiServiceError> {
+        let object_id = ObjectId::parse_str(list_id).map_err(|_| ApiServiceError::InvalidObjectId)?;
+        let filter = doc! { "_id": object_id, "user_id": user_id };
+        let update = doc! { "$set": { "name": name, "updated_at": DateTime::now() } };
+        match self.collection.update_one(filter, update, None).await {
+            Ok(result) => Ok(result),
+            Err(e) if is_duplicate_key(&e) => Err(ApiServiceError::DuplicateName),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Delete a list and everything saved in it.
+    pub async fn delete(&self, user_id: ObjectId, list_id: &str) -> Result<DeleteResult, ApiServiceError> {
+        let object_id = ObjectId::parse_str(list_id).map_err(|_| ApiServiceError::InvalidObjectId)?;
+        let result = self.collection.delete_one(doc! { "_id": object_id, "user_id": user_id }, None).await?;
+        Ok(result)
+    }
+
+    /// Save a course in a list, at `position` or at the end.
+    pub async fn add_item(
+        &self,
+        user_id: ObjectId,
+        list_id: &str,
+        course_id: ObjectId,
+        note: Option<String>,
+        position: Option<u32>,
+    ) -> Result<UpdateResult, ApiServiceError> {
+        let object_id = ObjectId::parse_str(list_id).map_err(|_| ApiServiceError::InvalidObjectId)?;
+        let item = BookmarkItem { course_id, note, added_at: DateTime::now() };
+        let item = bson::to_document(&item).map_err(|e| MongoError::custom(e.to_string()))?;
+
+        let mut push = doc! { "$each": [item] };
+        if let Some(position) = position {
+            push.insert("$position", position);
+        }
+        let filter = doc! { "_id": object_id, "user_id": user_id, "items.course_id": { "$ne": course_id } };
+        let update = doc! { "$push": { "items": push }, "$set": { "updated_at": DateTime::now() } };
+        let result = self.collection.update_one(filter, update, None).await?;
+
+        // Nothing matched, either the list is missing or the course is already saved
+        if result.matched_count == 0 {
+            let exists = doc! { "_id": object_id, "user_id": user_id };
+            if self.collection.count_documents(exists, None).await? > 0 {
+                return Err(ApiServiceError::AlreadyBookmarked);
+            }
+        }
+        Ok(result)
+    }
+
+    /// Change the note of a saved course.
+    pub async fn update_note(
+        &self,
+        user_id: ObjectId,
+        list_id: &str,
+        course_id: ObjectId,
+        note: Option<String>,
+    ) -> Result<UpdateResult, ApiServiceError> {
+        let object_id = ObjectId::parse_str(list_id).map_err(|_| ApiServiceError::InvalidObjectId)?;
+        let filter = doc! { "_id": object_id, "user_id": user_id, "items.course_id": course_id };
+        let update = doc! { "$set": { "items.$.note": note, "updated_at": DateTime::now() } };
+        Ok(self.collection.update_one(filter, update, None).await?)
+    }
+
+    /// Remove a course from a list.
+    pub async fn remove_item(&self, user_id: ObjectId, list_id: &str, course_id: ObjectId) -> Result<UpdateResult, ApiServiceError> {
+        let object_id = ObjectId::parse_str(list_id).map_err(|_| ApiServiceError::InvalidObjectId)?;
+        let filter = doc! { "_id": object_id, "user_id": user_id, "items.course_id": course_id };
+        let update = doc! { "$pull": { "items": { "course_id": course_id } }, "$set": { "updated_at": DateTime::now() } };
+        Ok(self.collection.update_one(filter, update, None).await?)
+    }
+
+    /// Reorder the courses of a list. `course_ids` must list every saved course exactly once.
+    /// Returns `None` when the list does not exist.
+    pub async fn reorder(
+        &self,
+        user_id: ObjectId,
+        list_id: &str,
+        course_ids: &[ObjectId],
+    ) -> Result<Option<UpdateResult>, ApiServiceError> {
+        let object_id = ObjectId::parse_str(list_id).map_err(|_| ApiServiceError::InvalidObjectId)?;
+        let filter = doc! { "_id": object_id, "user_id": user_id };
+        // Items are moved as stored, so the update can check they did not change meanwhile
+        let raw = self.collection.clone_with_type::<Document>();
+        let items = match raw.find_one(filter.clone(), None).await? {
+            Some(list) => list.get_array("items").cloned().unwrap_or_default(),
+            None => return Ok(None),
+        };
+
+        let unique: HashSet<&ObjectId> = course_ids.iter().collect();
+        let mut by_course: HashMap<ObjectId, Bson> = items
+            .iter()
+            .filter_map(|item| Some((item.as_document()?.get_object_id("course_id").ok()?, item.clone())))
+            .collect();
+        if unique.len() != course_ids.len() || course_ids.len() != items.len() || by_course.len() != items.len() {
+            return Err(ApiServiceError::InvalidOrder);
+        }
+        let mut reordered = Vec::new();
+        for course_id in course_ids {
+            reordered.push(by_course.remove(course_id).ok_or(ApiServiceError::InvalidOrder)?);
+        }
+
+        let guarded = doc! { "_id": object_id, "user_id": user_id, "items": items };
+        let update = doc! { "$set": { "items": reordered, "updated_at": DateTime::now() } };
+        let result = self.collection.update_one(guarded, update, None).await?;
+        if result.matched_count == 0 {
+            return Err(ApiServiceError::ListChanged);
+        }
+        Ok(Some(result))
+    }
+}
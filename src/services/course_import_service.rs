@@ -0,0 +1,221 @@
+use crate::extractors::audit_extractor::AuditContext;
+use crate::models::{
+    course_import_model::{
+        CourseImportRow, CsvCourseImportRow, ImportFormat, ImportReport, ImportRowResult, ImportRowStatus,
+    },
+    course_model::Course,
+};
+use crate::services::course_service::{ApiService as CourseService, ApiServiceError as CourseError};
+use mongodb::{bson::{oid::ObjectId, DateTime}, error::Error as MongoError};
+use std::collections::HashMap;
+
+/// Bulk import of courses from CSV or NDJSON. Rows are validated one by one and upserted by
+/// `url`, a row failing never stops the import.
+#[derive(Clone)]
+pub struct ApiService {
+    course_service: CourseService,
+}
+
+// Helper function to split the CSV topics column.
+fn split_topics(topics: &str) -> Vec<String> {
+    topics.split(';').map(|t| t.trim().to_string()).filter(|t| !t.is_empty()).collect()
+}
+
+// Helper function to parse every row of the file, with its 1-based row number.
+fn parse_rows(format: ImportFormat, data: &[u8]) -> Vec<(usize, Result<CourseImportRow, String>)> {
+    match format {
+        ImportFormat::Csv => csv::ReaderBuilder::new()
+            .trim(csv::Trim::All)
+            .from_reader(data)
+            .deserialize::<CsvCourseImportRow>()
+            .enumerate()
+            .map(|(i, row)| {
+                let row = row
+                    .map(|row| CourseImportRow {
+                        title: row.title,
+                        platform: row.platform,
+                        author: row.author,
+                        duration: row.duration,
+                        language: row.language,
+                        description: row.description,
+                        url: row.url,
+                        topics: split_topics(&row.topics),
+                    })
+                    .map_err(|e| e.to_string());
+                (i + 1, row)
+            })
+            .collect(),
+        ImportFormat::Ndjson => String::from_utf8_lossy(data)
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .map(|(i, line)| (i + 1, serde_json::from_str::<CourseImportRow>(line).map_err(|e| e.to_string())))
+            .collect(),
+    }
+}
+
+// Helper function to check a row against the rules of `Course`.
+fn validate(row: &CourseImportRow) -> Result<(), String> {
+    for (field, value) in [("title", &row.title), ("platform", &row.platform), ("author", &row.author), ("url", &row.url)] {
+        if value.trim().is_empty() {
+            return Err(format!("{} must not be empty", field));
+        }
+    }
+    if row.duration <= 0 {
+        return Err("duration must be a positive number of minutes".to_string());
+    }
+    match url::Url::parse(row.url.trim()) {
+        Ok(url) if url.scheme() == "http" || url.scheme() == "https" => Ok(()),
+        Ok(_) => Err("url must use http or https".to_string()),
+        Err(e) => Err(format!("Invalid url: {}", e)),
+    }
+}
+
+// Helper function to turn a valid row into a new `Course`.
+fn to_course(row: CourseImportRow) -> Course {
+    let now = DateTime::now();
+    Course {
+        _id: None,
+        title: row.title.trim().to_string(),
+        platform: row.platform.trim().to_string(),
+        author: row.author.trim().to_string(),
+        platform_id: None,
+        author_id: None,
+        duration: row.duration,
+        language: row.language.trim().to_string(),
+        description: row.description,
+        url: row.url.trim().to_string(),
+        topics: row.topics.iter().map(|t| t.trim().to_string()).filter(|t| !t.is_empty()).collect(),
+        created_at: now,
+        updated_at: now,
+        rating_average: 0.0,
+        rating_count: 0,
+        rating_sum: 0,
+        link_checks: vec![],
+        link_failures: 0,
+        link_broken_at: None,
+        deleted_at: None,
+    }
+}
+
+impl ApiService {
+    pub fn new(course_service: CourseService) -> ApiService {
+        ApiService { course_service }
+    }
+
+    /// Import a CSV or NDJSON file. With `dry_run`, rows are validated and matched against
+    /// the existing courses but nothing is written. Imports are admin only, missing platforms and
+    /// authors are created.
+    pub async fn import(&self, format: ImportFormat, data: &[u8], dry_run: bool, ctx: &AuditContext) -> ImportReport {
+        let mut report = ImportReport { dry_run, ..Default::default() };
+        // Rows met earlier in a dry run by URL, as they would have been written, so repeated rows are
+        // compared with them instead of the stored course
+        let mut seen: HashMap<String, (Option<ObjectId>, Course)> = HashMap::new();
+
+        for (row_number, row) in parse_rows(format, data) {
+            let result = match row.and_then(|row| validate(&row).map(|_| row)) {
+                Ok(row) => self.import_row(to_course(row), dry_run, &mut seen, ctx).await,
+                Err(error) => Err(error),
+            };
+
+            let row_result = match result {
+                Ok((status, url, course_id)) => ImportRowResult { row: row_number, status, url: Some(url), course_id, error: None },
+                Err(error) => ImportRowResult {
+                    row: row_number,
+                    status: ImportRowStatus::Failed,
+                    url: None,
+                    course_id: None,
+                    error: Some(error),
+                },
+            };
+            match row_result.status {
+                ImportRowStatus::Created => report.created += 1,
+                ImportRowStatus::Updated => report.updated += 1,
+                ImportRowStatus::Unchanged => report.unchanged += 1,
+                ImportRowStatus::Failed => report.failed += 1,
+            }
+            report.rows.push(row_result);
+        }
+
+        report
+    }
+
+    // Upsert one course by `url`. Returns the outcome, the url and the `_id` of the course.
+    async fn import_row(
+        &self,
+        course: Course,
+        dry_run: bool,
+        seen: &mut HashMap<String, (Option<ObjectId>, Course)>,
+        ctx: &AuditContext,
+    ) -> Result<(ImportRowStatus, String, Option<ObjectId>), String> {
+        if dry_run {
+            return self.check_row(course, seen, ctx).await;
+        }
+        let url = course.url.clone();
+        let existing = self.course_service.get_by_url(&url).await.map_err(|e| format!("Database error: {}", e))?;
+        let (status, course_id) = match existing {
+            Some(existing) => self.update_existing(course, existing, dry_run, ctx).await?,
+            None => match self.course_service.create(&course, true, ctx).await {
+                Ok(result) => (ImportRowStatus::Created, result.inserted_id.as_object_id()),
+                // Another import created it meanwhile, the unique index on `url` kept a single course
+                Err(CourseError::DuplicateUrl) => {
+                    match self.course_service.get_by_url(&url).await.map_err(|e| format!("Database error: {}", e))? {
+                        Some(existing) => self.update_existing(course, existing, dry_run, ctx).await?,
+                        None => return Err("url belongs to a deleted course, restore it instead".to_string()),
+                    }
+                }
+                Err(e) => return Err(format!("Database error: {}", e)),
+            },
+        };
+        Ok((status, url, course_id))
+    }
+
+    // Helper function to find the outcome of a row in a dry run. A row repeating a URL is compared
+    // with the earlier row, as if that one had been written.
+    async fn check_row(
+        &self,
+        course: Course,
+        seen: &mut HashMap<String, (Option<ObjectId>, Course)>,
+        ctx: &AuditContext,
+    ) -> Result<(ImportRowStatus, String, Option<ObjectId>), String> {
+        let db_error = |e: MongoError| format!("Database error: {}", e);
+        let url = course.url.clone();
+        let mut written = course.clone();
+        written.topics = self.course_service.canonical_topics(&course.topics).await.map_err(db_error)?;
+
+        let (status, course_id) = match seen.get(&url) {
+            Some((course_id, earlier)) => {
+                let differs = self.course_service.differs(earlier, &course).await.map_err(db_error)?;
+                (if differs { ImportRowStatus::Updated } else { ImportRowStatus::Unchanged }, *course_id)
+            }
+            None => match self.course_service.get_by_url(&url).await.map_err(db_error)? {
+                Some(existing) => self.update_existing(course, existing, true, ctx).await?,
+                None => (ImportRowStatus::Created, None),
+            },
+        };
+        seen.insert(url.clone(), (course_id, written));
+        Ok((status, url, course_id))
+    }
+
+    // Helper function to write a row over the course with the same `url`, unless nothing changed.
+    async fn update_existing(
+        &self,
+        mut course: Course,
+        existing: Course,
+        dry_run: bool,
+        ctx: &AuditContext,
+    ) -> Result<(ImportRowStatus, Option<ObjectId>), String> {
+        course.created_at = existing.created_at;
+        let differs = self.course_service.differs(&existing, &course).await.map_err(|e| format!("Database error: {}", e))?;
+        if !differs {
+            return Ok((ImportRowStatus::Unchanged, existing._id));
+        }
+        if let (Some(course_id), false) = (existing._id, dry_run) {
+            self.course_service
+                .update(&course, &course_id.to_hex(), true, ctx)
+                .await
+                .map_err(|e| format!("Database error: {}", e))?;
+        }
+        Ok((ImportRowStatus::Updated, existing._id))
+    }
+}
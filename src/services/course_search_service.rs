@@ -1,21 +1,243 @@
 use crate::models::course_model::Course;
-use mongodb::{bson::Document, error::Error as MongoError};
+use crate::models::course_search_model::{
+    CourseSearchCriteria, CourseSearchParams, CourseSearchSort, DurationBucket, FacetCount, FacetedSearchResult, MatchMode,
+    SearchFacets, SortOrder,
+};
+use mongodb::{bson::{doc, Bson, DateTime, Document}, error::Error as MongoError, options::FindOptions, Cursor};
+use crate::services::topic_service::{ApiService as TopicService, TopicExpansion};
+use crate::utils::mongo::escape_regex;
 use futures::stream::StreamExt;
+use thiserror::Error;
+
+// Nested groups deeper than this are rejected
+const MAX_GROUP_DEPTH: usize = 4;
+// Values returned per facet, the most frequent first
+const FACET_LIMIT: i64 = 20;
+// Hits per page of a faceted search, by default and at most
+const SEARCH_HITS_LIMIT: i64 = 20;
+const MAX_SEARCH_HITS_LIMIT: i64 = 100;
+// Lower bounds of the duration buckets, in minutes
+const DURATION_BOUNDARIES: [i32; 6] = [0, 30, 60, 180, 600, 1200];
+
+#[derive(Debug, Error)]
+pub enum ApiServiceError {
+    #[error("Invalid search: {0}")]
+    InvalidSearch(String),
+    #[error("Invalid aggregation result: {0}")]
+    InvalidResult(#[from] bson::de::Error),
+    #[error("Database error: {0}")]
+    DatabaseError(#[from] MongoError),
+}
 
 #[derive(Clone)]
 pub struct ApiService {
     collection: mongodb::Collection<Course>,
+    topics: TopicService, // Expands searched topics to their synonyms and descendants
+}
+
+// Helper function to add an inclusive lower and exclusive upper date bound on a field.
+fn push_date_range(filters: &mut Vec<Document>, field: &str, after: Option<chrono::DateTime<chrono::Utc>>, before: Option<chrono::DateTime<chrono::Utc>>) -> Result<(), ApiServiceError> {
+    if let (Some(after), Some(before)) = (after, before) {
+        if after >= before {
+            return Err(ApiServiceError::InvalidSearch(format!("{} range is empty", field)));
+        }
+    }
+    let mut range = Document::new();
+    if let Some(after) = after {
+        range.insert("$gte", DateTime::from_chrono(after));
+    }
+    if let Some(before) = before {
+        range.insert("$lt", DateTime::from_chrono(before));
+    }
+    if !range.is_empty() {
+        filters.push(doc! { field: range });
+    }
+    Ok(())
+}
+
+// Helper function to combine filters, `None` when there are none.
+fn combine(operator: &str, mut filters: Vec<Document>) -> Option<Document> {
+    match filters.len() {
+        0 => None,
+        1 if operator == "$and" || operator == "$or" => filters.pop(),
+        _ => Some(doc! { operator: filters }),
+    }
+}
+
+// Helper function to build the filter of one search node and its nested groups.
+fn criteria_filter(c: &CourseSearchCriteria, expansion: &TopicExpansion, depth: usize) -> Result<Option<Document>, ApiServiceError> {
+    if depth > MAX_GROUP_DEPTH {
+        return Err(ApiServiceError::InvalidSearch(format!("Groups can be nested at most {} levels deep", MAX_GROUP_DEPTH)));
+    }
+
+    // Text fields, combined with `match`
+    let mut fields = vec![];
+    if let Some(ref title) = c.title {
+        fields.push(doc! { "title": { "$regex": title, "$options": "i" } });
+    }
+    if let Some(ref author) = c.author {
+        fields.push(doc! { "author": { "$regex": author, "$options": "i" } });
+    }
+    if let Some(ref platform) = c.platform {
+        fields.push(doc! { "platform": { "$regex": platform, "$options": "i" } });
+    }
+    if let Some(ref topics) = c.topics {
+        // Each topic matches any of its expanded values
+        let expanded: Vec<Vec<String>> = topics
+            .iter()
+            .map(|topic| expansion.get(topic).cloned().unwrap_or_else(|| vec![topic.clone()]))
+            .collect();
+        match c.topics_match.unwrap_or_default() {
+            MatchMode::Any => fields.push(doc! { "topics": { "$in": expanded.concat() } }),
+            MatchMode::All => {
+                let each = expanded.into_iter().map(|values| doc! { "topics": { "$in": values } }).collect();
+                fields.extend(combine("$and", each));
+            }
+        }
+    }
+
+    let mut filters = vec![];
+    let fields_operator = match c.match_mode.unwrap_or_default() {
+        MatchMode::Any => "$or",
+        MatchMode::All => "$and",
+    };
+    filters.extend(combine(fields_operator, fields));
+
+    // Restrictions, they always apply
+    if let Some(ref language) = c.language {
+        filters.push(doc! { "language": { "$regex": format!("^{}$", escape_regex(language)), "$options": "i" } });
+    }
+    if let (Some(min), Some(max)) = (c.min_duration, c.max_duration) {
+        if min > max {
+            return Err(ApiServiceError::InvalidSearch("min_duration is greater than max_duration".to_string()));
+        }
+    }
+    let mut duration = Document::new();
+    if let Some(min) = c.min_duration {
+        duration.insert("$gte", min);
+    }
+    if let Some(max) = c.max_duration {
+        duration.insert("$lte", max);
+    }
+    if !duration.is_empty() {
+        filters.push(doc! { "duration": duration });
+    }
+    if let Some(min_rating) = c.min_rating {
+        filters.push(doc! { "rating_average": { "$gte": min_rating } });
+    }
+    push_date_range(&mut filters, "created_at", c.created_after, c.created_before)?;
+    push_date_range(&mut filters, "updated_at", c.updated_after, c.updated_before)?;
+
+    // Nested groups
+    for (operator, groups) in [("$and", &c.all), ("$or", &c.any), ("$nor", &c.not)] {
+        let Some(groups) = groups else { continue };
+        let mut group_filters = vec![];
+        for group in groups {
+            match criteria_filter(group, expansion, depth + 1)? {
+                Some(filter) => group_filters.push(filter),
+                None => return Err(ApiServiceError::InvalidSearch("Search groups cannot be empty".to_string())),
+            }
+        }
+        filters.extend(combine(operator, group_filters));
+    }
+
+    Ok(combine("$and", filters))
+}
+
+/// Build the MongoDB filter of a search. Fails when the search is empty or inconsistent.
+pub fn build_filter(params: &CourseSearchParams, expansion: &TopicExpansion) -> Result<Document, ApiServiceError> {
+    criteria_filter(&params.criteria, expansion, 0)?
+        .ok_or_else(|| ApiServiceError::InvalidSearch("At least one search parameter must be provided".to_string()))
+}
+
+/// Build the MongoDB sort document of a search, `None` keeps the natural order.
+pub fn build_sort(params: &CourseSearchParams) -> Option<Document> {
+    let sort = params.sort?;
+    let (fields, natural): (&[&str], SortOrder) = match sort {
+        CourseSearchSort::Rating => (&["rating_average", "rating_count"], SortOrder::Desc),
+        CourseSearchSort::Title => (&["title"], SortOrder::Asc),
+        CourseSearchSort::Duration => (&["duration"], SortOrder::Asc),
+        CourseSearchSort::CreatedAt => (&["created_at"], SortOrder::Desc),
+        CourseSearchSort::UpdatedAt => (&["updated_at"], SortOrder::Desc),
+    };
+    let direction = match params.order.unwrap_or(natural) {
+        SortOrder::Asc => 1,
+        SortOrder::Desc => -1,
+    };
+
+    let mut document = Document::new();
+    for field in fields {
+        document.insert(*field, direction);
+    }
+    // Stable pagination between equal values
+    document.insert("_id", direction);
+    Some(document)
+}
+
+// Helper function to build the filter of a facet: the search without its own top-level filter,
+// so the facet shows what selecting another value would return.
+fn facet_filter(
+    params: &CourseSearchParams,
+    expansion: &TopicExpansion,
+    clear: fn(&mut CourseSearchCriteria),
+) -> Result<Document, ApiServiceError> {
+    let mut criteria = params.criteria.clone();
+    clear(&mut criteria);
+    let filter = criteria_filter(&criteria, expansion, 0)?.unwrap_or_default();
+    Ok(doc! { "$match": filter })
+}
+
+// Helper function to read the `{ _id, count }` documents of a facet.
+fn facet_counts(result: &Document, facet: &str) -> Vec<FacetCount> {
+    let Ok(entries) = result.get_array(facet) else { return vec![] };
+    entries
+        .iter()
+        .filter_map(Bson::as_document)
+        .filter_map(|entry| {
+            let value = entry.get_str("_id").ok()?;
+            let count = entry.get_i32("count").map(i64::from).or_else(|_| entry.get_i64("count")).ok()?;
+            Some(FacetCount { value: value.to_string(), count })
+        })
+        .collect()
+}
+
+// Helper function to read the duration buckets, keyed by their lower bound.
+fn duration_buckets(result: &Document) -> Vec<DurationBucket> {
+    let Ok(entries) = result.get_array("duration") else { return vec![] };
+    entries
+        .iter()
+        .filter_map(Bson::as_document)
+        .filter_map(|entry| {
+            let min_duration = entry.get_i32("_id").ok()?;
+            let count = entry.get_i32("count").map(i64::from).or_else(|_| entry.get_i64("count")).ok()?;
+            let max_duration = DURATION_BOUNDARIES.iter().copied().find(|bound| *bound > min_duration);
+            Some(DurationBucket { min_duration, max_duration, count })
+        })
+        .collect()
 }
 
 impl ApiService {
-    pub fn new(collection: mongodb::Collection<Course>) -> Self {
-        Self { collection }
+    pub fn new(collection: mongodb::Collection<Course>, topics: TopicService) -> Self {
+        Self { collection, topics }
+    }
+
+    /// Expand every topic of a search, nested groups included.
+    pub async fn topic_expansion(&self, params: &CourseSearchParams) -> Result<TopicExpansion, MongoError> {
+        fn collect(c: &CourseSearchCriteria, topics: &mut Vec<String>) {
+            topics.extend(c.topics.iter().flatten().cloned());
+            for group in [&c.all, &c.any, &c.not].into_iter().flatten().flatten() {
+                collect(group, topics);
+            }
+        }
+        let mut topics = vec![];
+        collect(&params.criteria, &mut topics);
+        self.topics.expand(&topics).await
     }
 
-    /// Search for courses by title, author, or platform with case-insensitive matching.
-    /// At least one of the fields must match.
-    pub async fn search(&self, filter: Document) -> Result<Vec<Course>, MongoError> {
-        let mut cursor = self.collection.find(filter, None).await?;
+    /// Search for courses matching a filter built with `build_filter`.
+    /// `sort` is a MongoDB sort document, the natural order is kept when missing.
+    pub async fn search(&self, filter: Document, sort: Option<Document>) -> Result<Vec<Course>, MongoError> {
+        let mut cursor = self.search_stream(filter, sort).await?;
         let mut results = Vec::new();
 
         while let Some(doc) = cursor.next().await {
@@ -27,4 +249,230 @@ impl ApiService {
 
         Ok(results)
     }
+
+    /// Same search as `search`, returning the cursor so the results can be streamed without buffering.
+    pub async fn search_stream(&self, filter: Document, sort: Option<Document>) -> Result<Cursor<Course>, MongoError> {
+        // Deleted documents never show up in search results
+        let filter = doc! { "$and": [filter, { "deleted_at": null }] };
+        let options = FindOptions::builder().sort(sort).build();
+        self.collection.find(filter, options).await
+    }
+
+    /// Search with facet counts, in one aggregation. Every facet is computed with the filters
+    /// of the search except its own, e.g. the platform counts ignore the `platform` filter.
+    pub async fn search_with_facets(&self, params: &CourseSearchParams) -> Result<FacetedSearchResult, ApiServiceError> {
+        let limit = params.limit.unwrap_or(SEARCH_HITS_LIMIT);
+        if !(1..=MAX_SEARCH_HITS_LIMIT).contains(&limit) {
+            return Err(ApiServiceError::InvalidSearch(format!("limit must be between 1 and {}", MAX_SEARCH_HITS_LIMIT)));
+        }
+        let skip = i64::try_from(params.skip.unwrap_or(0))
+            .map_err(|_| ApiServiceError::InvalidSearch("skip is too large".to_string()))?;
+
+        let expansion = self.topic_expansion(params).await?;
+        let filter = build_filter(params, &expansion)?;
+        // A `$facet` result is a single document, so the hits are paged to stay under the size limit
+        let mut hits = vec![doc! { "$match": filter.clone() }];
+        if let Some(sort) = build_sort(params) {
+            hits.push(doc! { "$sort": sort });
+        }
+        hits.push(doc! { "$skip": skip });
+        hits.push(doc! { "$limit": limit });
+        let total = vec![doc! { "$match": filter }, doc! { "$count": "total" }];
+
+        let by_value = |field: &str| vec![doc! { "$sortByCount": format!("${}", field) }, doc! { "$limit": FACET_LIMIT }];
+        let mut platform = vec![facet_filter(params, &expansion, |c| c.platform = None)?];
+        platform.extend(by_value("platform"));
+        let mut author = vec![facet_filter(params, &expansion, |c| c.author = None)?];
+        author.extend(by_value("author"));
+        let mut language = vec![facet_filter(params, &expansion, |c| c.language = None)?];
+        language.extend(by_value("language"));
+        let mut topics = vec![
+            facet_filter(params, &expansion, |c| c.topics = None)?,
+            doc! { "$unwind": "$topics" },
+        ];
+        topics.extend(by_value("topics"));
+        let duration = vec![
+            facet_filter(params, &expansion, |c| {
+                c.min_duration = None;
+                c.max_duration = None;
+            })?,
+            doc! { "$bucket": {
+                "groupBy": "$duration",
+                "boundaries": DURATION_BOUNDARIES.iter().copied().chain([i32::MAX]).collect::<Vec<i32>>(),
+                "default": "other",
+                "output": { "count": { "$sum": 1 } },
+            } },
+        ];
+
+        let pipeline = vec![
+            // Deleted documents never show up in search results
+            doc! { "$match": { "deleted_at": null } },
+            doc! { "$facet": {
+                "hits": hits,
+                "total": total,
+                "platform": platform,
+                "author": author,
+                "language": language,
+                "topics": topics,
+                "duration": duration,
+            } },
+        ];
+        let mut cursor = self.collection.aggregate(pipeline, None).await?;
+        let result = match cursor.next().await {
+            Some(result) => result?,
+            None => Document::new(),
+        };
+
+        let hits = match result.get("hits") {
+            Some(hits) => bson::from_bson(hits.clone())?,
+            None => vec![],
+        };
+        let total = result
+            .get_array("total")
+            .ok()
+            .and_then(|total| total.first())
+            .and_then(Bson::as_document)
+            .and_then(|total| total.get_i32("total").map(i64::from).or_else(|_| total.get_i64("total")).ok())
+            .unwrap_or(0);
+        Ok(FacetedSearchResult {
+            hits,
+            total,
+            facets: SearchFacets {
+                platform: facet_counts(&result, "platform"),
+                author: facet_counts(&result, "author"),
+                language: facet_counts(&result, "language"),
+                topics: facet_counts(&result, "topics"),
+                duration: duration_buckets(&result),
+            },
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn params(value: serde_json::Value) -> CourseSearchParams {
+        serde_json::from_value(value).unwrap()
+    }
+
+    fn filter(value: serde_json::Value) -> Result<Document, ApiServiceError> {
+        build_filter(&params(value), &TopicExpansion::new())
+    }
+
+    fn regex(field: &str, value: &str) -> Document {
+        doc! { field: { "$regex": value, "$options": "i" } }
+    }
+
+    // Helper function to wrap a search in `levels` nested `all` groups.
+    fn nested(levels: usize) -> serde_json::Value {
+        (0..levels).fold(json!({ "title": "rust" }), |search, _| json!({ "all": [search] }))
+    }
+
+    #[test]
+    fn text_fields_are_combined_with_the_match_mode() {
+        let any = filter(json!({ "title": "rust", "author": "ferris" })).unwrap();
+        assert_eq!(any, doc! { "$or": [regex("title", "rust"), regex("author", "ferris")] });
+
+        let all = filter(json!({ "title": "rust", "author": "ferris", "match": "all" })).unwrap();
+        assert_eq!(all, doc! { "$and": [regex("title", "rust"), regex("author", "ferris")] });
+    }
+
+    #[test]
+    fn groups_are_combined_with_and_or_and_nor() {
+        let found = filter(json!({
+            "title": "rust",
+            "all": [{ "platform": "udemy" }],
+            "any": [{ "author": "ferris" }, { "title": "async" }],
+            "not": [{ "language": "fr" }],
+        }))
+        .unwrap();
+        let language = doc! { "language": { "$regex": "^fr$", "$options": "i" } };
+        assert_eq!(found, doc! { "$and": [
+            regex("title", "rust"),
+            regex("platform", "udemy"),
+            { "$or": [regex("author", "ferris"), regex("title", "async")] },
+            { "$nor": [language] },
+        ] });
+    }
+
+    #[test]
+    fn topics_match_any_of_their_expanded_values() {
+        let expansion = TopicExpansion::from([
+            ("rust".to_string(), vec!["rust".to_string(), "rustlang".to_string()]),
+            ("web".to_string(), vec!["web".to_string()]),
+        ]);
+        let any = build_filter(&params(json!({ "topics": ["rust", "web"] })), &expansion).unwrap();
+        assert_eq!(any, doc! { "topics": { "$in": ["rust", "rustlang", "web"] } });
+
+        let all = build_filter(&params(json!({ "topics": ["rust", "web"], "topics_match": "all" })), &expansion).unwrap();
+        assert_eq!(all, doc! { "$and": [
+            { "topics": { "$in": ["rust", "rustlang"] } },
+            { "topics": { "$in": ["web"] } },
+        ] });
+    }
+
+    #[test]
+    fn groups_deeper_than_the_limit_are_rejected() {
+        assert_eq!(filter(nested(MAX_GROUP_DEPTH)).unwrap(), regex("title", "rust"));
+        assert!(matches!(filter(nested(MAX_GROUP_DEPTH + 1)), Err(ApiServiceError::InvalidSearch(_))));
+    }
+
+    #[test]
+    fn empty_searches_and_groups_are_rejected() {
+        assert!(matches!(filter(json!({})), Err(ApiServiceError::InvalidSearch(_))));
+        assert!(matches!(filter(json!({ "title": "rust", "any": [{}] })), Err(ApiServiceError::InvalidSearch(_))));
+    }
+
+    #[test]
+    fn ranges_are_validated_and_bounded() {
+        let found = filter(json!({
+            "min_duration": 10,
+            "max_duration": 60,
+            "created_after": "2024-01-01T00:00:00Z",
+            "created_before": "2024-02-01T00:00:00Z",
+        }))
+        .unwrap();
+        let date = |text: &str| DateTime::from_chrono(text.parse::<chrono::DateTime<chrono::Utc>>().unwrap());
+        assert_eq!(found, doc! { "$and": [
+            { "duration": { "$gte": 10, "$lte": 60 } },
+            { "created_at": { "$gte": date("2024-01-01T00:00:00Z"), "$lt": date("2024-02-01T00:00:00Z") } },
+        ] });
+
+        assert!(matches!(filter(json!({ "min_duration": 60, "max_duration": 10 })), Err(ApiServiceError::InvalidSearch(_))));
+        let empty_dates = json!({ "updated_after": "2024-02-01T00:00:00Z", "updated_before": "2024-02-01T00:00:00Z" });
+        assert!(matches!(filter(empty_dates), Err(ApiServiceError::InvalidSearch(_))));
+    }
+
+    #[test]
+    fn sorts_use_the_natural_order_and_break_ties_by_id() {
+        assert_eq!(build_sort(&params(json!({ "title": "rust" }))), None);
+        assert_eq!(
+            build_sort(&params(json!({ "sort": "rating" }))),
+            Some(doc! { "rating_average": -1, "rating_count": -1, "_id": -1 }),
+        );
+        assert_eq!(build_sort(&params(json!({ "sort": "title" }))), Some(doc! { "title": 1, "_id": 1 }));
+        assert_eq!(build_sort(&params(json!({ "sort": "duration", "order": "desc" }))), Some(doc! { "duration": -1, "_id": -1 }));
+    }
+
+    #[test]
+    fn each_facet_leaves_out_its_own_filter() {
+        let search = params(json!({ "platform": "udemy", "author": "ferris", "match": "all", "min_duration": 10 }));
+        let expansion = TopicExpansion::new();
+        let duration = doc! { "duration": { "$gte": 10 } };
+
+        let platform = facet_filter(&search, &expansion, |c| c.platform = None).unwrap();
+        assert_eq!(platform, doc! { "$match": { "$and": [regex("author", "ferris"), duration.clone()] } });
+
+        let author = facet_filter(&search, &expansion, |c| c.author = None).unwrap();
+        assert_eq!(author, doc! { "$match": { "$and": [regex("platform", "udemy"), duration] } });
+
+        let by_duration = facet_filter(&search, &expansion, |c| c.min_duration = None).unwrap();
+        assert_eq!(by_duration, doc! { "$match": { "$and": [regex("author", "ferris"), regex("platform", "udemy")] } });
+
+        // A facet over the only filter counts every course
+        let only_platform = params(json!({ "platform": "udemy" }));
+        assert_eq!(facet_filter(&only_platform, &expansion, |c| c.platform = None).unwrap(), doc! { "$match": {} });
+    }
 }
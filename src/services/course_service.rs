@@ -1,9 +1,22 @@
-use crate::models::course_model::Course;
+use crate::extractors::audit_extractor::AuditContext;
+use crate::models::{audit_model::AuditAction, course_model::{Course, LinkCheck}};
+use crate::services::{
+    audit_service::ApiService as AuditService,
+    author_service::ApiService as AuthorService,
+    platform_service::ApiService as PlatformService,
+    suggest_service::ApiService as SuggestService,
+    topic_service::ApiService as TopicService,
+};
+use crate::utils::{mongo::is_duplicate_key, slug::slugify};
 use mongodb::{
-    bson::{doc, oid::ObjectId, Document},
+    bson::{doc, oid::ObjectId, Bson, Document},
     error::Error as MongoError,
+    options::{FindOptions, IndexOptions},
     results::{DeleteResult, UpdateResult, InsertOneResult},
+    bson::DateTime,
     Collection,
+    Cursor,
+    IndexModel,
 };
 use futures::stream::StreamExt;
 use thiserror::Error;
@@ -12,6 +25,10 @@ use thiserror::Error;
 pub enum ApiServiceError {
     #[error("Invalid ObjectId format")]
     InvalidObjectId,
+    #[error("Invalid reference: {0}")]
+    InvalidReference(String),
+    #[error("A course with this url already exists")]
+    DuplicateUrl,
     #[error("Database error: {0}")]
     DatabaseError(#[from] MongoError),
 }
@@ -19,6 +36,11 @@ pub enum ApiServiceError {
 #[derive(Clone)]
 pub struct ApiService {
     collection: Collection<Course>,
+    audit: AuditService,
+    suggest: SuggestService, // Autocomplete index, updated on every write
+    topics: TopicService,    // Topics are canonicalized on every write
+    platforms: PlatformService,
+    authors: AuthorService,
 }
 
 // Helper function to convert a `Course` into a MongoDB Document.
@@ -27,6 +49,8 @@ fn course_to_document(c: &Course) -> Document {
         "title"         : c.title.clone(),
         "platform"      : c.platform.clone(),
         "author"        : c.author.clone(),
+        "platform_id"   : c.platform_id,
+        "author_id"     : c.author_id,
         "duration"      : c.duration,
         "language"      : c.language.clone(),
         "description"   : c.description.clone(),
@@ -37,14 +61,70 @@ fn course_to_document(c: &Course) -> Document {
     }
 }
 
+// Helper function to map unique `url` violations to `DuplicateUrl`.
+fn url_error(err: MongoError) -> ApiServiceError {
+    if is_duplicate_key(&err) {
+        ApiServiceError::DuplicateUrl
+    } else {
+        err.into()
+    }
+}
+
 impl ApiService {
-    pub fn new(collection: Collection<Course>) -> ApiService {
-        ApiService { collection }
+    pub fn new(
+        collection: Collection<Course>,
+        audit: AuditService,
+        suggest: SuggestService,
+        topics: TopicService,
+        platforms: PlatformService,
+        authors: AuthorService,
+    ) -> ApiService {
+        ApiService { collection, audit, suggest, topics, platforms, authors }
     }
 
-    /// Get all courses from the collection.
+    // Helper function to get a copy of a course ready to be written: canonical topics, and platform
+    // and author references with their names. Given ids must exist. Names are resolved, and only
+    // created with `create_references` since the platform and author routes are admin only; unknown
+    // names are otherwise kept without a reference.
+    async fn prepare(&self, c: &Course, create_references: bool, ctx: &AuditContext) -> Result<Course, ApiServiceError> {
+        let mut course = Course { topics: self.topics.normalize(&c.topics).await?, ..c.clone() };
+
+        let platform = match c.platform_id {
+            Some(id) => Some(
+                self.platforms
+                    .get(id)
+                    .await?
+                    .ok_or_else(|| ApiServiceError::InvalidReference(format!("Platform {} not found", id)))?,
+            ),
+            None if create_references => self.platforms.resolve(&c.platform, ctx).await?,
+            None => self.platforms.find(&c.platform).await?,
+        };
+        course.platform_id = platform.as_ref().and_then(|platform| platform._id);
+        if let Some(platform) = platform {
+            course.platform = platform.name;
+        }
+
+        let author = match c.author_id {
+            Some(id) => Some(
+                self.authors
+                    .get(id)
+                    .await?
+                    .ok_or_else(|| ApiServiceError::InvalidReference(format!("Author {} not found", id)))?,
+            ),
+            None if create_references => self.authors.resolve(&c.author, ctx).await?,
+            None => self.authors.find(&c.author).await?,
+        };
+        course.author_id = author.as_ref().and_then(|author| author._id);
+        if let Some(author) = author {
+            course.author = author.name;
+        }
+
+        Ok(course)
+    }
+
+    /// Get all courses from the collection, except the deleted ones.
     pub async fn get_all(&self) -> Result<Vec<Course>, MongoError> {
-        let mut cursor = self.collection.find(None, None).await?;
+        let mut cursor = self.collection.find(doc! { "deleted_at": null }, None).await?;
         let mut docs = Vec::new();
 
         while let Some(result) = cursor.next().await {
@@ -57,33 +137,258 @@ impl ApiService {
         Ok(docs)
     }
 
-    /// Get a course by its MongoDB `_id`.
+    /// Open a cursor over all courses, except the deleted ones, to stream them without buffering.
+    pub async fn stream_all(&self) -> Result<Cursor<Course>, MongoError> {
+        self.collection.find(doc! { "deleted_at": null }, None).await
+    }
+
+    /// Get a course by its MongoDB `_id`, unless it is deleted.
     pub async fn get_by_id(&self, course_id: &str) -> Result<Option<Course>, ApiServiceError> {
         let object_id = ObjectId::parse_str(course_id).map_err(|_| ApiServiceError::InvalidObjectId)?;
-        let filter = doc! { "_id": object_id };
+        let filter = doc! { "_id": object_id, "deleted_at": null };
         let result = self.collection.find_one(filter, None).await?;
         Ok(result)
     }
 
-    /// Create a new course in the collection.
-    pub async fn create(&self, c: &Course) -> Result<InsertOneResult, MongoError> {
-        self.collection.insert_one(c, None).await
+    /// Check whether a course exists and is not deleted.
+    pub async fn exists(&self, course_id: ObjectId) -> Result<bool, MongoError> {
+        let count = self.collection.count_documents(doc! { "_id": course_id, "deleted_at": null }, None).await?;
+        Ok(count > 0)
+    }
+
+    /// Find a course by its URL, unless it is deleted.
+    pub async fn get_by_url(&self, url: &str) -> Result<Option<Course>, MongoError> {
+        self.collection.find_one(doc! { "url": url, "deleted_at": null }, None).await
+    }
+
+    /// Get the `_id` of every course, including the deleted ones.
+    pub async fn get_ids(&self) -> Result<Vec<ObjectId>, MongoError> {
+        let ids = self.collection.distinct("_id", None, None).await?;
+        Ok(ids.iter().filter_map(|id| id.as_object_id()).collect())
+    }
+
+    /// Create the unique index on `url`, deleted courses included so restoring one never collides.
+    /// Courses without a link are left out. Fails while duplicates exist.
+    pub async fn ensure_indexes(&self) -> Result<(), MongoError> {
+        let options = IndexOptions::builder()
+            .name("unique_url".to_string())
+            .unique(true)
+            .partial_filter_expression(doc! { "url": { "$gt": "" } })
+            .build();
+        let index = IndexModel::builder().keys(doc! { "url": 1 }).options(options).build();
+        self.collection.create_index(index, None).await?;
+        Ok(())
+    }
+
+    /// The canonical spelling of topics, as a course write would store them.
+    pub async fn canonical_topics(&self, topics: &[String]) -> Result<Vec<String>, MongoError> {
+        self.topics.normalize(topics).await
+    }
+
+    /// Whether writing `c` over `existing` would change anything but the dates. Read only: topics are
+    /// canonicalized and platform and author names compared the way they would be resolved.
+    pub async fn differs(&self, existing: &Course, c: &Course) -> Result<bool, MongoError> {
+        let same_reference = |id: Option<ObjectId>, name: &str, existing_id: Option<ObjectId>, existing_name: &str| match id {
+            Some(id) => Some(id) == existing_id,
+            None => slugify(name) == slugify(existing_name),
+        };
+        Ok(c.title != existing.title
+            || !same_reference(c.platform_id, &c.platform, existing.platform_id, &existing.platform)
+            || !same_reference(c.author_id, &c.author, existing.author_id, &existing.author)
+            || c.duration != existing.duration
+            || c.language != existing.language
+            || c.description != existing.description
+            || c.url != existing.url
+            || self.topics.normalize(&c.topics).await? != existing.topics)
+    }
+
+    /// Create a new course in the collection, with canonical topics and resolved references. Missing
+    /// platforms and authors are created with `create_references`.
+    pub async fn create(&self, c: &Course, create_references: bool, ctx: &AuditContext) -> Result<InsertOneResult, ApiServiceError> {
+        let c = &self.prepare(c, create_references, ctx).await?;
+        let result = self.collection.insert_one(c, None).await.map_err(url_error)?;
+        if let Some(course_id) = result.inserted_id.as_object_id() {
+            self.suggest.upsert(course_id, c);
+        }
+        let target_id = result.inserted_id.as_object_id().map(|id| id.to_hex());
+        self.audit.record(ctx, AuditAction::Create, "course", target_id, None, Some(course_to_document(c))).await;
+        Ok(result)
+    }
+
+    /// Update an existing course by its MongoDB `_id`, with canonical topics and resolved references.
+    /// Missing platforms and authors are created with `create_references`.
+    pub async fn update(&self, c: &Course, course_id: &str, create_references: bool, ctx: &AuditContext) -> Result<UpdateResult, ApiServiceError> {
+        self.update_matching(c, course_id, Document::new(), create_references, ctx).await
+    }
+
+    /// Same as `update`, only when the course still holds the values of `expected`, so background
+    /// jobs never overwrite a concurrent edit. Nothing is matched when the course changed meanwhile.
+    /// Jobs are started by admins, missing references are created.
+    pub async fn update_if_unchanged(&self, c: &Course, expected: &Course, ctx: &AuditContext) -> Result<UpdateResult, ApiServiceError> {
+        let course_id = expected._id.map(|id| id.to_hex()).unwrap_or_default();
+        self.update_matching(c, &course_id, course_to_document(expected), true, ctx).await
+    }
+
+    // Helper function to update a course matching the extra conditions of `guard`.
+    async fn update_matching(
+        &self,
+        c: &Course,
+        course_id: &str,
+        guard: Document,
+        create_references: bool,
+        ctx: &AuditContext,
+    ) -> Result<UpdateResult, ApiServiceError> {
+        let object_id = ObjectId::parse_str(course_id).map_err(|_| ApiServiceError::InvalidObjectId)?;
+        let c = &self.prepare(c, create_references, ctx).await?;
+        let mut filter = doc! { "_id": object_id, "deleted_at": null };
+        filter.extend(guard);
+        let before = self.collection.find_one(filter.clone(), None).await?;
+        let changes = course_to_document(c);
+        let mut set = changes.clone();
+        // A new link is checked from scratch, the history of the old one is kept
+        if before.as_ref().is_some_and(|before| before.url != c.url) {
+            set.insert("link_failures", 0);
+            set.insert("link_broken_at", Bson::Null);
+        }
+        let result = self.collection.update_one(filter, doc! { "$set": set }, None).await.map_err(url_error)?;
+
+        if let (Some(before), true) = (before, result.modified_count > 0) {
+            self.suggest.upsert(object_id, c);
+            self.audit.record_update(ctx, "course", course_id, &course_to_document(&before), &changes).await;
+        }
+        Ok(result)
+    }
+
+    /// Add a rating change to the aggregate rating of a course, e.g. `(4, 1)` for a new 4-star review
+    /// or `(-1, 0)` for a review going from 5 to 4 stars. The average is recomputed in the same update.
+    pub async fn apply_rating(&self, course_id: ObjectId, sum_delta: i64, count_delta: i64) -> Result<UpdateResult, MongoError> {
+        let pipeline = vec![
+            doc! { "$set": {
+                "rating_sum": { "$add": [{ "$ifNull": ["$rating_sum", 0_i64] }, sum_delta] },
+                "rating_count": { "$add": [{ "$ifNull": ["$rating_count", 0_i64] }, count_delta] },
+            } },
+            doc! { "$set": {
+                "rating_average": { "$cond": [
+                    { "$gt": ["$rating_count", 0] },
+                    { "$divide": ["$rating_sum", "$rating_count"] },
+                    0.0,
+                ] },
+            } },
+        ];
+        self.collection.update_one(doc! { "_id": course_id }, pipeline, None).await
     }
 
-    /// Update an existing course by its MongoDB `_id`.
-    pub async fn update(&self, c: &Course, course_id: &str) -> Result<UpdateResult, ApiServiceError> {
+    /// Record a check of the link of `checked`, keeping the last `history` checks. Only written while
+    /// the course still has the checked URL and the failure count the new state was computed from,
+    /// otherwise nothing matches and the check is dropped.
+    pub async fn apply_link_check(
+        &self,
+        checked: &Course,
+        check: &LinkCheck,
+        failures: i32,
+        broken_at: Option<DateTime>,
+        history: i32,
+    ) -> Result<UpdateResult, MongoError> {
+        let check = doc! {
+            "checked_at" : check.checked_at,
+            "ok"         : check.ok,
+            "status"     : check.status,
+            "final_url"  : check.final_url.clone(),
+            "error"      : check.error.clone(),
+        };
+        let update = doc! {
+            "$push": { "link_checks": { "$each": [check], "$slice": -history } },
+            "$set": { "link_failures": failures, "link_broken_at": broken_at },
+        };
+        // A course never checked has no `link_failures` yet
+        let expected_failures = if checked.link_failures == 0 {
+            Bson::Document(doc! { "$in": [0, null] })
+        } else {
+            Bson::Int32(checked.link_failures)
+        };
+        let filter = doc! { "_id": checked._id, "url": &checked.url, "link_failures": expected_failures };
+        self.collection.update_one(filter, update, None).await
+    }
+
+    /// Get the courses whose link is broken, the longest broken first.
+    pub async fn get_broken(&self) -> Result<Vec<Course>, MongoError> {
+        let filter = doc! { "link_broken_at": { "$ne": null }, "deleted_at": null };
+        let options = FindOptions::builder().sort(doc! { "link_broken_at": 1 }).build();
+        let mut cursor = self.collection.find(filter, options).await?;
+        let mut docs = Vec::new();
+
+        while let Some(result) = cursor.next().await {
+            match result {
+                Ok(course) => docs.push(course),
+                Err(err) => return Err(err),
+            }
+        }
+
+        Ok(docs)
+    }
+
+    /// Soft delete a course by its MongoDB `_id`. It stays in the collection with a `deleted_at`
+    /// tombstone until it is restored or purged.
+    pub async fn delete(&self, course_id: &str, ctx: &AuditContext) -> Result<UpdateResult, ApiServiceError> {
         let object_id = ObjectId::parse_str(course_id).map_err(|_| ApiServiceError::InvalidObjectId)?;
-        let filter = doc! { "_id": object_id };
-        let update = doc! { "$set": course_to_document(c) };
+        let filter = doc! { "_id": object_id, "deleted_at": null };
+        let now = DateTime::now();
+        let update = doc! { "$set": { "deleted_at": now } };
         let result = self.collection.update_one(filter, update, None).await?;
+
+        if result.modified_count > 0 {
+            self.suggest.remove(object_id);
+            let after = Some(doc! { "deleted_at": now });
+            self.audit.record(ctx, AuditAction::Delete, "course", Some(course_id.to_string()), None, after).await;
+        }
         Ok(result)
     }
 
-    /// Delete a course by its MongoDB `_id`.
-    pub async fn delete(&self, course_id: &str) -> Result<DeleteResult, ApiServiceError> {
+    /// Restore a soft deleted course by its MongoDB `_id`.
+    pub async fn restore(&self, course_id: &str, ctx: &AuditContext) -> Result<UpdateResult, ApiServiceError> {
         let object_id = ObjectId::parse_str(course_id).map_err(|_| ApiServiceError::InvalidObjectId)?;
-        let filter = doc! { "_id": object_id };
+        let filter = doc! { "_id": object_id, "deleted_at": { "$ne": null } };
+        let before = self.collection.find_one(filter.clone(), None).await?;
+        let update = doc! { "$unset": { "deleted_at": "" } };
+        let result = self.collection.update_one(filter, update, None).await?;
+
+        if let (Some(before), true) = (before, result.modified_count > 0) {
+            self.suggest.upsert(object_id, &before);
+            let before = Some(doc! { "deleted_at": before.deleted_at });
+            self.audit.record(ctx, AuditAction::Restore, "course", Some(course_id.to_string()), before, None).await;
+        }
+        Ok(result)
+    }
+
+    /// Get the soft deleted courses, optionally only the ones deleted before `cutoff`.
+    pub async fn get_deleted(&self, cutoff: Option<DateTime>) -> Result<Vec<Course>, MongoError> {
+        let filter = match cutoff {
+            Some(cutoff) => doc! { "deleted_at": { "$ne": null, "$lt": cutoff } },
+            None => doc! { "deleted_at": { "$ne": null } },
+        };
+        let mut cursor = self.collection.find(filter, None).await?;
+        let mut docs = Vec::new();
+
+        while let Some(result) = cursor.next().await {
+            match result {
+                Ok(course) => docs.push(course),
+                Err(err) => return Err(err),
+            }
+        }
+
+        Ok(docs)
+    }
+
+    /// Permanently remove a soft deleted course.
+    pub async fn purge(&self, course_id: ObjectId, ctx: &AuditContext) -> Result<DeleteResult, MongoError> {
+        let filter = doc! { "_id": course_id, "deleted_at": { "$ne": null } };
+        let before = self.collection.find_one(filter.clone(), None).await?;
         let result = self.collection.delete_one(filter, None).await?;
+
+        if let (Some(before), true) = (before, result.deleted_count > 0) {
+            let before = Some(course_to_document(&before));
+            self.audit.record(ctx, AuditAction::Purge, "course", Some(course_id.to_hex()), before, None).await;
+        }
         Ok(result)
     }
 }
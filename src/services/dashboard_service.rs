@@ -0,0 +1,222 @@
+use crate::models::{
+    dashboard_model::{Breakdown, CourseInProgress, Dashboard, RecentActivity},
+    user_model::User,
+    watched_model::Watched,
+};
+use chrono::{Duration, NaiveDate, Utc};
+use futures::stream::StreamExt;
+use mongodb::{
+    bson::{doc, from_document, oid::ObjectId, Document},
+    error::Error as MongoError,
+    Collection,
+};
+use serde::Deserialize;
+use std::collections::HashSet;
+use thiserror::Error;
+
+const RECENT_ACTIVITY_LIMIT: i64 = 10;
+
+#[derive(Debug, Error)]
+pub enum ApiServiceError {
+    #[error("User has no _id")]
+    MissingUserId,
+    #[error("Invalid aggregation result: {0}")]
+    InvalidResult(#[from] bson::de::Error),
+    #[error("Database error: {0}")]
+    DatabaseError(#[from] MongoError),
+}
+
+// Output of the `$facet` stage
+#[derive(Debug, Deserialize)]
+struct DashboardFacets {
+    totals: Vec<Totals>,
+    courses_in_progress: Vec<CourseInProgress>,
+    topics: Vec<Breakdown>,
+    platforms: Vec<Breakdown>,
+    recent_activity: Vec<RecentActivity>,
+    activity_days: Vec<ActivityDay>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Totals {
+    completed: i64,
+    minutes: f64,
+}
+
+#[derive(Debug, Deserialize)]
+struct ActivityDay {
+    #[serde(rename = "_id")]
+    day: String,
+}
+
+// Helper function to build the stages grouping the records by an expression, e.g. `$course.platform`.
+// Superseded records are left out like in the totals.
+fn breakdown_stages(key: &str) -> Vec<Document> {
+    vec![
+        doc! { "$match": { "superseded": false } },
+        doc! { "$group": {
+            "_id": key,
+            "courses": { "$sum": 1 },
+            "completed": { "$sum": { "$cond": ["$finished", 1, 0] } },
+            "minutes": { "$sum": "$minutes" },
+        } },
+        doc! { "$sort": { "minutes": -1, "_id": 1 } },
+        doc! { "$project": { "_id": 0, "name": "$_id", "courses": 1, "completed": 1, "minutes": 1 } },
+    ]
+}
+
+// Helper function to count the consecutive days with activity, ending today or yesterday.
+fn current_streak(days: &[ActivityDay]) -> i64 {
+    let days: HashSet<NaiveDate> = days
+        .iter()
+        .filter_map(|d| NaiveDate::parse_from_str(&d.day, "%Y-%m-%d").ok())
+        .collect();
+
+    let today = Utc::now().date_naive();
+    let mut day = if days.contains(&today) { today } else { today - Duration::days(1) };
+    let mut streak = 0;
+    while days.contains(&day) {
+        streak += 1;
+        day -= Duration::days(1);
+    }
+    streak
+}
+
+#[derive(Clone)]
+pub struct ApiService {
+    collection: Collection<Watched>,
+    course_collection_name: String, // Joined with `$lookup`
+}
+
+impl ApiService {
+    pub fn new(collection: Collection<Watched>, course_collection_name: String) -> ApiService {
+        ApiService { collection, course_collection_name }
+    }
+
+    /// Build the dashboard of a learner in a single aggregation over their watched records joined
+    /// with the courses. Covers records with the user's `user_id` and the ones listed in `watched_ids`.
+    pub async fn get(&self, user: &User) -> Result<Dashboard, ApiServiceError> {
+        let user_id = user._id.ok_or(ApiServiceError::MissingUserId)?;
+        let listed: Vec<ObjectId> = user
+            .watched_ids
+            .clone()
+            .unwrap_or_default()
+            .iter()
+            .filter_map(|id| ObjectId::parse_str(id).ok())
+            .collect();
+
+        let mut topic_stages = vec![doc! { "$match": { "superseded": false } }, doc! { "$unwind": "$course.topics" }];
+        topic_stages.extend(breakdown_stages("$course.topics"));
+
+        let pipeline = vec![
+            doc! { "$match": { "$or": [ { "user_id": user_id }, { "_id": { "$in": listed } } ] } },
+            // An archived record is superseded when the user restarted the course, its time is then
+            // counted once on the active record. Records archived with no replacement still count.
+            doc! { "$group": {
+                "_id": "$course_id",
+                "records": { "$push": "$$ROOT" },
+                "has_active": { "$max": { "$not": ["$archived"] } },
+            } },
+            doc! { "$unwind": "$records" },
+            doc! { "$replaceRoot": { "newRoot": { "$mergeObjects": [
+                "$records",
+                { "superseded": { "$and": ["$records.archived", "$has_active"] } },
+            ] } } },
+            doc! { "$lookup": {
+                "from": &self.course_collection_name,
+                "let": { "course_id": "$course_id" },
+                "pipeline": [
+                    { "$match": { "$expr": { "$eq": ["$_id", "$$course_id"] }, "deleted_at": null } },
+                ],
+                "as": "course",
+            } },
+            doc! { "$unwind": "$course" }, // Records of deleted and purged courses are left out
+            doc! { "$addFields": {
+                "finished": { "$ne": [{ "$ifNull": ["$finished_at", null] }, null] },
+                "percent_complete": { "$ifNull": ["$percent_complete", 0.0] },
+                "last_position_minutes": { "$ifNull": ["$last_position_minutes", 0.0] },
+            } },
+            // Records finished before progress tracking existed count as complete
+            doc! { "$addFields": {
+                "minutes": { "$multiply": [
+                    "$course.duration",
+                    { "$divide": [{ "$cond": ["$finished", 100.0, "$percent_complete"] }, 100.0] },
+                ] },
+            } },
+            doc! { "$facet": {
+                "totals": [
+                    { "$match": { "superseded": false } },
+                    { "$group": {
+                        "_id": null,
+                        "completed": { "$sum": { "$cond": ["$finished", 1, 0] } },
+                        "minutes": { "$sum": "$minutes" },
+                    } },
+                ],
+                "courses_in_progress": [
+                    { "$match": { "finished": false, "archived": false } },
+                    { "$sort": { "updated_at": -1 } },
+                    { "$project": {
+                        "_id": 0,
+                        "watched_id": "$_id",
+                        "course_id": 1,
+                        "title": "$course.title",
+                        "platform": "$course.platform",
+                        "duration": "$course.duration",
+                        "percent_complete": 1,
+                        "last_position_minutes": 1,
+                        "updated_at": 1,
+                    } },
+                ],
+                "topics": topic_stages,
+                "platforms": breakdown_stages("$course.platform"),
+                "recent_activity": [
+                    { "$sort": { "updated_at": -1 } },
+                    { "$limit": RECENT_ACTIVITY_LIMIT },
+                    { "$project": {
+                        "_id": 0,
+                        "watched_id": "$_id",
+                        "course_id": 1,
+                        "title": "$course.title",
+                        "percent_complete": 1,
+                        "finished_at": 1,
+                        "updated_at": 1,
+                    } },
+                ],
+                // Every day a record was created, started, finished or last updated
+                "activity_days": [
+                    { "$project": { "days": [
+                        { "$dateToString": { "format": "%Y-%m-%d", "date": "$created_at" } },
+                        { "$dateToString": { "format": "%Y-%m-%d", "date": "$started_at" } },
+                        { "$dateToString": { "format": "%Y-%m-%d", "date": "$finished_at" } },
+                        { "$dateToString": { "format": "%Y-%m-%d", "date": "$updated_at" } },
+                    ] } },
+                    { "$unwind": "$days" },
+                    { "$match": { "days": { "$ne": null } } },
+                    { "$group": { "_id": "$days" } },
+                ],
+            } },
+        ];
+
+        let mut cursor = self.collection.aggregate(pipeline, None).await?;
+        let facets = match cursor.next().await {
+            Some(result) => from_document::<DashboardFacets>(result?)?,
+            None => return Err(MongoError::custom("Empty $facet result").into()),
+        };
+
+        let (completed_count, total_minutes_learned) = facets
+            .totals
+            .first()
+            .map_or((0, 0.0), |totals| (totals.completed, totals.minutes));
+
+        Ok(Dashboard {
+            user_id,
+            courses_in_progress: facets.courses_in_progress,
+            completed_count,
+            total_minutes_learned,
+            topics: facets.topics,
+            platforms: facets.platforms,
+            current_streak_days: current_streak(&facets.activity_days),
+            recent_activity: facets.recent_activity,
+        })
+    }
+}
@@ -0,0 +1,167 @@
+use crate::extractors::audit_extractor::AuditContext;
+use crate::fetchers::{FetchError, PageFetcher};
+use crate::models::{
+    course_model::Course,
+    enrichment_model::{CourseMetadata, EnrichmentFailure, EnrichmentJobStatus},
+};
+use crate::services::course_service::{ApiService as CourseService, ApiServiceError as CourseError};
+use crate::utils::metadata::extract;
+use actix_web::rt::time::sleep;
+use mongodb::{bson::DateTime, error::Error as MongoError};
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use thiserror::Error;
+
+const MAX_JOB_FAILURES: usize = 50;
+const JOB_DELAY: Duration = Duration::from_millis(500); // Between two pages, to stay polite with the platforms
+
+#[derive(Debug, Error)]
+pub enum ApiServiceError {
+    #[error("Failed to fetch the page: {0}")]
+    FetchError(#[from] FetchError),
+    #[error("An enrichment job is already running")]
+    JobRunning,
+    #[error("Course error: {0}")]
+    CourseError(#[from] CourseError),
+    #[error("Database error: {0}")]
+    DatabaseError(#[from] MongoError),
+}
+
+/// Fills course fields from the metadata of their page.
+#[derive(Clone)]
+pub struct ApiService {
+    fetcher: Arc<dyn PageFetcher>,
+    course_service: CourseService,
+    job: Arc<Mutex<EnrichmentJobStatus>>, // Status of the last job
+}
+
+// Helper function to copy the metadata onto a course, only into empty fields unless `overwrite`.
+// Returns whether anything changed.
+fn apply(course: &mut Course, metadata: &CourseMetadata, overwrite: bool) -> bool {
+    let mut changed = false;
+    let mut set_text = |field: &mut String, value: &Option<String>| {
+        if let Some(value) = value {
+            if (overwrite || field.trim().is_empty()) && field != value {
+                *field = value.clone();
+                changed = true;
+            }
+        }
+    };
+    set_text(&mut course.title, &metadata.title);
+    set_text(&mut course.description, &metadata.description);
+    set_text(&mut course.language, &metadata.language);
+
+    let author = course.author.clone();
+    set_text(&mut course.author, &metadata.author);
+    if course.author != author {
+        // Resolved again from the new name
+        course.author_id = None;
+    }
+
+    if let Some(duration) = metadata.duration {
+        if (overwrite || course.duration <= 0) && course.duration != duration {
+            course.duration = duration;
+            changed = true;
+        }
+    }
+    changed
+}
+
+impl ApiService {
+    pub fn new(fetcher: Arc<dyn PageFetcher>, course_service: CourseService) -> ApiService {
+        ApiService { fetcher, course_service, job: Arc::new(Mutex::new(EnrichmentJobStatus::default())) }
+    }
+
+    /// Fetch the page of a course and extract its metadata.
+    pub async fn enrich(&self, url: &str) -> Result<CourseMetadata, ApiServiceError> {
+        let page = self.fetcher.fetch(url.trim()).await?;
+        Ok(extract(&page.url, &page.body))
+    }
+
+    /// Get the status of the last enrichment job.
+    pub fn job_status(&self) -> EnrichmentJobStatus {
+        self.job.lock().unwrap().clone()
+    }
+
+    /// Mark a new job as running, fails while another one is. The job itself is `run_job`.
+    pub fn start_job(&self, overwrite: bool) -> Result<EnrichmentJobStatus, ApiServiceError> {
+        let mut job = self.job.lock().unwrap();
+        if job.running {
+            return Err(ApiServiceError::JobRunning);
+        }
+        *job = EnrichmentJobStatus { running: true, overwrite, started_at: Some(DateTime::now()), ..Default::default() };
+        Ok(job.clone())
+    }
+
+    /// Enrich every course with a URL, one page at a time. Courses are saved through the course
+    /// service, so topics, references, the audit log and the suggest index stay consistent.
+    pub async fn run_job(&self, overwrite: bool, ctx: &AuditContext) {
+        let result = self.enrich_courses(overwrite, ctx).await;
+        let mut job = self.job.lock().unwrap();
+        if let Err(e) = result {
+            eprintln!("Error while enriching courses: {:?}", e);
+            job.error = Some(e.to_string());
+        }
+        job.running = false;
+        job.finished_at = Some(DateTime::now());
+    }
+
+    // Helper function to apply the metadata to the course as it is now, the page took time to
+    // fetch. Courses edited again before the update are left alone until the next job.
+    async fn apply_to_current(
+        &self,
+        course_id: &str,
+        metadata: &CourseMetadata,
+        overwrite: bool,
+        ctx: &AuditContext,
+    ) -> Result<bool, ApiServiceError> {
+        let Some(current) = self.course_service.get_by_id(course_id).await? else {
+            return Ok(false); // Deleted meanwhile
+        };
+        let mut course = current.clone();
+        if !apply(&mut course, metadata, overwrite) {
+            return Ok(false);
+        }
+        course.updated_at = DateTime::now();
+        let result = self.course_service.update_if_unchanged(&course, &current, ctx).await?;
+        Ok(result.modified_count > 0)
+    }
+
+    async fn enrich_courses(&self, overwrite: bool, ctx: &AuditContext) -> Result<(), ApiServiceError> {
+        let courses: Vec<Course> = self
+            .course_service
+            .get_all()
+            .await?
+            .into_iter()
+            .filter(|course| course._id.is_some() && !course.url.trim().is_empty())
+            .collect();
+        self.job.lock().unwrap().total = courses.len() as u64;
+
+        for (position, course) in courses.into_iter().enumerate() {
+            if position > 0 {
+                sleep(JOB_DELAY).await;
+            }
+            let course_id = course._id.map(|id| id.to_hex()).unwrap_or_default();
+            let result = match self.enrich(&course.url).await {
+                Ok(metadata) => self.apply_to_current(&course_id, &metadata, overwrite, ctx).await,
+                Err(e) => Err(e),
+            };
+
+            let mut job = self.job.lock().unwrap();
+            job.processed += 1;
+            match result {
+                Ok(updated) => job.updated += u64::from(updated),
+                Err(e) => {
+                    job.failed += 1;
+                    if job.failures.len() == MAX_JOB_FAILURES {
+                        job.failures.remove(0);
+                    }
+                    job.failures.push(EnrichmentFailure { course_id, url: course.url.clone(), error: e.to_string() });
+                }
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,179 @@
+use crate::models::{
+    course_model::Course,
+    export_model::{ExportFormat, UserExport},
+    user_model::User,
+    watched_model::Watched,
+};
+use crate::utils::export::{csv_line, ndjson_line, CsvRecord};
+use futures::stream::{self, BoxStream, Stream, StreamExt};
+use mongodb::{
+    bson::{doc, Document},
+    error::Error as MongoError,
+    Collection,
+};
+use serde::Serialize;
+use thiserror::Error;
+
+const XAPI_VERSION: &str = "1.0.3";
+const XAPI_COMPLETED_VERB: &str = "http://adlnet.gov/expapi/verbs/completed";
+const XAPI_COURSE_ACTIVITY: &str = "http://adlnet.gov/expapi/activities/course";
+
+#[derive(Debug, Error)]
+pub enum ApiServiceError {
+    #[error("The {0} export does not support this format")]
+    UnsupportedFormat(&'static str),
+    #[error("CSV error: {0}")]
+    CsvError(#[from] csv::Error),
+    #[error("JSON error: {0}")]
+    JsonError(#[from] serde_json::Error),
+    #[error("Invalid aggregation result: {0}")]
+    InvalidResult(#[from] bson::document::ValueAccessError),
+    #[error("Database error: {0}")]
+    DatabaseError(#[from] MongoError),
+}
+
+/// A stream of encoded lines, ready to be sent as a response body.
+pub type ExportStream = BoxStream<'static, Result<Vec<u8>, ApiServiceError>>;
+
+/// Exports read straight from the MongoDB cursors, so every record is encoded and sent
+/// as soon as it is read and nothing is collected in memory.
+#[derive(Clone)]
+pub struct ApiService {
+    courses: Collection<Course>,
+    users: Collection<User>,
+    watched: Collection<Watched>,
+    course_collection_name: String, // Joined with `$lookup` for xAPI statements
+    user_collection_name: String,
+    xapi_home_page: String, // Identifies the learning platform in xAPI statements
+}
+
+// Helper function to encode a stream of records as CSV, header first.
+fn csv_stream<T, S>(records: S) -> ExportStream
+where
+    T: CsvRecord + Send + 'static,
+    S: Stream<Item = Result<T, MongoError>> + Send + 'static,
+{
+    let header = stream::once(async { csv_line(T::csv_header()).map_err(ApiServiceError::from) });
+    let rows = records.map(|record| Ok(csv_line(record?.csv_row())?));
+    header.chain(rows).boxed()
+}
+
+// Helper function to encode a stream of records as NDJSON.
+fn ndjson_stream<T, S>(records: S) -> ExportStream
+where
+    T: Serialize + Send + 'static,
+    S: Stream<Item = Result<T, MongoError>> + Send + 'static,
+{
+    records.map(|record| Ok(ndjson_line(&record?)?)).boxed()
+}
+
+// Helper function to build the xAPI statement of a completed watched record joined with its course and user.
+fn xapi_statement(joined: &Document, home_page: &str) -> Result<serde_json::Value, ApiServiceError> {
+    let course = joined.get_document("course")?;
+    let user = joined.get_document("user")?;
+    let finished_at = joined.get_datetime("finished_at")?;
+
+    // Time actually spent when tracked, the length of the course otherwise
+    let minutes = match joined.get_f64("time_spent_minutes") {
+        Ok(minutes) if minutes > 0.0 => minutes,
+        _ => course.get_i32("duration").map(f64::from).unwrap_or_default(),
+    };
+
+    Ok(serde_json::json!({
+        "version": XAPI_VERSION,
+        "actor": {
+            "objectType": "Agent",
+            "name": format!("{} {}", user.get_str("name").unwrap_or_default(), user.get_str("lastname").unwrap_or_default()),
+            "mbox": format!("mailto:{}", user.get_str("email")?),
+            "account": { "homePage": home_page, "name": user.get_object_id("_id")?.to_hex() },
+        },
+        "verb": {
+            "id": XAPI_COMPLETED_VERB,
+            "display": { "en-US": "completed" },
+        },
+        "object": {
+            "objectType": "Activity",
+            "id": course.get_str("url")?,
+            "definition": {
+                "type": XAPI_COURSE_ACTIVITY,
+                "name": { "en-US": course.get_str("title")? },
+                "description": { "en-US": course.get_str("description").unwrap_or_default() },
+            },
+        },
+        "result": {
+            "completion": true,
+            "duration": format!("PT{}M", minutes.round() as i64),
+        },
+        "context": {
+            "platform": course.get_str("platform").unwrap_or_default(),
+            "language": course.get_str("language").unwrap_or_default(),
+        },
+        "timestamp": finished_at.try_to_rfc3339_string().unwrap_or_default(),
+    }))
+}
+
+impl ApiService {
+    pub fn new(
+        courses: Collection<Course>,
+        users: Collection<User>,
+        watched: Collection<Watched>,
+        course_collection_name: String,
+        user_collection_name: String,
+        xapi_home_page: String,
+    ) -> ApiService {
+        ApiService { courses, users, watched, course_collection_name, user_collection_name, xapi_home_page }
+    }
+
+    /// Export the courses, except the deleted ones.
+    pub async fn courses(&self, format: ExportFormat) -> Result<ExportStream, ApiServiceError> {
+        let cursor = self.courses.find(doc! { "deleted_at": null }, None).await?;
+        match format {
+            ExportFormat::Csv => Ok(csv_stream(cursor)),
+            ExportFormat::Ndjson => Ok(ndjson_stream(cursor)),
+            ExportFormat::Xapi => Err(ApiServiceError::UnsupportedFormat("course")),
+        }
+    }
+
+    /// Export the users without their password hash, except the deleted ones.
+    pub async fn users(&self, format: ExportFormat) -> Result<ExportStream, ApiServiceError> {
+        let cursor = self.users.find(doc! { "deleted_at": null }, None).await?;
+        let users = cursor.map(|user| user.map(UserExport::from));
+        match format {
+            ExportFormat::Csv => Ok(csv_stream(users)),
+            ExportFormat::Ndjson => Ok(ndjson_stream(users)),
+            ExportFormat::Xapi => Err(ApiServiceError::UnsupportedFormat("user")),
+        }
+    }
+
+    /// Export the watched records. As xAPI, only the completed records of known learners are
+    /// exported, one `completed` statement each.
+    pub async fn watched(&self, format: ExportFormat) -> Result<ExportStream, ApiServiceError> {
+        match format {
+            ExportFormat::Csv => Ok(csv_stream(self.watched.find(None, None).await?)),
+            ExportFormat::Ndjson => Ok(ndjson_stream(self.watched.find(None, None).await?)),
+            ExportFormat::Xapi => {
+                let pipeline = vec![
+                    doc! { "$match": { "finished_at": { "$type": "date" }, "user_id": { "$type": "objectId" } } },
+                    doc! { "$lookup": {
+                        "from": &self.course_collection_name,
+                        "localField": "course_id",
+                        "foreignField": "_id",
+                        "as": "course",
+                    } },
+                    doc! { "$lookup": {
+                        "from": &self.user_collection_name,
+                        "localField": "user_id",
+                        "foreignField": "_id",
+                        "as": "user",
+                    } },
+                    doc! { "$unwind": "$course" },
+                    doc! { "$unwind": "$user" },
+                ];
+                let cursor = self.watched.aggregate(pipeline, None).await?;
+                let home_page = self.xapi_home_page.clone();
+                let statements = cursor.map(move |joined| Ok(ndjson_line(&xapi_statement(&joined?, &home_page)?)?));
+                Ok(statements.boxed())
+            }
+        }
+    }
+}
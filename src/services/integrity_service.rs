@@ -0,0 +1,241 @@
+use crate::extractors::audit_extractor::AuditContext;
+use crate::models::{
+    integrity_model::{DanglingWatchedIds, IntegrityReport, PurgeReport},
+    user_model::User,
+    watched_model::Watched,
+};
+use crate::services::{
+    course_service::ApiService as CourseService,
+    user_service::ApiService as UserService,
+    watched_service::ApiService as WatchedService,
+};
+use mongodb::{
+    bson::{doc, oid::ObjectId, DateTime},
+    error::Error as MongoError,
+};
+use std::{collections::HashSet, str::FromStr};
+use thiserror::Error;
+
+/// What happens to the watched records of a course or user being deleted. `Restrict` is
+/// checked when the course or user is soft deleted, the other policies apply when the
+/// tombstone is purged so a restore brings everything back.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeletePolicy {
+    Cascade,  // Delete the watched records too
+    Restrict, // Refuse the delete while watched records exist
+    Archive,  // Keep the watched records, archived
+}
+
+impl FromStr for DeletePolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "cascade" => Ok(DeletePolicy::Cascade),
+            "restrict" => Ok(DeletePolicy::Restrict),
+            "archive" => Ok(DeletePolicy::Archive),
+            other => Err(format!("Unknown delete policy: {}", other)),
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum ApiServiceError {
+    #[error("Course not found")]
+    MissingCourse,
+    #[error("User not found")]
+    MissingUser,
+    #[error("Still referenced by {0} watched records")]
+    Referenced(usize),
+    #[error("Database error: {0}")]
+    DatabaseError(#[from] MongoError),
+}
+
+/// Keeps watched records, courses and users consistent with each other.
+#[derive(Clone)]
+pub struct ApiService {
+    course_service: CourseService,
+    user_service: UserService,
+    watched_service: WatchedService,
+    policy: DeletePolicy,
+}
+
+impl ApiService {
+    pub fn new(
+        course_service: CourseService,
+        user_service: UserService,
+        watched_service: WatchedService,
+        policy: DeletePolicy,
+    ) -> ApiService {
+        ApiService { course_service, user_service, watched_service, policy }
+    }
+
+    /// Check that the course and user a watched record points to exist.
+    pub async fn check_watched_references(&self, w: &Watched) -> Result<(), ApiServiceError> {
+        if !self.course_service.exists(w.course_id).await? {
+            return Err(ApiServiceError::MissingCourse);
+        }
+        if let Some(user_id) = w.user_id {
+            if !self.user_service.exists(user_id).await? {
+                return Err(ApiServiceError::MissingUser);
+            }
+        }
+        Ok(())
+    }
+
+    // Helper function to find the watched records of a course.
+    async fn course_watched_ids(&self, course_id: ObjectId) -> Result<Vec<ObjectId>, MongoError> {
+        self.watched_service.find_ids(doc! { "course_id": course_id }).await
+    }
+
+    // Helper function to find the watched records of a user, with the user's `user_id`
+    // as well as the ones listed in `watched_ids`.
+    async fn user_watched_ids(&self, user: &User) -> Result<Vec<ObjectId>, MongoError> {
+        let listed: Vec<ObjectId> = user
+            .watched_ids
+            .clone()
+            .unwrap_or_default()
+            .iter()
+            .filter_map(|id| ObjectId::parse_str(id).ok())
+            .collect();
+        let filter = doc! { "$or": [ { "user_id": user._id }, { "_id": { "$in": listed } } ] };
+        self.watched_service.find_ids(filter).await
+    }
+
+    /// Check the restrict policy before a course is soft deleted.
+    pub async fn before_course_delete(&self, course_id: &str) -> Result<(), ApiServiceError> {
+        let course_id = match ObjectId::parse_str(course_id) {
+            Ok(course_id) => course_id,
+            Err(_) => return Ok(()), // The delete itself reports the invalid id
+        };
+        if self.policy != DeletePolicy::Restrict {
+            return Ok(());
+        }
+        match self.course_watched_ids(course_id).await?.len() {
+            0 => Ok(()),
+            count => Err(ApiServiceError::Referenced(count)),
+        }
+    }
+
+    /// Check the restrict policy before a user is soft deleted.
+    pub async fn before_user_delete(&self, user_id: &str) -> Result<(), ApiServiceError> {
+        let user = match self.user_service.get_by_id(user_id).await {
+            Ok(Some(user)) => user,
+            Ok(None) | Err(_) => return Ok(()), // The delete itself reports the missing user
+        };
+        if self.policy != DeletePolicy::Restrict {
+            return Ok(());
+        }
+        match self.user_watched_ids(&user).await?.len() {
+            0 => Ok(()),
+            count => Err(ApiServiceError::Referenced(count)),
+        }
+    }
+
+    /// Permanently remove the courses and users soft deleted before `cutoff`, applying the
+    /// delete policy to their watched records first.
+    pub async fn purge_deleted(&self, cutoff: DateTime, ctx: &AuditContext) -> Result<PurgeReport, ApiServiceError> {
+        let mut report = PurgeReport::default();
+
+        for course in self.course_service.get_deleted(Some(cutoff)).await? {
+            let Some(course_id) = course._id else { continue };
+            let watched_ids = self.course_watched_ids(course_id).await?;
+            match self.apply_policy(&watched_ids, ctx).await {
+                Ok(()) => {}
+                Err(ApiServiceError::Referenced(_)) => {
+                    report.skipped.push(course_id);
+                    continue;
+                }
+                Err(e) => return Err(e),
+            }
+            if self.course_service.purge(course_id, ctx).await?.deleted_count > 0 {
+                report.courses_purged.push(course_id);
+            }
+        }
+
+        for user in self.user_service.get_deleted(Some(cutoff)).await? {
+            let Some(user_id) = user._id else { continue };
+            let watched_ids = self.user_watched_ids(&user).await?;
+            match self.apply_policy(&watched_ids, ctx).await {
+                Ok(()) => {}
+                Err(ApiServiceError::Referenced(_)) => {
+                    report.skipped.push(user_id);
+                    continue;
+                }
+                Err(e) => return Err(e),
+            }
+            if self.user_service.purge(user_id, ctx).await?.deleted_count > 0 {
+                report.users_purged.push(user_id);
+            }
+        }
+
+        Ok(report)
+    }
+
+    async fn apply_policy(&self, watched_ids: &[ObjectId], ctx: &AuditContext) -> Result<(), ApiServiceError> {
+        if watched_ids.is_empty() {
+            return Ok(());
+        }
+        match self.policy {
+            DeletePolicy::Restrict => return Err(ApiServiceError::Referenced(watched_ids.len())),
+            DeletePolicy::Archive => {
+                self.watched_service.archive_many(watched_ids, ctx).await?;
+            }
+            DeletePolicy::Cascade => {
+                self.watched_service.delete_many(watched_ids, ctx).await?;
+                self.user_service.pull_watched_ids(watched_ids, ctx).await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Find active watched records pointing to missing courses or users, and `watched_ids` pointing to
+    /// missing watched records. With `repair`, the orphaned records are archived and the dangling ids removed.
+    pub async fn check(&self, repair: bool, ctx: &AuditContext) -> Result<IntegrityReport, ApiServiceError> {
+        let course_ids = self.course_service.get_ids().await?;
+        let user_ids = self.user_service.get_ids().await?;
+
+        let watched_missing_course = self
+            .watched_service
+            .find_ids(doc! { "archived": false, "course_id": { "$nin": &course_ids } })
+            .await?;
+        let watched_missing_user = self
+            .watched_service
+            .find_ids(doc! { "archived": false, "user_id": { "$type": "objectId", "$nin": &user_ids } })
+            .await?;
+
+        let existing: HashSet<String> = self
+            .watched_service
+            .find_ids(doc! {})
+            .await?
+            .iter()
+            .map(|id| id.to_hex())
+            .collect();
+        let dangling_watched_ids: Vec<DanglingWatchedIds> = self
+            .user_service
+            .get_watched_ids()
+            .await?
+            .into_iter()
+            .filter_map(|(user_id, watched_ids)| {
+                let missing: Vec<String> = watched_ids.into_iter().filter(|id| !existing.contains(id)).collect();
+                (!missing.is_empty()).then_some(DanglingWatchedIds { user_id, watched_ids: missing })
+            })
+            .collect();
+
+        if repair {
+            let orphans: Vec<ObjectId> = watched_missing_course.iter().chain(&watched_missing_user).copied().collect();
+            if !orphans.is_empty() {
+                self.watched_service.archive_many(&orphans, ctx).await?;
+            }
+            let dangling: Vec<ObjectId> = dangling_watched_ids
+                .iter()
+                .flat_map(|d| d.watched_ids.iter().filter_map(|id| ObjectId::parse_str(id).ok()))
+                .collect();
+            if !dangling.is_empty() {
+                self.user_service.pull_watched_ids(&dangling, ctx).await?;
+            }
+        }
+
+        Ok(IntegrityReport { watched_missing_course, watched_missing_user, dangling_watched_ids, repaired: repair })
+    }
+}
@@ -0,0 +1,378 @@
+use crate::extractors::audit_extractor::AuditContext;
+use crate::models::{
+    audit_model::AuditAction,
+    learning_path_model::{LearningPath, PathCourseProgress, PathCourseStatus, PathProgress},
+    watched_model::Watched,
+};
+use crate::services::audit_service::ApiService as AuditService;
+use futures::stream::StreamExt;
+use mongodb::{
+    bson::{doc, oid::ObjectId, Document},
+    error::Error as MongoError,
+    results::{DeleteResult, InsertOneResult, UpdateResult},
+    Collection,
+};
+use std::collections::{HashMap, HashSet};
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum ApiServiceError {
+    #[error("Invalid ObjectId format")]
+    InvalidObjectId,
+    #[error("Invalid learning path: {0}")]
+    InvalidPath(String),
+    #[error("Still a prerequisite of other learning paths")]
+    InUse,
+    #[error("Database error: {0}")]
+    DatabaseError(#[from] MongoError),
+}
+
+#[derive(Clone)]
+pub struct ApiService {
+    collection: Collection<LearningPath>,
+    audit: AuditService,
+}
+
+// Helper function to convert a `LearningPath` into a MongoDB Document.
+fn learning_path_to_document(p: &LearningPath) -> Document {
+    let courses: Vec<Document> = p
+        .courses
+        .iter()
+        .map(|c| doc! { "course_id": c.course_id, "required": c.required, "prerequisites": c.prerequisites.clone() })
+        .collect();
+    doc! {
+        "title"         : p.title.clone(),
+        "description"   : p.description.clone(),
+        "courses"       : courses,
+        "prerequisites" : p.prerequisites.clone(),
+        "created_at"    : p.created_at,
+        "updated_at"    : p.updated_at,
+    }
+}
+
+// Helper function to check the structure of a path: every course appears once and only
+// depends on courses listed before it, which also rules out cycles.
+fn validate(p: &LearningPath) -> Result<(), ApiServiceError> {
+    let mut seen = HashSet::new();
+    for course in &p.courses {
+        if let Some(missing) = course.prerequisites.iter().find(|id| !seen.contains(*id)) {
+            return Err(ApiServiceError::InvalidPath(format!(
+                "Prerequisite {} of course {} must be listed earlier in the path",
+                missing, course.course_id
+            )));
+        }
+        if !seen.insert(course.course_id) {
+            return Err(ApiServiceError::InvalidPath(format!("Course {} is listed twice", course.course_id)));
+        }
+    }
+    if p._id.is_some_and(|id| p.prerequisites.contains(&id)) {
+        return Err(ApiServiceError::InvalidPath("A path cannot be its own prerequisite".to_string()));
+    }
+    Ok(())
+}
+
+// Helper function to get the completion status and best percent complete of every watched course.
+fn course_statuses(watched: &[Watched]) -> HashMap<ObjectId, (PathCourseStatus, f64)> {
+    let mut statuses: HashMap<ObjectId, (PathCourseStatus, f64)> = HashMap::new();
+    for w in watched {
+        let (status, percent) = if w.finished_at.is_some() {
+            (PathCourseStatus::Completed, 100.0)
+        } else {
+            (PathCourseStatus::InProgress, w.percent_complete)
+        };
+        let entry = statuses.entry(w.course_id).or_insert((status, percent));
+        if status == PathCourseStatus::Completed {
+            entry.0 = PathCourseStatus::Completed;
+        }
+        entry.1 = entry.1.max(percent);
+    }
+    statuses
+}
+
+// Helper function to check that every required course of a path is completed.
+fn courses_completed(p: &LearningPath, statuses: &HashMap<ObjectId, (PathCourseStatus, f64)>) -> bool {
+    p.courses
+        .iter()
+        .filter(|c| c.required)
+        .all(|c| matches!(statuses.get(&c.course_id), Some((PathCourseStatus::Completed, _))))
+}
+
+// Helper function to check that a path is completed: its required courses and, transitively, its
+// prerequisite paths. `paths` holds the prerequisites, missing ones count as not completed.
+fn path_completed(
+    path_id: ObjectId,
+    paths: &HashMap<ObjectId, LearningPath>,
+    statuses: &HashMap<ObjectId, (PathCourseStatus, f64)>,
+    visiting: &mut HashSet<ObjectId>,
+) -> bool {
+    let Some(p) = paths.get(&path_id) else { return false };
+    // A cycle left by older data never completes
+    if !visiting.insert(path_id) {
+        return false;
+    }
+    let completed = courses_completed(p, statuses)
+        && p.prerequisites.iter().all(|id| path_completed(*id, paths, statuses, visiting));
+    visiting.remove(&path_id);
+    completed
+}
+
+// Helper function to check whether `path_id` can reach itself through the prerequisites in `graph`.
+fn has_cycle(path_id: ObjectId, graph: &HashMap<ObjectId, Vec<ObjectId>>) -> bool {
+    let mut visited = HashSet::new();
+    let mut pending: Vec<ObjectId> = graph.get(&path_id).cloned().unwrap_or_default();
+    while let Some(id) = pending.pop() {
+        if id == path_id {
+            return true;
+        }
+        if visited.insert(id) {
+            pending.extend(graph.get(&id).into_iter().flatten());
+        }
+    }
+    false
+}
+
+impl ApiService {
+    pub fn new(collection: Collection<LearningPath>, audit: AuditService) -> ApiService {
+        ApiService { collection, audit }
+    }
+
+    /// Get all learning paths from the collection.
+    pub async fn get_all(&self) -> Result<Vec<LearningPath>, MongoError> {
+        let mut cursor = self.collection.find(None, None).await?;
+        let mut docs = Vec::new();
+
+        while let Some(result) = cursor.next().await {
+            match result {
+                Ok(path) => docs.push(path),
+                Err(err) => return Err(err),
+            }
+        }
+
+        Ok(docs)
+    }
+
+    /// Get a learning path by its MongoDB `_id`.
+    pub async fn get_by_id(&self, path_id: &str) -> Result<Option<LearningPath>, ApiServiceError> {
+        let object_id = ObjectId::parse_str(path_id).map_err(|_| ApiServiceError::InvalidObjectId)?;
+        let filter = doc! { "_id": object_id };
+        let result = self.collection.find_one(filter, None).await?;
+        Ok(result)
+    }
+
+    /// Check whether a learning path exists.
+    pub async fn exists(&self, path_id: ObjectId) -> Result<bool, MongoError> {
+        let count = self.collection.count_documents(doc! { "_id": path_id }, None).await?;
+        Ok(count > 0)
+    }
+
+    // Helper function to reject prerequisites that depend on the updated path, directly or through other paths.
+    // A new path cannot be part of a cycle since no other path refers to it yet.
+    async fn check_no_cycle(&self, path_id: ObjectId, prerequisites: &[ObjectId]) -> Result<(), ApiServiceError> {
+        let mut graph: HashMap<ObjectId, Vec<ObjectId>> = HashMap::new();
+        let mut cursor = self.collection.find(doc! { "prerequisites.0": { "$exists": true } }, None).await?;
+        while let Some(result) = cursor.next().await {
+            let path = result?;
+            if let Some(id) = path._id {
+                graph.insert(id, path.prerequisites);
+            }
+        }
+        graph.insert(path_id, prerequisites.to_vec());
+        if has_cycle(path_id, &graph) {
+            return Err(ApiServiceError::InvalidPath("The prerequisites of a path cannot depend on the path itself".to_string()));
+        }
+        Ok(())
+    }
+
+    // Helper function to load the prerequisite paths of a path, transitively.
+    async fn prerequisite_paths(&self, p: &LearningPath) -> Result<HashMap<ObjectId, LearningPath>, MongoError> {
+        let mut paths: HashMap<ObjectId, LearningPath> = HashMap::new();
+        let mut pending: Vec<ObjectId> = p.prerequisites.clone();
+        while !pending.is_empty() {
+            let mut cursor = self.collection.find(doc! { "_id": { "$in": &pending } }, None).await?;
+            pending.clear();
+            while let Some(result) = cursor.next().await {
+                let path = result?;
+                let Some(id) = path._id else { continue };
+                pending.extend(path.prerequisites.iter().filter(|id| !paths.contains_key(*id)));
+                paths.insert(id, path);
+            }
+            pending.retain(|id| !paths.contains_key(id));
+        }
+        Ok(paths)
+    }
+
+    /// Create a new learning path in the collection.
+    pub async fn create(&self, p: &LearningPath, ctx: &AuditContext) -> Result<InsertOneResult, ApiServiceError> {
+        validate(p)?;
+        let result = self.collection.insert_one(p, None).await?;
+        let target_id = result.inserted_id.as_object_id().map(|id| id.to_hex());
+        let after = Some(learning_path_to_document(p));
+        self.audit.record(ctx, AuditAction::Create, "learning_path", target_id, None, after).await;
+        Ok(result)
+    }
+
+    /// Update an existing learning path by its MongoDB `_id`.
+    pub async fn update(&self, p: &LearningPath, path_id: &str, ctx: &AuditContext) -> Result<UpdateResult, ApiServiceError> {
+        let object_id = ObjectId::parse_str(path_id).map_err(|_| ApiServiceError::InvalidObjectId)?;
+        if p.prerequisites.contains(&object_id) {
+            return Err(ApiServiceError::InvalidPath("A path cannot be its own prerequisite".to_string()));
+        }
+        validate(p)?;
+        self.check_no_cycle(object_id, &p.prerequisites).await?;
+
+        let filter = doc! { "_id": object_id };
+        let before = self.collection.find_one(filter.clone(), None).await?;
+        let changes = learning_path_to_document(p);
+        let update = doc! { "$set": changes.clone() };
+        let result = self.collection.update_one(filter, update, None).await?;
+
+        if let (Some(before), true) = (before, result.modified_count > 0) {
+            self.audit
+                .record_update(ctx, "learning_path", path_id, &learning_path_to_document(&before), &changes)
+                .await;
+        }
+        Ok(result)
+    }
+
+    /// Delete a learning path by its MongoDB `_id`, only when no other path lists it as a prerequisite.
+    pub async fn delete(&self, path_id: &str, ctx: &AuditContext) -> Result<DeleteResult, ApiServiceError> {
+        let object_id = ObjectId::parse_str(path_id).map_err(|_| ApiServiceError::InvalidObjectId)?;
+        if self.collection.count_documents(doc! { "prerequisites": object_id }, None).await? > 0 {
+            return Err(ApiServiceError::InUse);
+        }
+        let filter = doc! { "_id": object_id };
+        let before = self.collection.find_one(filter.clone(), None).await?;
+        let result = self.collection.delete_one(filter, None).await?;
+
+        if let (Some(before), true) = (before, result.deleted_count > 0) {
+            let before = Some(learning_path_to_document(&before));
+            self.audit.record(ctx, AuditAction::Delete, "learning_path", Some(path_id.to_string()), before, None).await;
+        }
+        Ok(result)
+    }
+
+    /// Compute the progress of a learner through a path from their watched records.
+    pub async fn progress(
+        &self,
+        p: &LearningPath,
+        user_id: ObjectId,
+        watched: &[Watched],
+    ) -> Result<PathProgress, ApiServiceError> {
+        let path_id = p._id.ok_or(ApiServiceError::InvalidObjectId)?;
+        let statuses = course_statuses(watched);
+
+        let paths = self.prerequisite_paths(p).await?;
+        let prerequisites_met = p
+            .prerequisites
+            .iter()
+            .all(|id| path_completed(*id, &paths, &statuses, &mut HashSet::from([path_id])));
+
+        let completed_ids: HashSet<ObjectId> = statuses
+            .iter()
+            .filter(|(_, (status, _))| *status == PathCourseStatus::Completed)
+            .map(|(id, _)| *id)
+            .collect();
+        let courses: Vec<PathCourseProgress> = p
+            .courses
+            .iter()
+            .map(|c| {
+                let (status, percent_complete) = statuses
+                    .get(&c.course_id)
+                    .copied()
+                    .unwrap_or((PathCourseStatus::NotStarted, 0.0));
+                PathCourseProgress {
+                    course_id: c.course_id,
+                    required: c.required,
+                    status,
+                    percent_complete,
+                    locked: !prerequisites_met || !c.prerequisites.iter().all(|id| completed_ids.contains(id)),
+                }
+            })
+            .collect();
+
+        let required_total = courses.iter().filter(|c| c.required).count();
+        let required_completed = courses
+            .iter()
+            .filter(|c| c.required && c.status == PathCourseStatus::Completed)
+            .count();
+        let percent_complete = if required_total == 0 {
+            100.0
+        } else {
+            required_completed as f64 * 100.0 / required_total as f64
+        };
+        let next_course_id = courses
+            .iter()
+            .find(|c| !c.locked && c.status != PathCourseStatus::Completed)
+            .map(|c| c.course_id);
+
+        Ok(PathProgress {
+            path_id,
+            user_id,
+            required_total,
+            required_completed,
+            percent_complete,
+            completed: prerequisites_met && required_completed == required_total,
+            prerequisites_met,
+            next_course_id,
+            courses,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::learning_path_model::PathCourse;
+    use mongodb::bson::DateTime;
+
+    fn path(id: ObjectId, courses: &[ObjectId], prerequisites: &[ObjectId]) -> LearningPath {
+        LearningPath {
+            _id: Some(id),
+            title: "Path".to_string(),
+            description: String::new(),
+            courses: courses
+                .iter()
+                .map(|course_id| PathCourse { course_id: *course_id, required: true, prerequisites: vec![] })
+                .collect(),
+            prerequisites: prerequisites.to_vec(),
+            created_at: DateTime::now(),
+            updated_at: DateTime::now(),
+        }
+    }
+
+    #[test]
+    fn cycles_through_other_paths_are_found() {
+        let (a, b, c) = (ObjectId::new(), ObjectId::new(), ObjectId::new());
+        let mut graph = HashMap::from([(b, vec![c]), (c, vec![])]);
+        graph.insert(a, vec![b]);
+        assert!(!has_cycle(a, &graph));
+
+        // `c` now requires `a`, which requires `b`, which requires `c`
+        graph.insert(c, vec![a]);
+        assert!(has_cycle(a, &graph));
+        assert!(has_cycle(c, &graph));
+    }
+
+    #[test]
+    fn prerequisite_paths_are_completed_transitively() {
+        let (first, second) = (ObjectId::new(), ObjectId::new());
+        let (course_1, course_2) = (ObjectId::new(), ObjectId::new());
+        let paths = HashMap::from([
+            (first, path(first, &[course_1], &[])),
+            (second, path(second, &[course_2], &[first])),
+        ]);
+        let only_second: HashMap<_, _> = HashMap::from([(course_2, (PathCourseStatus::Completed, 100.0))]);
+        assert!(!path_completed(second, &paths, &only_second, &mut HashSet::new()));
+
+        let mut both = only_second.clone();
+        both.insert(course_1, (PathCourseStatus::Completed, 100.0));
+        assert!(path_completed(second, &paths, &both, &mut HashSet::new()));
+    }
+
+    #[test]
+    fn cyclic_prerequisites_never_complete() {
+        let (a, b) = (ObjectId::new(), ObjectId::new());
+        let paths = HashMap::from([(a, path(a, &[], &[b])), (b, path(b, &[], &[a]))]);
+        assert!(!path_completed(a, &paths, &HashMap::new(), &mut HashSet::new()));
+    }
+}
@@ -0,0 +1,168 @@
+use crate::fetchers::PageFetcher;
+use crate::models::course_model::{Course, LinkCheck};
+use crate::services::course_service::ApiService as CourseService;
+use actix_web::rt::time::sleep;
+use mongodb::{bson::DateTime, error::Error as MongoError};
+use std::{sync::Arc, time::Duration};
+
+const HISTORY: i32 = 20; // Checks kept on each course
+const CHECK_DELAY: Duration = Duration::from_millis(200); // Between two links
+
+/// Checks the links of the courses and marks the dead ones.
+#[derive(Clone)]
+pub struct ApiService {
+    fetcher: Arc<dyn PageFetcher>,
+    course_service: CourseService,
+    threshold: i32, // Consecutive failures before a link is broken
+}
+
+// Helper function to check a link. Only a 2xx status after redirects counts as a success.
+async fn check_link(fetcher: &dyn PageFetcher, url: &str) -> LinkCheck {
+    let checked_at = DateTime::now();
+    match fetcher.check(url).await {
+        Ok(link) => LinkCheck {
+            checked_at,
+            ok: (200..300).contains(&link.status),
+            status: Some(i32::from(link.status)),
+            final_url: (link.url != url).then_some(link.url),
+            error: None,
+        },
+        Err(e) => LinkCheck { checked_at, ok: false, status: None, final_url: None, error: Some(e.to_string()) },
+    }
+}
+
+// Helper function to get the consecutive failures and the broken date of a link after a check.
+// A link is broken from the check reaching `threshold` failures until the next success.
+fn link_state(failures: i32, broken_at: Option<DateTime>, check: &LinkCheck, threshold: i32) -> (i32, Option<DateTime>) {
+    let failures = if check.ok { 0 } else { failures + 1 };
+    let broken_at = if failures >= threshold { broken_at.or(Some(check.checked_at)) } else { None };
+    (failures, broken_at)
+}
+
+impl ApiService {
+    pub fn new(fetcher: Arc<dyn PageFetcher>, course_service: CourseService, threshold: i32) -> ApiService {
+        ApiService { fetcher, course_service, threshold: threshold.max(1) }
+    }
+
+    /// Check the link of one course. Only a 2xx status after redirects counts as a success.
+    pub async fn check(&self, url: &str) -> LinkCheck {
+        check_link(self.fetcher.as_ref(), url).await
+    }
+
+    /// Check and record the link of one course. Returns whether it just became broken. The check is
+    /// dropped when the URL was edited or the course checked again while the link was fetched.
+    pub async fn check_course(&self, course: &Course) -> Result<bool, MongoError> {
+        if course._id.is_none() {
+            return Ok(false);
+        }
+        let check = self.check(&course.url).await;
+        let (failures, broken_at) = link_state(course.link_failures, course.link_broken_at, &check, self.threshold);
+        let result = self.course_service.apply_link_check(course, &check, failures, broken_at, HISTORY).await?;
+        if result.matched_count == 0 {
+            return Ok(false);
+        }
+        Ok(course.link_broken_at.is_none() && broken_at.is_some())
+    }
+
+    /// Check the links of every course, one at a time. Returns how many became broken. A course
+    /// that cannot be recorded is logged and skipped, the others are still checked.
+    pub async fn check_all(&self) -> Result<u64, MongoError> {
+        let courses = self.course_service.get_all().await?;
+        let mut newly_broken = 0;
+        for (position, course) in courses.iter().filter(|course| !course.url.trim().is_empty()).enumerate() {
+            if position > 0 {
+                sleep(CHECK_DELAY).await;
+            }
+            match self.check_course(course).await {
+                Ok(true) => newly_broken += 1,
+                Ok(false) => {}
+                Err(e) => eprintln!("Error while recording the link check of course {:?}: {:?}", course._id, e),
+            }
+        }
+        Ok(newly_broken)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fetchers::http_fetcher::HttpFetcher;
+    use crate::utils::test_server::serve;
+    use actix_web::{web, HttpResponse};
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    // Helper function to serve the stub links. `/flaky` answers 503 until `up` is set.
+    fn stub_server(up: Arc<AtomicBool>) -> (HttpFetcher, String) {
+        let up = web::Data::from(up);
+        let addr = serve(move |cfg| {
+            cfg.app_data(up.clone())
+                .route("/ok", web::get().to(HttpResponse::Ok))
+                .route("/moved", web::get().to(|| async { HttpResponse::Found().insert_header(("Location", "/ok")).finish() }))
+                .service(
+                    web::resource("/no-head")
+                        .route(web::head().to(HttpResponse::MethodNotAllowed))
+                        .route(web::get().to(HttpResponse::Ok)),
+                )
+                .route(
+                    "/flaky",
+                    web::route().to(|up: web::Data<AtomicBool>| async move {
+                        if up.load(Ordering::SeqCst) {
+                            HttpResponse::Ok().finish()
+                        } else {
+                            HttpResponse::ServiceUnavailable().finish()
+                        }
+                    }),
+                );
+        });
+        (HttpFetcher::new(Duration::from_secs(5), true), format!("http://{}", addr))
+    }
+
+    #[actix_web::test]
+    async fn redirects_are_followed() {
+        let (fetcher, base) = stub_server(Arc::new(AtomicBool::new(true)));
+        let check = check_link(&fetcher, &format!("{}/moved", base)).await;
+        assert!(check.ok);
+        assert_eq!(check.status, Some(200));
+        assert_eq!(check.final_url, Some(format!("{}/ok", base)));
+
+        let direct = check_link(&fetcher, &format!("{}/ok", base)).await;
+        assert!(direct.ok);
+        assert_eq!(direct.final_url, None);
+    }
+
+    #[actix_web::test]
+    async fn refused_head_falls_back_to_get() {
+        let (fetcher, base) = stub_server(Arc::new(AtomicBool::new(true)));
+        let check = check_link(&fetcher, &format!("{}/no-head", base)).await;
+        assert!(check.ok);
+        assert_eq!(check.status, Some(200));
+    }
+
+    #[actix_web::test]
+    async fn consecutive_failures_break_the_link_until_it_answers_again() {
+        const THRESHOLD: i32 = 3;
+        let up = Arc::new(AtomicBool::new(false));
+        let (fetcher, base) = stub_server(up.clone());
+        let url = format!("{}/flaky", base);
+
+        let (mut failures, mut broken_at) = (0, None);
+        for attempt in 1..=THRESHOLD + 1 {
+            let check = check_link(&fetcher, &url).await;
+            assert!(!check.ok);
+            assert_eq!(check.status, Some(503));
+            let previous = broken_at;
+            (failures, broken_at) = link_state(failures, broken_at, &check, THRESHOLD);
+            assert_eq!(failures, attempt);
+            match attempt {
+                a if a < THRESHOLD => assert_eq!(broken_at, None),
+                a if a == THRESHOLD => assert_eq!(broken_at, Some(check.checked_at)),
+                _ => assert_eq!(broken_at, previous), // Broken since the first time
+            }
+        }
+
+        up.store(true, Ordering::SeqCst);
+        let check = check_link(&fetcher, &url).await;
+        assert!(check.ok);
+        assert_eq!(link_state(failures, broken_at, &check, THRESHOLD), (0, None));
+    }
+}
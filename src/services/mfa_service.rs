@@ -0,0 +1,249 @@
+use crate::models::{mfa_model::MfaEnrollment, user_model::User};
+use crate::utils::token::{generate_token, hash_token};
+use hmac::{Hmac, Mac};
+use mongodb::{
+    bson::{doc, oid::ObjectId, DateTime},
+    error::Error as MongoError,
+    options::ReplaceOptions,
+    Collection,
+};
+use rand::RngCore;
+use sha1::Sha1;
+use thiserror::Error;
+
+const SECRET_LENGTH: usize = 20; // 160 bits, as recommended by RFC 4226
+const TOTP_PERIOD_SECONDS: i64 = 30;
+const TOTP_DIGITS: u32 = 6;
+const TOTP_ALLOWED_DRIFT_STEPS: i64 = 1;
+const RECOVERY_CODE_COUNT: usize = 10;
+const RECOVERY_CODE_LENGTH: usize = 10;
+
+#[derive(Debug, Error)]
+pub enum ApiServiceError {
+    #[error("MFA is already enabled for this user")]
+    AlreadyEnabled,
+    #[error("MFA enrollment not found")]
+    NotEnrolled,
+    #[error("Invalid MFA code")]
+    InvalidCode,
+    #[error("Database error: {0}")]
+    DatabaseError(#[from] MongoError),
+}
+
+#[derive(Clone)]
+pub struct ApiService {
+    collection: Collection<MfaEnrollment>,
+    issuer: String,
+}
+
+// Helper function to compute the RFC 6238 TOTP code with `digits` digits for a given time step.
+fn totp_code(secret: &[u8], step: i64, digits: u32) -> String {
+    let mut mac = Hmac::<Sha1>::new_from_slice(secret).expect("HMAC accepts keys of any length");
+    mac.update(&(step as u64).to_be_bytes());
+    let digest = mac.finalize().into_bytes();
+
+    // Dynamic truncation (RFC 4226, section 5.3)
+    let offset = (digest[digest.len() - 1] & 0x0f) as usize;
+    let binary = u32::from_be_bytes([
+        digest[offset] & 0x7f,
+        digest[offset + 1],
+        digest[offset + 2],
+        digest[offset + 3],
+    ]);
+
+    format!("{:0width$}", binary % 10u32.pow(digits), width = digits as usize)
+}
+
+// Helper function to find the time step matching a code, allowing for clock drift.
+fn matching_step(secret: &str, code: &str) -> Option<i64> {
+    let secret = base32::decode(base32::Alphabet::RFC4648 { padding: false }, secret)?;
+    let current_step = chrono::Utc::now().timestamp() / TOTP_PERIOD_SECONDS;
+
+    (-TOTP_ALLOWED_DRIFT_STEPS..=TOTP_ALLOWED_DRIFT_STEPS)
+        .map(|drift| current_step + drift)
+        .find(|step| totp_code(&secret, *step, TOTP_DIGITS) == code)
+}
+
+// Helper function to strip the spaces and dashes users tend to type into codes.
+fn normalize_code(code: &str) -> String {
+    code.chars().filter(|c| !c.is_whitespace() && *c != '-').collect()
+}
+
+impl ApiService {
+    pub fn new(collection: Collection<MfaEnrollment>, issuer: String) -> ApiService {
+        ApiService { collection, issuer }
+    }
+
+    /// Start (or restart) an MFA enrollment for a user.
+    /// Returns the base32 secret and the `otpauth://` provisioning URI.
+    pub async fn enroll(&self, user: &User, user_id: ObjectId) -> Result<(String, String), ApiServiceError> {
+        if self.is_enabled(user_id).await? {
+            return Err(ApiServiceError::AlreadyEnabled);
+        }
+
+        let mut raw_secret = [0u8; SECRET_LENGTH];
+        rand::thread_rng().fill_bytes(&mut raw_secret);
+        let secret = base32::encode(base32::Alphabet::RFC4648 { padding: false }, &raw_secret);
+
+        let enrollment = MfaEnrollment {
+            _id: None,
+            user_id,
+            secret: secret.clone(),
+            enabled: false,
+            recovery_codes: Vec::new(),
+            last_used_step: None,
+            created_at: DateTime::now(),
+            updated_at: DateTime::now(),
+        };
+        let options = ReplaceOptions::builder().upsert(true).build();
+        self.collection
+            .replace_one(doc! { "user_id": user_id }, enrollment, options)
+            .await?;
+
+        let label: String = url::form_urlencoded::byte_serialize(format!("{}:{}", self.issuer, user.email).as_bytes()).collect();
+        let issuer: String = url::form_urlencoded::byte_serialize(self.issuer.as_bytes()).collect();
+        let uri = format!(
+            "otpauth://totp/{}?secret={}&issuer={}&algorithm=SHA1&digits={}&period={}",
+            label, secret, issuer, TOTP_DIGITS, TOTP_PERIOD_SECONDS
+        );
+
+        Ok((secret, uri))
+    }
+
+    /// Confirm a pending enrollment with a first code and enable MFA.
+    /// Returns the plain-text recovery codes, which are only shown once.
+    pub async fn confirm(&self, user_id: ObjectId, code: &str) -> Result<Vec<String>, ApiServiceError> {
+        let filter = doc! { "user_id": user_id, "enabled": false };
+        let enrollment = self
+            .collection
+            .find_one(filter, None)
+            .await?
+            .ok_or(ApiServiceError::NotEnrolled)?;
+
+        let step = matching_step(&enrollment.secret, &normalize_code(code)).ok_or(ApiServiceError::InvalidCode)?;
+
+        let recovery_codes: Vec<String> = (0..RECOVERY_CODE_COUNT)
+            .map(|_| generate_token(RECOVERY_CODE_LENGTH).to_lowercase())
+            .collect();
+        let hashes: Vec<String> = recovery_codes.iter().map(|c| hash_token(c)).collect();
+
+        let update = doc! {
+            "$set": {
+                "enabled"        : true,
+                "recovery_codes" : hashes,
+                "last_used_step" : step,
+                "updated_at"     : DateTime::now(),
+            }
+        };
+        // Only the pending enrollment the code was checked against, a concurrent confirm or a new
+        // enrollment in between leaves nothing to match and these codes are never returned
+        let filter = doc! { "_id": enrollment._id, "secret": &enrollment.secret, "enabled": false };
+        let result = self.collection.update_one(filter, update, None).await?;
+        if result.modified_count != 1 {
+            return Err(ApiServiceError::NotEnrolled);
+        }
+
+        Ok(recovery_codes)
+    }
+
+    /// Check whether a user has a confirmed MFA enrollment.
+    pub async fn is_enabled(&self, user_id: ObjectId) -> Result<bool, MongoError> {
+        let count = self
+            .collection
+            .count_documents(doc! { "user_id": user_id, "enabled": true }, None)
+            .await?;
+        Ok(count > 0)
+    }
+
+    /// Verify a second factor for a user. Accepts either a TOTP code, which can only be used once,
+    /// or one of the recovery codes, which is removed on use.
+    pub async fn verify(&self, user_id: ObjectId, code: &str) -> Result<(), ApiServiceError> {
+        let enrollment = self
+            .collection
+            .find_one(doc! { "user_id": user_id, "enabled": true }, None)
+            .await?
+            .ok_or(ApiServiceError::NotEnrolled)?;
+        let code = normalize_code(code);
+
+        if let Some(step) = matching_step(&enrollment.secret, &code) {
+            // Only accept the step if it is newer than the last one used
+            let filter = doc! {
+                "_id": enrollment._id,
+                "$or": [
+                    { "last_used_step": null },
+                    { "last_used_step": { "$lt": step } },
+                ],
+            };
+            let update = doc! { "$set": { "last_used_step": step, "updated_at": DateTime::now() } };
+            let result = self.collection.update_one(filter, update, None).await?;
+            if result.modified_count > 0 {
+                return Ok(());
+            }
+            return Err(ApiServiceError::InvalidCode);
+        }
+
+        let filter = doc! { "_id": enrollment._id, "recovery_codes": hash_token(&code.to_lowercase()) };
+        let update = doc! {
+            "$pull": { "recovery_codes": hash_token(&code.to_lowercase()) },
+            "$set": { "updated_at": DateTime::now() },
+        };
+        let result = self.collection.update_one(filter, update, None).await?;
+        if result.modified_count > 0 {
+            Ok(())
+        } else {
+            Err(ApiServiceError::InvalidCode)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // RFC 6238, Appendix B: SHA-1 with the ASCII secret "12345678901234567890" and 8 digits
+    const RFC_SECRET: &[u8] = b"12345678901234567890";
+    const RFC_VECTORS: [(i64, &str); 6] = [
+        (59, "94287082"),
+        (1_111_111_109, "07081804"),
+        (1_111_111_111, "14050471"),
+        (1_234_567_890, "89005924"),
+        (2_000_000_000, "69279037"),
+        (20_000_000_000, "65353130"),
+    ];
+
+    #[test]
+    fn totp_code_matches_rfc_6238_vectors() {
+        for (time, expected) in RFC_VECTORS {
+            assert_eq!(totp_code(RFC_SECRET, time / TOTP_PERIOD_SECONDS, 8), expected, "T = {}", time);
+        }
+    }
+
+    #[test]
+    fn six_digit_codes_are_the_low_digits_of_the_rfc_vectors() {
+        for (time, expected) in RFC_VECTORS {
+            assert_eq!(totp_code(RFC_SECRET, time / TOTP_PERIOD_SECONDS, TOTP_DIGITS), expected[2..], "T = {}", time);
+        }
+    }
+
+    #[test]
+    fn matching_step_accepts_the_current_and_adjacent_steps_only() {
+        let secret = base32::encode(base32::Alphabet::RFC4648 { padding: false }, RFC_SECRET);
+        let current_step = chrono::Utc::now().timestamp() / TOTP_PERIOD_SECONDS;
+
+        let code = totp_code(RFC_SECRET, current_step, TOTP_DIGITS);
+        // The step may have just moved on, either way the code is within the drift
+        assert!(matching_step(&secret, &code).is_some_and(|step| (step - current_step).abs() <= 1));
+
+        let old_code = totp_code(RFC_SECRET, current_step - 3, TOTP_DIGITS);
+        if old_code != code {
+            assert_eq!(matching_step(&secret, &old_code), None);
+        }
+        assert_eq!(matching_step("not base32!", &code), None);
+    }
+
+    #[test]
+    fn normalize_code_strips_spaces_and_dashes() {
+        assert_eq!(normalize_code(" 123 456 "), "123456");
+        assert_eq!(normalize_code("abcd-efgh"), "abcdefgh");
+    }
+}
@@ -1,6 +1,27 @@
+pub mod api_key_service;
+pub mod audit_service;
 pub mod auth_service;
+pub mod author_service;
+pub mod bookmark_service;
+pub mod course_import_service;
 pub mod course_service;
 pub mod course_search_service;
+pub mod dashboard_service;
+pub mod enrichment_service;
+pub mod export_service;
+pub mod integrity_service;
+pub mod learning_path_service;
+pub mod link_check_service;
+pub mod mfa_service;
+pub mod oidc_service;
+pub mod platform_service;
+pub mod recommendation_service;
+pub mod reference_service;
+pub mod review_service;
+pub mod session_service;
+pub mod suggest_service;
+pub mod topic_service;
 pub mod user_service;
 pub mod user_search_service;
+pub mod verification_service;
 pub mod watched_service;
\ No newline at end of file
@@ -0,0 +1,404 @@
+use crate::models::oidc_model::{ExternalIdentity, IdTokenClaims, OidcLoginState};
+use crate::utils::token::generate_token;
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::Duration;
+use jsonwebtoken::{decode, decode_header, jwk::JwkSet, Algorithm, DecodingKey, Validation};
+use mongodb::{
+    bson::{doc, oid::ObjectId, DateTime},
+    error::Error as MongoError,
+    Collection,
+};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::sync::{Arc, RwLock};
+use thiserror::Error;
+
+const LOGIN_STATE_TTL_MINUTES: i64 = 10;
+const PKCE_VERIFIER_LENGTH: usize = 64;
+
+#[derive(Debug, Error)]
+pub enum ApiServiceError {
+    #[error("OpenID Connect login is not configured")]
+    NotConfigured,
+    #[error("Unknown or expired login state")]
+    InvalidState,
+    #[error("Identity provider error: {0}")]
+    ProviderError(String),
+    #[error("Invalid ID token: {0}")]
+    InvalidIdToken(#[from] jsonwebtoken::errors::Error),
+    #[error("HTTP error: {0}")]
+    HttpError(#[from] reqwest::Error),
+    #[error("Database error: {0}")]
+    DatabaseError(#[from] MongoError),
+}
+
+/// Settings of the OpenID Connect provider, read from the environment in `main.rs`.
+#[derive(Debug, Clone)]
+pub struct OidcConfig {
+    pub issuer_url: String,
+    pub client_id: String,
+    pub client_secret: Option<String>,
+    pub redirect_url: String,
+}
+
+// Subset of the provider discovery document we rely on
+#[derive(Debug, Clone, Deserialize)]
+struct ProviderMetadata {
+    issuer: String,
+    authorization_endpoint: String,
+    token_endpoint: String,
+    jwks_uri: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct TokenResponse {
+    id_token: String,
+}
+
+#[derive(Clone)]
+pub struct ApiService {
+    states: Collection<OidcLoginState>,
+    identities: Collection<ExternalIdentity>,
+    config: Option<OidcConfig>,
+    http: reqwest::Client,
+    metadata: Arc<RwLock<Option<ProviderMetadata>>>,
+}
+
+// Helper function to create the state, nonce and PKCE verifier of a new login.
+fn new_login_state() -> OidcLoginState {
+    let now = chrono::Utc::now();
+    OidcLoginState {
+        _id: None,
+        state: generate_token(32),
+        nonce: generate_token(32),
+        code_verifier: generate_token(PKCE_VERIFIER_LENGTH),
+        expires_at: DateTime::from_chrono(now + Duration::minutes(LOGIN_STATE_TTL_MINUTES)),
+        created_at: DateTime::from_chrono(now),
+    }
+}
+
+// Helper function to derive the PKCE S256 challenge from a verifier.
+fn pkce_challenge(verifier: &str) -> String {
+    URL_SAFE_NO_PAD.encode(Sha256::digest(verifier.as_bytes()))
+}
+
+impl ApiService {
+    pub fn new(
+        states: Collection<OidcLoginState>,
+        identities: Collection<ExternalIdentity>,
+        config: Option<OidcConfig>,
+    ) -> ApiService {
+        ApiService {
+            states,
+            identities,
+            config,
+            http: reqwest::Client::new(),
+            metadata: Arc::new(RwLock::new(None)),
+        }
+    }
+
+    fn config(&self) -> Result<&OidcConfig, ApiServiceError> {
+        self.config.as_ref().ok_or(ApiServiceError::NotConfigured)
+    }
+
+    /// Fetch the provider discovery document, cached after the first successful request.
+    async fn metadata(&self) -> Result<ProviderMetadata, ApiServiceError> {
+        if let Some(metadata) = self.metadata.read().unwrap().as_ref() {
+            return Ok(metadata.clone());
+        }
+
+        let config = self.config()?;
+        let url = format!("{}/.well-known/openid-configuration", config.issuer_url.trim_end_matches('/'));
+        let metadata: ProviderMetadata = self.http.get(url).send().await?.error_for_status()?.json().await?;
+
+        // The discovery document must belong to the configured issuer
+        if metadata.issuer.trim_end_matches('/') != config.issuer_url.trim_end_matches('/') {
+            return Err(ApiServiceError::ProviderError(format!(
+                "Discovery document issuer {} does not match {}",
+                metadata.issuer, config.issuer_url
+            )));
+        }
+
+        *self.metadata.write().unwrap() = Some(metadata.clone());
+        Ok(metadata)
+    }
+
+    /// The issuer identities are linked under.
+    pub fn issuer(&self) -> Result<&str, ApiServiceError> {
+        Ok(&self.config()?.issuer_url)
+    }
+
+    /// Start an authorization code + PKCE login. Returns the provider URL to redirect to and the
+    /// state, which the caller binds to the browser.
+    pub async fn authorization_url(&self, login_hint: Option<&str>) -> Result<(String, String), ApiServiceError> {
+        let metadata = self.metadata().await?;
+        let login_state = new_login_state();
+        self.states.insert_one(&login_state, None).await?;
+        let url = self.build_authorization_url(&metadata, &login_state, login_hint)?;
+        Ok((url, login_state.state))
+    }
+
+    /// How long a login may take, also the lifetime of the state cookie.
+    pub fn login_state_ttl(&self) -> Duration {
+        Duration::minutes(LOGIN_STATE_TTL_MINUTES)
+    }
+
+    // Helper function to build the provider URL of a login.
+    fn build_authorization_url(
+        &self,
+        metadata: &ProviderMetadata,
+        login_state: &OidcLoginState,
+        login_hint: Option<&str>,
+    ) -> Result<String, ApiServiceError> {
+        let config = self.config()?;
+        let challenge = pkce_challenge(&login_state.code_verifier);
+        let mut params = vec![
+            ("response_type", "code"),
+            ("client_id", config.client_id.as_str()),
+            ("redirect_uri", config.redirect_url.as_str()),
+            ("scope", "openid email profile"),
+            ("state", login_state.state.as_str()),
+            ("nonce", login_state.nonce.as_str()),
+            ("code_challenge", challenge.as_str()),
+            ("code_challenge_method", "S256"),
+        ];
+        if let Some(hint) = login_hint {
+            params.push(("login_hint", hint));
+        }
+
+        let url = url::Url::parse_with_params(&metadata.authorization_endpoint, &params)
+            .map_err(|e| ApiServiceError::ProviderError(format!("Invalid authorization endpoint: {}", e)))?;
+        Ok(url.to_string())
+    }
+
+    /// Finish a login: check the state, exchange the code and validate the returned ID token.
+    pub async fn authenticate(&self, code: &str, state: &str) -> Result<IdTokenClaims, ApiServiceError> {
+        let metadata = self.metadata().await?;
+
+        // The state is single use
+        let filter = doc! { "state": state, "expires_at": { "$gt": DateTime::now() } };
+        let login_state = self
+            .states
+            .find_one_and_delete(filter, None)
+            .await?
+            .ok_or(ApiServiceError::InvalidState)?;
+
+        self.exchange_code(code, &login_state, &metadata).await
+    }
+
+    // Helper function to exchange an authorization code and validate the returned ID token against the login.
+    async fn exchange_code(
+        &self,
+        code: &str,
+        login_state: &OidcLoginState,
+        metadata: &ProviderMetadata,
+    ) -> Result<IdTokenClaims, ApiServiceError> {
+        let config = self.config()?;
+        let mut form = vec![
+            ("grant_type", "authorization_code"),
+            ("code", code),
+            ("redirect_uri", config.redirect_url.as_str()),
+            ("client_id", config.client_id.as_str()),
+            ("code_verifier", login_state.code_verifier.as_str()),
+        ];
+        if let Some(secret) = config.client_secret.as_deref() {
+            form.push(("client_secret", secret));
+        }
+
+        let response = self.http.post(&metadata.token_endpoint).form(&form).send().await?;
+        if !response.status().is_success() {
+            return Err(ApiServiceError::ProviderError(format!(
+                "Token endpoint returned {}",
+                response.status()
+            )));
+        }
+        let tokens: TokenResponse = response.json().await?;
+
+        let claims = self.validate_id_token(&tokens.id_token, metadata).await?;
+        if claims.nonce.as_deref() != Some(login_state.nonce.as_str()) {
+            return Err(ApiServiceError::ProviderError("ID token nonce does not match".to_string()));
+        }
+
+        Ok(claims)
+    }
+
+    // Check the signature, issuer, audience and expiry of an ID token.
+    // HMAC-signed tokens are verified with the client secret, asymmetric ones with the provider JWKS.
+    async fn validate_id_token(&self, id_token: &str, metadata: &ProviderMetadata) -> Result<IdTokenClaims, ApiServiceError> {
+        let config = self.config()?;
+        let header = decode_header(id_token)?;
+
+        let key = match header.alg {
+            Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512 => {
+                let secret = config.client_secret.as_deref().ok_or_else(|| {
+                    ApiServiceError::ProviderError("HMAC-signed ID token without a client secret".to_string())
+                })?;
+                DecodingKey::from_secret(secret.as_bytes())
+            }
+            _ => {
+                let jwks: JwkSet = self.http.get(&metadata.jwks_uri).send().await?.error_for_status()?.json().await?;
+                let jwk = match header.kid.as_deref() {
+                    Some(kid) => jwks.find(kid),
+                    None => jwks.keys.first(),
+                }
+                .ok_or_else(|| ApiServiceError::ProviderError("No matching key in provider JWKS".to_string()))?;
+                DecodingKey::from_jwk(jwk)?
+            }
+        };
+
+        let mut validation = Validation::new(header.alg);
+        validation.set_issuer(&[&metadata.issuer]);
+        validation.set_audience(&[&config.client_id]);
+
+        Ok(decode::<IdTokenClaims>(id_token, &key, &validation)?.claims)
+    }
+
+    /// Find the user an external identity is linked to.
+    pub async fn find_linked_user(&self, subject: &str) -> Result<Option<ObjectId>, ApiServiceError> {
+        let filter = doc! { "issuer": self.issuer()?, "subject": subject };
+        let identity = self.identities.find_one(filter, None).await?;
+        Ok(identity.map(|identity| identity.user_id))
+    }
+
+    /// Link an external identity to a user.
+    pub async fn link(&self, user_id: ObjectId, claims: &IdTokenClaims) -> Result<(), ApiServiceError> {
+        let identity = ExternalIdentity {
+            _id: None,
+            user_id,
+            issuer: self.issuer()?.to_string(),
+            subject: claims.sub.clone(),
+            email: claims.email.clone(),
+            created_at: DateTime::now(),
+        };
+        self.identities.insert_one(identity, None).await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::routes::mock_idp_route::{self, MockIdp};
+    use crate::utils::test_server::serve;
+    use actix_web::web;
+    use jsonwebtoken::{encode, EncodingKey, Header};
+    use mongodb::{options::ClientOptions, Client};
+    use reqwest::{header::LOCATION, redirect::Policy};
+
+    const CLIENT_ID: &str = "mylearning";
+    const CLIENT_SECRET: &str = "mock-secret";
+    const REDIRECT_URL: &str = "http://localhost:8080/auth/oidc/callback";
+
+    // Helper function to start the mock provider and a service configured for it with `client_secret`.
+    // The login states are kept by the tests, the collections are never reached.
+    fn setup(client_secret: &str) -> (ApiService, String) {
+        let mock = web::Data::new(MockIdp::new(CLIENT_SECRET.to_string()));
+        let addr = serve(move |cfg| {
+            cfg.app_data(mock.clone());
+            mock_idp_route::init(cfg);
+        });
+        let issuer_url = format!("http://{}/mock-idp", addr);
+        let db = Client::with_options(ClientOptions::default()).unwrap().database("oidc_tests");
+        let config = OidcConfig {
+            issuer_url: issuer_url.clone(),
+            client_id: CLIENT_ID.to_string(),
+            client_secret: Some(client_secret.to_string()),
+            redirect_url: REDIRECT_URL.to_string(),
+        };
+        (ApiService::new(db.collection("states"), db.collection("identities"), Some(config)), issuer_url)
+    }
+
+    // Helper function to open the authorization URL like a browser and return the code and state of the callback.
+    async fn authorize(url: &str) -> (String, Option<String>) {
+        let client = reqwest::Client::builder().redirect(Policy::none()).build().unwrap();
+        let response = client.get(url).send().await.unwrap();
+        assert_eq!(response.status(), 302);
+        let location = url::Url::parse(response.headers()[LOCATION].to_str().unwrap()).unwrap();
+        assert!(location.as_str().starts_with(REDIRECT_URL));
+        let param = |name: &str| location.query_pairs().find(|(key, _)| key == name).map(|(_, value)| value.into_owned());
+        (param("code").unwrap(), param("state"))
+    }
+
+    // Helper function to run a login up to the callback.
+    async fn start_login(service: &ApiService, login_hint: &str) -> (ProviderMetadata, OidcLoginState, String) {
+        let metadata = service.metadata().await.unwrap();
+        let login_state = new_login_state();
+        let url = service.build_authorization_url(&metadata, &login_state, Some(login_hint)).unwrap();
+        let (code, state) = authorize(&url).await;
+        assert_eq!(state.as_deref(), Some(login_state.state.as_str()));
+        (metadata, login_state, code)
+    }
+
+    #[actix_web::test]
+    async fn login_returns_the_identity_of_the_login_hint() {
+        let (service, issuer_url) = setup(CLIENT_SECRET);
+        let (metadata, login_state, code) = start_login(&service, "ada@campus.test").await;
+        assert_eq!(metadata.issuer, issuer_url);
+
+        let claims = service.exchange_code(&code, &login_state, &metadata).await.unwrap();
+        assert_eq!(claims.sub, "mock|ada@campus.test");
+        assert_eq!(claims.email.as_deref(), Some("ada@campus.test"));
+        assert_eq!(claims.email_verified, Some(true));
+        assert_eq!(claims.nonce.as_deref(), Some(login_state.nonce.as_str()));
+    }
+
+    #[actix_web::test]
+    async fn codes_are_single_use() {
+        let (service, _) = setup(CLIENT_SECRET);
+        let (metadata, login_state, code) = start_login(&service, "ada@campus.test").await;
+
+        assert!(service.exchange_code(&code, &login_state, &metadata).await.is_ok());
+        let replay = service.exchange_code(&code, &login_state, &metadata).await;
+        assert!(matches!(replay, Err(ApiServiceError::ProviderError(_))));
+    }
+
+    #[actix_web::test]
+    async fn codes_are_bound_to_the_pkce_verifier() {
+        let (service, _) = setup(CLIENT_SECRET);
+        let (metadata, _, code) = start_login(&service, "ada@campus.test").await;
+
+        let other_login = new_login_state();
+        let result = service.exchange_code(&code, &other_login, &metadata).await;
+        assert!(matches!(result, Err(ApiServiceError::ProviderError(_))));
+    }
+
+    #[actix_web::test]
+    async fn nonce_must_match_the_login() {
+        let (service, _) = setup(CLIENT_SECRET);
+        let (metadata, login_state, code) = start_login(&service, "ada@campus.test").await;
+
+        let replayed = OidcLoginState { nonce: generate_token(32), ..login_state };
+        let result = service.exchange_code(&code, &replayed, &metadata).await;
+        assert!(matches!(result, Err(ApiServiceError::ProviderError(_))));
+    }
+
+    #[actix_web::test]
+    async fn wrong_client_secret_is_rejected() {
+        let (service, _) = setup("not-the-secret");
+        let (metadata, login_state, code) = start_login(&service, "ada@campus.test").await;
+
+        let result = service.exchange_code(&code, &login_state, &metadata).await;
+        assert!(matches!(result, Err(ApiServiceError::ProviderError(_))));
+    }
+
+    #[actix_web::test]
+    async fn id_tokens_are_checked_for_signature_issuer_and_audience() {
+        let (service, issuer_url) = setup(CLIENT_SECRET);
+        let metadata = service.metadata().await.unwrap();
+        let now = chrono::Utc::now().timestamp();
+        let token = |secret: &str, issuer: &str, audience: &str| {
+            let claims = serde_json::json!({ "iss": issuer, "aud": audience, "sub": "mock|eve", "iat": now, "exp": now + 60 });
+            encode(&Header::default(), &claims, &EncodingKey::from_secret(secret.as_bytes())).unwrap()
+        };
+
+        assert!(service.validate_id_token(&token(CLIENT_SECRET, &issuer_url, CLIENT_ID), &metadata).await.is_ok());
+        for forged in [
+            token("another-secret", &issuer_url, CLIENT_ID),
+            token(CLIENT_SECRET, "http://evil.test/mock-idp", CLIENT_ID),
+            token(CLIENT_SECRET, &issuer_url, "another-client"),
+        ] {
+            let result = service.validate_id_token(&forged, &metadata).await;
+            assert!(matches!(result, Err(ApiServiceError::InvalidIdToken(_))));
+        }
+    }
+}
@@ -0,0 +1,56 @@
+use crate::models::platform_model::{Platform, PlatformRequest};
+use crate::services::reference_service::{
+    name_and_slug, validate_url, ApiService as ReferenceService, ApiServiceError, ReferenceEntity,
+};
+use mongodb::bson::{doc, oid::ObjectId, DateTime, Document};
+
+/// Platforms are managed by the shared reference service.
+pub type ApiService = ReferenceService<Platform>;
+
+impl ReferenceEntity for Platform {
+    type Request = PlatformRequest;
+
+    const KIND: &'static str = "platform";
+    const ID_FIELD: &'static str = "platform_id";
+
+    fn id(&self) -> Option<ObjectId> {
+        self._id
+    }
+
+    fn set_id(&mut self, id: Option<ObjectId>) {
+        self._id = id;
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn named(name: String, slug: String, now: DateTime) -> Platform {
+        Platform { _id: None, name, slug, logo_url: None, homepage_url: None, created_at: now, updated_at: now }
+    }
+
+    fn from_request(req: &PlatformRequest, existing: Option<&Platform>) -> Result<Platform, ApiServiceError> {
+        let (name, slug) = name_and_slug(&req.name)?;
+        let now = DateTime::now();
+        Ok(Platform {
+            _id: existing.and_then(|existing| existing._id),
+            name,
+            slug,
+            logo_url: validate_url("logo_url", &req.logo_url)?,
+            homepage_url: validate_url("homepage_url", &req.homepage_url)?,
+            created_at: existing.map_or(now, |existing| existing.created_at),
+            updated_at: now,
+        })
+    }
+
+    fn to_document(&self) -> Document {
+        doc! {
+            "name"         : self.name.clone(),
+            "slug"         : self.slug.clone(),
+            "logo_url"     : self.logo_url.clone(),
+            "homepage_url" : self.homepage_url.clone(),
+            "created_at"   : self.created_at,
+            "updated_at"   : self.updated_at,
+        }
+    }
+}
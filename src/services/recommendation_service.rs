@@ -0,0 +1,224 @@
+use crate::models::{
+    course_model::Course,
+    recommendation_model::{DismissedRecommendation, Recommendation},
+    user_model::User,
+    watched_model::Watched,
+};
+use futures::stream::StreamExt;
+use mongodb::{
+    bson::{doc, oid::ObjectId, DateTime},
+    error::Error as MongoError,
+    options::UpdateOptions,
+    results::{DeleteResult, UpdateResult},
+    Collection,
+};
+use std::collections::{HashMap, HashSet};
+use thiserror::Error;
+
+// Weight of each signal in the final score, they add up to 1
+const TOPIC_WEIGHT: f64 = 0.5;
+const CO_WATCH_WEIGHT: f64 = 0.3;
+const MAJOR_WEIGHT: f64 = 0.2;
+
+#[derive(Debug, Error)]
+pub enum ApiServiceError {
+    #[error("User has no _id")]
+    MissingUserId,
+    #[error("Database error: {0}")]
+    DatabaseError(#[from] MongoError),
+}
+
+/// Suggests courses a learner has not watched yet from three signals: how much their topics
+/// overlap with the learner's history, how many learners who watched the same courses also
+/// watched them, and how popular they are among learners with the same major.
+#[derive(Clone)]
+pub struct ApiService {
+    courses: Collection<Course>,
+    users: Collection<User>,
+    watched: Collection<Watched>,
+    dismissed: Collection<DismissedRecommendation>,
+}
+
+// Helper function to compare topics regardless of case and spacing.
+fn normalize_topic(topic: &str) -> String {
+    topic.trim().to_lowercase()
+}
+
+impl ApiService {
+    pub fn new(
+        courses: Collection<Course>,
+        users: Collection<User>,
+        watched: Collection<Watched>,
+        dismissed: Collection<DismissedRecommendation>,
+    ) -> ApiService {
+        ApiService { courses, users, watched, dismissed }
+    }
+
+    // Get the courses of the user's watched records, archived ones included.
+    async fn watched_course_ids(&self, user: &User, user_id: ObjectId) -> Result<Vec<ObjectId>, MongoError> {
+        let listed: Vec<ObjectId> = user
+            .watched_ids
+            .clone()
+            .unwrap_or_default()
+            .iter()
+            .filter_map(|id| ObjectId::parse_str(id).ok())
+            .collect();
+        let filter = doc! { "$or": [ { "user_id": user_id }, { "_id": { "$in": listed } } ] };
+        let ids = self.watched.distinct("course_id", filter, None).await?;
+        Ok(ids.iter().filter_map(|id| id.as_object_id()).collect())
+    }
+
+    // Count, per course outside `excluded`, how many of the given learners watched it.
+    async fn count_viewers(
+        &self,
+        user_ids: &[ObjectId],
+        excluded: &[ObjectId],
+    ) -> Result<HashMap<ObjectId, i64>, MongoError> {
+        let mut counts = HashMap::new();
+        if user_ids.is_empty() {
+            return Ok(counts);
+        }
+
+        let pipeline = vec![
+            doc! { "$match": { "user_id": { "$in": user_ids }, "course_id": { "$nin": excluded } } },
+            doc! { "$group": { "_id": { "course_id": "$course_id", "user_id": "$user_id" } } },
+            doc! { "$group": { "_id": "$_id.course_id", "viewers": { "$sum": 1 } } },
+        ];
+        let mut cursor = self.watched.aggregate(pipeline, None).await?;
+        while let Some(result) = cursor.next().await {
+            let group = result?;
+            if let (Ok(course_id), Ok(viewers)) = (group.get_object_id("_id"), group.get_i32("viewers")) {
+                counts.insert(course_id, viewers as i64);
+            }
+        }
+        Ok(counts)
+    }
+
+    /// Get the dismissed courses of a user.
+    pub async fn get_dismissed(&self, user_id: ObjectId) -> Result<Vec<ObjectId>, MongoError> {
+        let ids = self.dismissed.distinct("course_id", doc! { "user_id": user_id }, None).await?;
+        Ok(ids.iter().filter_map(|id| id.as_object_id()).collect())
+    }
+
+    /// Stop suggesting a course to a user. Dismissing twice is a no-op.
+    pub async fn dismiss(&self, user_id: ObjectId, course_id: ObjectId) -> Result<UpdateResult, MongoError> {
+        let filter = doc! { "user_id": user_id, "course_id": course_id };
+        let update = doc! { "$setOnInsert": { "created_at": DateTime::now() } };
+        let options = UpdateOptions::builder().upsert(true).build();
+        self.dismissed.update_one(filter, update, options).await
+    }
+
+    /// Allow a dismissed course to be suggested again.
+    pub async fn undismiss(&self, user_id: ObjectId, course_id: ObjectId) -> Result<DeleteResult, MongoError> {
+        self.dismissed.delete_one(doc! { "user_id": user_id, "course_id": course_id }, None).await
+    }
+
+    /// Score the courses the user has neither watched nor dismissed and return the best `limit` ones.
+    /// With `explain`, every recommendation lists the signals that contributed to its score.
+    pub async fn recommend(&self, user: &User, limit: usize, explain: bool) -> Result<Vec<Recommendation>, ApiServiceError> {
+        let user_id = user._id.ok_or(ApiServiceError::MissingUserId)?;
+        let watched_ids = self.watched_course_ids(user, user_id).await?;
+        let mut excluded = watched_ids.clone();
+        excluded.extend(self.get_dismissed(user_id).await?);
+
+        // Topic profile of the learner, how often each topic appears in their watched courses
+        let mut profile: HashMap<String, f64> = HashMap::new();
+        let mut watched_topics: HashSet<String> = HashSet::new(); // As written, to find the candidates
+        let mut cursor = self.courses.find(doc! { "_id": { "$in": &watched_ids } }, None).await?;
+        while let Some(result) = cursor.next().await {
+            let course = result?;
+            for topic in course.topics.iter().map(|t| normalize_topic(t)).collect::<HashSet<_>>() {
+                *profile.entry(topic).or_default() += 1.0;
+            }
+            watched_topics.extend(course.topics);
+        }
+        let profile_total: f64 = profile.values().sum();
+
+        // Learners who watched at least one of the same courses
+        let filter = doc! {
+            "course_id": { "$in": &watched_ids },
+            "user_id": { "$type": "objectId", "$ne": user_id },
+        };
+        let peers: Vec<ObjectId> = self
+            .watched
+            .distinct("user_id", filter, None)
+            .await?
+            .iter()
+            .filter_map(|id| id.as_object_id())
+            .collect();
+        let co_watch = self.count_viewers(&peers, &excluded).await?;
+
+        // Other learners with the same major
+        let classmates: Vec<ObjectId> = if user.major.trim().is_empty() {
+            Vec::new()
+        } else {
+            let filter = doc! { "major": &user.major, "_id": { "$ne": user_id }, "deleted_at": null };
+            self.users
+                .distinct("_id", filter, None)
+                .await?
+                .iter()
+                .filter_map(|id| id.as_object_id())
+                .collect()
+        };
+        let major_popularity = self.count_viewers(&classmates, &excluded).await?;
+
+        // Only courses with at least one signal can score above zero, the others are never read
+        let signalled: HashSet<ObjectId> = co_watch.keys().chain(major_popularity.keys()).copied().collect();
+        let watched_topics: Vec<String> = watched_topics.into_iter().collect();
+        let signalled: Vec<ObjectId> = signalled.into_iter().collect();
+        let filter = doc! {
+            "_id": { "$nin": &excluded },
+            "deleted_at": null,
+            "$or": [ { "topics": { "$in": watched_topics } }, { "_id": { "$in": signalled } } ],
+        };
+
+        let mut recommendations = Vec::new();
+        let mut cursor = self.courses.find(filter, None).await?;
+        while let Some(result) = cursor.next().await {
+            let course = result?;
+            let Some(course_id) = course._id else { continue };
+            let mut reasons = Vec::new();
+
+            let topics: HashSet<String> = course.topics.iter().map(|t| normalize_topic(t)).collect();
+            let mut shared: Vec<&String> = topics.iter().filter(|t| profile.contains_key(*t)).collect();
+            let topic_score = if profile_total > 0.0 {
+                shared.iter().map(|t| profile[*t]).sum::<f64>() / profile_total
+            } else {
+                0.0
+            };
+            if !shared.is_empty() {
+                shared.sort();
+                let shared: Vec<&str> = shared.iter().map(|t| t.as_str()).collect();
+                reasons.push(format!("Shares topics with courses you watched: {}", shared.join(", ")));
+            }
+
+            let co_watchers = co_watch.get(&course_id).copied().unwrap_or(0);
+            let co_watch_score = if peers.is_empty() { 0.0 } else { co_watchers as f64 / peers.len() as f64 };
+            if co_watchers > 0 {
+                reasons.push(format!("Watched by {} learners who watched the same courses as you", co_watchers));
+            }
+
+            let classmate_viewers = major_popularity.get(&course_id).copied().unwrap_or(0);
+            let major_score = if classmates.is_empty() {
+                0.0
+            } else {
+                classmate_viewers as f64 / classmates.len() as f64
+            };
+            if classmate_viewers > 0 {
+                reasons.push(format!("Watched by {} other {} learners", classmate_viewers, user.major));
+            }
+
+            let score = TOPIC_WEIGHT * topic_score + CO_WATCH_WEIGHT * co_watch_score + MAJOR_WEIGHT * major_score;
+            if score > 0.0 {
+                if !explain {
+                    reasons.clear();
+                }
+                recommendations.push(Recommendation { course, score, reasons });
+            }
+        }
+
+        recommendations.sort_by(|a, b| b.score.total_cmp(&a.score));
+        recommendations.truncate(limit);
+        Ok(recommendations)
+    }
+}
@@ -0,0 +1,300 @@
+use crate::extractors::audit_extractor::AuditContext;
+use crate::models::{audit_model::AuditAction, course_model::{Course, ReferenceMigrationReport}};
+use crate::services::{audit_service::ApiService as AuditService, suggest_service::ApiService as SuggestService};
+use crate::utils::{mongo::is_duplicate_key, slug::slugify};
+use futures::stream::StreamExt;
+use mongodb::{
+    bson::{doc, oid::ObjectId, DateTime, Document},
+    error::Error as MongoError,
+    options::{FindOptions, IndexOptions},
+    results::{DeleteResult, InsertOneResult, UpdateResult},
+    Collection, IndexModel,
+};
+use serde::{de::DeserializeOwned, Serialize};
+use std::collections::HashMap;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum ApiServiceError {
+    #[error("Invalid ObjectId format")]
+    InvalidObjectId,
+    #[error("Invalid request: {0}")]
+    InvalidRequest(String),
+    #[error("Conflict: {0}")]
+    Conflict(String),
+    #[error("Still used by courses")]
+    InUse,
+    #[error("Database error: {0}")]
+    DatabaseError(#[from] MongoError),
+}
+
+/// A named record courses reference by `_id`, with a copy of its name on the course,
+/// e.g. a platform or an author.
+pub trait ReferenceEntity: Clone + Serialize + DeserializeOwned + Unpin + Send + Sync + 'static {
+    type Request;
+
+    const KIND: &'static str;     // Audit target type and name field of the course, e.g. `platform`
+    const ID_FIELD: &'static str; // Reference field of the course, e.g. `platform_id`
+
+    fn id(&self) -> Option<ObjectId>;
+    fn set_id(&mut self, id: Option<ObjectId>);
+    fn name(&self) -> &str;
+
+    /// A record with only a name, for the names of courses without a record yet.
+    fn named(name: String, slug: String, now: DateTime) -> Self;
+
+    /// Build a record from a request. `existing` is the record being updated.
+    fn from_request(req: &Self::Request, existing: Option<&Self>) -> Result<Self, ApiServiceError>;
+
+    fn to_document(&self) -> Document;
+}
+
+/// Check an optional link, only http and https are accepted.
+pub fn validate_url(field: &str, value: &Option<String>) -> Result<Option<String>, ApiServiceError> {
+    let Some(value) = value.as_deref().map(str::trim).filter(|value| !value.is_empty()) else {
+        return Ok(None);
+    };
+    match url::Url::parse(value) {
+        Ok(parsed) if parsed.scheme() == "http" || parsed.scheme() == "https" => Ok(Some(value.to_string())),
+        _ => Err(ApiServiceError::InvalidRequest(format!("{} must be an http or https URL", field))),
+    }
+}
+
+/// Check the name of a request and derive its slug.
+pub fn name_and_slug(name: &str) -> Result<(String, String), ApiServiceError> {
+    let name = name.trim().to_string();
+    let slug = slugify(&name);
+    if slug.is_empty() {
+        return Err(ApiServiceError::InvalidRequest("name is required".to_string()));
+    }
+    Ok((name, slug))
+}
+
+#[derive(Clone)]
+pub struct ApiService<T: ReferenceEntity> {
+    collection: Collection<T>,
+    courses: Collection<Document>, // Course collection, names are copied onto the courses
+    suggest: SuggestService,       // Refreshed when names change on the courses
+    audit: AuditService,
+}
+
+impl<T: ReferenceEntity> ApiService<T> {
+    pub fn new(collection: Collection<T>, courses: Collection<Document>, suggest: SuggestService, audit: AuditService) -> ApiService<T> {
+        ApiService { collection, courses, suggest, audit }
+    }
+
+    /// Create the unique index on `slug`.
+    pub async fn ensure_indexes(&self) -> Result<(), MongoError> {
+        let options = IndexOptions::builder().name("unique_slug".to_string()).unique(true).build();
+        let index = IndexModel::builder().keys(doc! { "slug": 1 }).options(options).build();
+        self.collection.create_index(index, None).await?;
+        Ok(())
+    }
+
+    /// Get all records, sorted by name.
+    pub async fn get_all(&self) -> Result<Vec<T>, MongoError> {
+        let options = FindOptions::builder().sort(doc! { "name": 1 }).build();
+        let mut cursor = self.collection.find(None, options).await?;
+        let mut docs = Vec::new();
+
+        while let Some(result) = cursor.next().await {
+            match result {
+                Ok(record) => docs.push(record),
+                Err(err) => return Err(err),
+            }
+        }
+
+        Ok(docs)
+    }
+
+    /// Get a record by its MongoDB `_id`.
+    pub async fn get_by_id(&self, record_id: &str) -> Result<Option<T>, ApiServiceError> {
+        let object_id = ObjectId::parse_str(record_id).map_err(|_| ApiServiceError::InvalidObjectId)?;
+        Ok(self.get(object_id).await?)
+    }
+
+    /// Get a record by its `_id`, for the references of a course.
+    pub async fn get(&self, record_id: ObjectId) -> Result<Option<T>, MongoError> {
+        self.collection.find_one(doc! { "_id": record_id }, None).await
+    }
+
+    /// Find the record with this name or another spelling of it, without creating it.
+    pub async fn find(&self, name: &str) -> Result<Option<T>, MongoError> {
+        let slug = slugify(name);
+        if slug.is_empty() {
+            return Ok(None);
+        }
+        self.collection.find_one(doc! { "slug": slug }, None).await
+    }
+
+    /// Find the record with this name or another spelling of it, creating it when there is none.
+    /// Returns `None` for an empty name.
+    pub async fn resolve(&self, name: &str, ctx: &AuditContext) -> Result<Option<T>, MongoError> {
+        if let Some(record) = self.find(name).await? {
+            return Ok(Some(record));
+        }
+        let slug = slugify(name);
+        if slug.is_empty() {
+            return Ok(None);
+        }
+
+        let mut record = T::named(name.trim().to_string(), slug.clone(), DateTime::now());
+        match self.collection.insert_one(&record, None).await {
+            Ok(result) => {
+                record.set_id(result.inserted_id.as_object_id());
+                let target_id = record.id().map(|id| id.to_hex());
+                self.audit.record(ctx, AuditAction::Create, T::KIND, target_id, None, Some(record.to_document())).await;
+                Ok(Some(record))
+            }
+            // Created concurrently
+            Err(e) if is_duplicate_key(&e) => self.collection.find_one(doc! { "slug": &slug }, None).await,
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Create a new record in the collection.
+    pub async fn create(&self, req: &T::Request, ctx: &AuditContext) -> Result<InsertOneResult, ApiServiceError> {
+        let record = T::from_request(req, None)?;
+        let result = match self.collection.insert_one(&record, None).await {
+            Ok(result) => result,
+            Err(e) if is_duplicate_key(&e) => {
+                return Err(ApiServiceError::Conflict(format!("{} already exists", record.name())))
+            }
+            Err(e) => return Err(e.into()),
+        };
+        let target_id = result.inserted_id.as_object_id().map(|id| id.to_hex());
+        self.audit.record(ctx, AuditAction::Create, T::KIND, target_id, None, Some(record.to_document())).await;
+        Ok(result)
+    }
+
+    /// Update a record by its MongoDB `_id`. A new name is copied onto its courses.
+    pub async fn update(&self, req: &T::Request, record_id: &str, ctx: &AuditContext) -> Result<Option<UpdateResult>, ApiServiceError> {
+        let Some(before) = self.get_by_id(record_id).await? else {
+            return Ok(None);
+        };
+        let record = T::from_request(req, Some(&before))?;
+        let changes = record.to_document();
+        let result = match self
+            .collection
+            .update_one(doc! { "_id": before.id() }, doc! { "$set": changes.clone() }, None)
+            .await
+        {
+            Ok(result) => result,
+            Err(e) if is_duplicate_key(&e) => {
+                return Err(ApiServiceError::Conflict(format!("{} already exists", record.name())))
+            }
+            Err(e) => return Err(e.into()),
+        };
+
+        if result.modified_count > 0 {
+            if record.name() != before.name() {
+                let filter = doc! { T::ID_FIELD: before.id() };
+                self.courses.update_many(filter.clone(), doc! { "$set": { T::KIND: record.name() } }, None).await?;
+                self.refresh_suggestions(filter).await?;
+            }
+            self.audit.record_update(ctx, T::KIND, record_id, &before.to_document(), &changes).await;
+        }
+        Ok(Some(result))
+    }
+
+    /// Delete a record by its MongoDB `_id`, only when no course references it.
+    pub async fn delete(&self, record_id: &str, ctx: &AuditContext) -> Result<DeleteResult, ApiServiceError> {
+        let object_id = ObjectId::parse_str(record_id).map_err(|_| ApiServiceError::InvalidObjectId)?;
+        if self.courses.count_documents(doc! { T::ID_FIELD: object_id }, None).await? > 0 {
+            return Err(ApiServiceError::InUse);
+        }
+        let filter = doc! { "_id": object_id };
+        let before = self.collection.find_one(filter.clone(), None).await?;
+        let result = self.collection.delete_one(filter, None).await?;
+
+        if let (Some(before), true) = (before, result.deleted_count > 0) {
+            let before = Some(before.to_document());
+            self.audit.record(ctx, AuditAction::Delete, T::KIND, Some(record_id.to_string()), before, None).await;
+        }
+        Ok(result)
+    }
+
+    // Helper function to reload the autocomplete terms of the live courses matching a filter,
+    // after their names were rewritten behind the course service.
+    async fn refresh_suggestions(&self, filter: Document) -> Result<(), MongoError> {
+        let filter = doc! { "$and": [filter, { "deleted_at": null }] };
+        let mut cursor = self.courses.clone_with_type::<Course>().find(filter, None).await?;
+        while let Some(course) = cursor.next().await {
+            let course = course?;
+            if let Some(course_id) = course._id {
+                self.suggest.upsert(course_id, &course);
+            }
+        }
+        Ok(())
+    }
+
+    /// Turn the names of every course into references. Spellings with the same slug are merged
+    /// into one record, named after the most common spelling.
+    pub async fn migrate(&self, dry_run: bool, ctx: &AuditContext) -> Result<ReferenceMigrationReport, MongoError> {
+        let mut report = ReferenceMigrationReport { dry_run, ..Default::default() };
+        let name_field = format!("${}", T::KIND);
+        let pipeline = vec![
+            doc! { "$match": { T::KIND: { "$type": "string" } } },
+            doc! { "$group": { "_id": name_field, "count": { "$sum": 1 } } },
+            doc! { "$sort": { "count": -1, "_id": 1 } },
+        ];
+        let mut cursor = self.courses.aggregate(pipeline, None).await?;
+
+        // Spellings grouped by slug, the most common first
+        let mut slugs: Vec<String> = vec![];
+        let mut spellings: HashMap<String, Vec<String>> = HashMap::new();
+        while let Some(result) = cursor.next().await {
+            let Ok(spelling) = result?.get_str("_id").map(str::to_string) else { continue };
+            let slug = slugify(&spelling);
+            if slug.is_empty() {
+                continue;
+            }
+            report.spellings += 1;
+            if !spellings.contains_key(&slug) {
+                slugs.push(slug.clone());
+            }
+            spellings.entry(slug).or_default().push(spelling);
+        }
+
+        for slug in slugs {
+            let group = &spellings[&slug];
+            let record = match self.collection.find_one(doc! { "slug": &slug }, None).await? {
+                Some(record) => Some(record),
+                None => {
+                    report.created.push(group[0].trim().to_string());
+                    if dry_run {
+                        None
+                    } else {
+                        self.resolve(&group[0], ctx).await?
+                    }
+                }
+            };
+
+            let mut modified = 0;
+            for spelling in group {
+                match record.as_ref().and_then(|record| record.id().map(|id| (id, record.name()))) {
+                    Some((id, name)) => {
+                        let filter = doc! {
+                            T::KIND: spelling,
+                            "$or": [{ T::KIND: { "$ne": name } }, { T::ID_FIELD: { "$ne": id } }],
+                        };
+                        modified += if dry_run {
+                            self.courses.count_documents(filter, None).await?
+                        } else {
+                            let update = doc! { "$set": { T::KIND: name, T::ID_FIELD: id } };
+                            self.courses.update_many(filter, update, None).await?.modified_count
+                        };
+                    }
+                    None => modified += self.courses.count_documents(doc! { T::KIND: spelling }, None).await?,
+                }
+            }
+            report.courses_updated += modified;
+
+            if let (false, true, Some(id)) = (dry_run, modified > 0, record.as_ref().and_then(|record| record.id())) {
+                self.refresh_suggestions(doc! { T::ID_FIELD: id }).await?;
+            }
+        }
+
+        Ok(report)
+    }
+}
@@ -0,0 +1,245 @@
+use crate::extractors::audit_extractor::AuditContext;
+use crate::models::{
+    audit_model::AuditAction,
+    review_model::{Review, ReviewSort},
+};
+use crate::services::{audit_service::ApiService as AuditService, course_service::ApiService as CourseService};
+use crate::utils::mongo::is_duplicate_key;
+use futures::stream::StreamExt;
+use mongodb::{
+    bson::{doc, oid::ObjectId, DateTime, Document},
+    error::Error as MongoError,
+    options::{FindOneAndUpdateOptions, FindOptions, IndexOptions, ReturnDocument},
+    Collection, IndexModel,
+};
+use thiserror::Error;
+
+pub const MIN_RATING: i32 = 1;
+pub const MAX_RATING: i32 = 5;
+
+#[derive(Debug, Error)]
+pub enum ApiServiceError {
+    #[error("Invalid ObjectId format")]
+    InvalidObjectId,
+    #[error("Rating must be between 1 and 5")]
+    InvalidRating,
+    #[error("Database error: {0}")]
+    DatabaseError(#[from] MongoError),
+}
+
+/// Reviews of courses. The aggregate rating of a course only counts the reviews that are
+/// not hidden, and every review change is applied to it with a single atomic update.
+#[derive(Clone)]
+pub struct ApiService {
+    collection: Collection<Review>,
+    course_service: CourseService,
+    audit: AuditService,
+}
+
+// Helper function to convert a `Review` into a MongoDB Document for the audit log.
+fn review_to_document(r: &Review) -> Document {
+    doc! {
+        "course_id" : r.course_id,
+        "user_id"   : r.user_id,
+        "rating"    : r.rating,
+        "comment"   : r.comment.clone(),
+        "hidden"    : r.hidden,
+        "flagged"   : r.flagged,
+    }
+}
+
+impl ApiService {
+    pub fn new(collection: Collection<Review>, course_service: CourseService, audit: AuditService) -> ApiService {
+        ApiService { collection, course_service, audit }
+    }
+
+    /// Create the unique index allowing a single review per learner and course.
+    pub async fn ensure_indexes(&self) -> Result<(), MongoError> {
+        let options = IndexOptions::builder().name("unique_course_user".to_string()).unique(true).build();
+        let index = IndexModel::builder()
+            .keys(doc! { "course_id": 1, "user_id": 1 })
+            .options(options)
+            .build();
+        self.collection.create_index(index, None).await?;
+        Ok(())
+    }
+
+    /// Get the reviews of a course, hidden ones only with `include_hidden`.
+    pub async fn get_by_course(
+        &self,
+        course_id: &str,
+        sort: ReviewSort,
+        include_hidden: bool,
+    ) -> Result<Vec<Review>, ApiServiceError> {
+        let object_id = ObjectId::parse_str(course_id).map_err(|_| ApiServiceError::InvalidObjectId)?;
+        let mut filter = doc! { "course_id": object_id };
+        if !include_hidden {
+            filter.insert("hidden", false);
+        }
+        let sort = match sort {
+            ReviewSort::Newest => doc! { "created_at": -1 },
+            ReviewSort::Oldest => doc! { "created_at": 1 },
+            ReviewSort::Highest => doc! { "rating": -1, "created_at": -1 },
+            ReviewSort::Lowest => doc! { "rating": 1, "created_at": -1 },
+        };
+        let options = FindOptions::builder().sort(sort).build();
+        let mut cursor = self.collection.find(filter, options).await?;
+        let mut docs = Vec::new();
+
+        while let Some(result) = cursor.next().await {
+            match result {
+                Ok(review) => docs.push(review),
+                Err(err) => return Err(err.into()),
+            }
+        }
+
+        Ok(docs)
+    }
+
+    /// Get the flagged reviews waiting for moderation, oldest first.
+    pub async fn get_flagged(&self) -> Result<Vec<Review>, MongoError> {
+        let options = FindOptions::builder().sort(doc! { "updated_at": 1 }).build();
+        let mut cursor = self.collection.find(doc! { "flagged": true }, options).await?;
+        let mut docs = Vec::new();
+
+        while let Some(result) = cursor.next().await {
+            match result {
+                Ok(review) => docs.push(review),
+                Err(err) => return Err(err),
+            }
+        }
+
+        Ok(docs)
+    }
+
+    pub async fn get_by_id(&self, review_id: &str) -> Result<Option<Review>, ApiServiceError> {
+        let object_id = ObjectId::parse_str(review_id).map_err(|_| ApiServiceError::InvalidObjectId)?;
+        Ok(self.collection.find_one(doc! { "_id": object_id }, None).await?)
+    }
+
+    /// Create the review of a learner for a course, or replace their rating and comment.
+    /// Returns the review and whether it was created.
+    pub async fn upsert(
+        &self,
+        course_id: ObjectId,
+        user_id: ObjectId,
+        rating: i32,
+        comment: &str,
+        ctx: &AuditContext,
+    ) -> Result<(Review, bool), ApiServiceError> {
+        if !(MIN_RATING..=MAX_RATING).contains(&rating) {
+            return Err(ApiServiceError::InvalidRating);
+        }
+
+        let now = DateTime::now();
+        let filter = doc! { "course_id": course_id, "user_id": user_id };
+        let update = doc! {
+            "$set": { "rating": rating, "comment": comment, "updated_at": now },
+            "$setOnInsert": { "hidden": false, "flagged": false, "flag_reasons": [], "flagged_by": [], "created_at": now },
+        };
+        let options = FindOneAndUpdateOptions::builder()
+            .upsert(true)
+            .return_document(ReturnDocument::Before)
+            .build();
+
+        // Two concurrent first reviews race on the unique index, the loser updates the winner's review
+        let before = match self.collection.find_one_and_update(filter.clone(), update.clone(), options.clone()).await {
+            Ok(before) => before,
+            Err(e) if is_duplicate_key(&e) => self.collection.find_one_and_update(filter.clone(), update, options).await?,
+            Err(e) => return Err(e.into()),
+        };
+        let after = self
+            .collection
+            .find_one(filter, None)
+            .await?
+            .ok_or_else(|| MongoError::custom("Review disappeared after upsert"))?;
+        let target_id = after._id.map(|id| id.to_hex());
+
+        match before {
+            None => {
+                self.course_service.apply_rating(course_id, rating as i64, 1).await?;
+                self.audit.record(ctx, AuditAction::Create, "review", target_id, None, Some(review_to_document(&after))).await;
+                Ok((after, true))
+            }
+            Some(before) => {
+                if !before.hidden && before.rating != rating {
+                    self.course_service.apply_rating(course_id, (rating - before.rating) as i64, 0).await?;
+                }
+                if let Some(target_id) = target_id {
+                    self.audit
+                        .record_update(ctx, "review", &target_id, &review_to_document(&before), &review_to_document(&after))
+                        .await;
+                }
+                Ok((after, false))
+            }
+        }
+    }
+
+    /// Delete a review. Returns the deleted review, `None` when it does not exist.
+    pub async fn delete(&self, review_id: &str, ctx: &AuditContext) -> Result<Option<Review>, ApiServiceError> {
+        let object_id = ObjectId::parse_str(review_id).map_err(|_| ApiServiceError::InvalidObjectId)?;
+        let deleted = self.collection.find_one_and_delete(doc! { "_id": object_id }, None).await?;
+
+        if let Some(ref review) = deleted {
+            if !review.hidden {
+                self.course_service.apply_rating(review.course_id, -(review.rating as i64), -1).await?;
+            }
+            let before = Some(review_to_document(review));
+            self.audit.record(ctx, AuditAction::Delete, "review", Some(review_id.to_string()), before, None).await;
+        }
+        Ok(deleted)
+    }
+
+    /// Hide or show a review. Hiding also clears the flag, the report has been handled.
+    /// Returns the updated review, `None` when it does not exist.
+    pub async fn set_hidden(&self, review_id: &str, hidden: bool, ctx: &AuditContext) -> Result<Option<Review>, ApiServiceError> {
+        let object_id = ObjectId::parse_str(review_id).map_err(|_| ApiServiceError::InvalidObjectId)?;
+        let filter = doc! { "_id": object_id, "hidden": !hidden };
+        let update = doc! { "$set": { "hidden": hidden, "flagged": false, "updated_at": DateTime::now() } };
+        let options = FindOneAndUpdateOptions::builder().return_document(ReturnDocument::After).build();
+
+        match self.collection.find_one_and_update(filter, update, options).await? {
+            Some(review) => {
+                let (sum_delta, count_delta) = if hidden { (-(review.rating as i64), -1) } else { (review.rating as i64, 1) };
+                self.course_service.apply_rating(review.course_id, sum_delta, count_delta).await?;
+                let before = doc! { "hidden": !hidden };
+                let after = doc! { "hidden": hidden };
+                self.audit
+                    .record(ctx, AuditAction::Update, "review", Some(review_id.to_string()), Some(before), Some(after))
+                    .await;
+                Ok(Some(review))
+            }
+            // Already in the requested state, or missing
+            None => Ok(self.collection.find_one(doc! { "_id": object_id }, None).await?),
+        }
+    }
+
+    /// Report a review for moderation. Each user reports a review once, reporting it again changes nothing.
+    pub async fn flag(
+        &self,
+        review_id: &str,
+        user_id: ObjectId,
+        reason: Option<&str>,
+        ctx: &AuditContext,
+    ) -> Result<Option<Review>, ApiServiceError> {
+        let object_id = ObjectId::parse_str(review_id).map_err(|_| ApiServiceError::InvalidObjectId)?;
+        let mut update = doc! {
+            "$set": { "flagged": true, "updated_at": DateTime::now() },
+            "$addToSet": { "flagged_by": user_id },
+        };
+        if let Some(reason) = reason {
+            update.insert("$push", doc! { "flag_reasons": reason });
+        }
+        let filter = doc! { "_id": object_id, "flagged_by": { "$ne": user_id } };
+        let options = FindOneAndUpdateOptions::builder().return_document(ReturnDocument::After).build();
+        let review = self.collection.find_one_and_update(filter, update, options).await?;
+        let Some(review) = review else {
+            // Missing, or already reported by this user
+            return Ok(self.collection.find_one(doc! { "_id": object_id }, None).await?);
+        };
+
+        let after = Some(doc! { "flagged": true, "reason": reason });
+        self.audit.record(ctx, AuditAction::Update, "review", Some(review_id.to_string()), None, after).await;
+        Ok(Some(review))
+    }
+}
@@ -0,0 +1,68 @@
+use crate::models::session_model::{Session, SessionKind};
+use crate::utils::token::{generate_token, hash_token};
+use chrono::Duration;
+use mongodb::{bson::{doc, oid::ObjectId, DateTime}, error::Error as MongoError, results::DeleteResult, Collection};
+
+const TOKEN_LENGTH: usize = 48;
+const SESSION_TTL_HOURS: i64 = 12;
+const MFA_CHALLENGE_TTL_MINUTES: i64 = 5;
+const EMAIL_VERIFICATION_TTL_HOURS: i64 = 48;
+
+#[derive(Clone)]
+pub struct ApiService {
+    collection: Collection<Session>,
+}
+
+impl ApiService {
+    pub fn new(collection: Collection<Session>) -> ApiService {
+        ApiService { collection }
+    }
+
+    /// Create a new session (or MFA challenge, or email verification) for a user and return its plain-text token.
+    /// Only the hash of the token is stored.
+    pub async fn create(&self, user_id: ObjectId, kind: SessionKind) -> Result<String, MongoError> {
+        let ttl = match kind {
+            SessionKind::Session => Duration::hours(SESSION_TTL_HOURS),
+            SessionKind::MfaChallenge => Duration::minutes(MFA_CHALLENGE_TTL_MINUTES),
+            SessionKind::EmailVerification => Duration::hours(EMAIL_VERIFICATION_TTL_HOURS),
+        };
+        let now = chrono::Utc::now();
+        let token = generate_token(TOKEN_LENGTH);
+
+        let session = Session {
+            _id: None,
+            token_hash: hash_token(&token),
+            user_id,
+            kind,
+            expires_at: DateTime::from_chrono(now + ttl),
+            created_at: DateTime::from_chrono(now),
+        };
+        self.collection.insert_one(session, None).await?;
+
+        Ok(token)
+    }
+
+    /// Find a non-expired session of the given kind by its plain-text token.
+    pub async fn find_valid(&self, token: &str, kind: SessionKind) -> Result<Option<Session>, MongoError> {
+        self.collection.find_one(valid_filter(token, kind), None).await
+    }
+
+    /// Find a non-expired session of the given kind and delete it, so the token can only be used once.
+    pub async fn consume(&self, token: &str, kind: SessionKind) -> Result<Option<Session>, MongoError> {
+        self.collection.find_one_and_delete(valid_filter(token, kind), None).await
+    }
+
+    /// Delete every session of the given kind of a user, e.g. the verification tokens sent to an old address.
+    pub async fn revoke_all(&self, user_id: ObjectId, kind: SessionKind) -> Result<DeleteResult, MongoError> {
+        self.collection.delete_many(doc! { "user_id": user_id, "kind": kind.as_str() }, None).await
+    }
+}
+
+// Helper function to build the lookup filter for a token.
+fn valid_filter(token: &str, kind: SessionKind) -> mongodb::bson::Document {
+    doc! {
+        "token_hash" : hash_token(token),
+        "kind"       : kind.as_str(),
+        "expires_at" : { "$gt": DateTime::now() },
+    }
+}
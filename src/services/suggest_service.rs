@@ -0,0 +1,331 @@
+use crate::models::{course_model::Course, suggest_model::{SuggestKind, Suggestion}};
+use mongodb::bson::oid::ObjectId;
+use std::{
+    cmp::Ordering,
+    collections::{HashMap, HashSet},
+    sync::{Arc, RwLock},
+};
+
+// One title, author or topic, shared by every course that has it
+struct Term {
+    text: String,        // As first written, returned to the client
+    chars: Vec<char>,    // Normalized
+    word_starts: Vec<usize>,
+    courses: HashSet<ObjectId>,
+}
+
+#[derive(Default)]
+struct Index {
+    terms: HashMap<(SuggestKind, String), Term>,
+    courses: HashMap<ObjectId, Vec<(SuggestKind, String)>>, // Terms each course contributes to
+}
+
+/// In-process autocomplete index over course titles, authors and topics. The course service
+/// keeps it in sync on every write, so it only has to be loaded once at startup.
+#[derive(Clone, Default)]
+pub struct ApiService {
+    index: Arc<RwLock<Index>>,
+}
+
+// Helper function to lowercase a text and reduce it to words separated by single spaces.
+fn normalize(text: &str) -> String {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+// Edits allowed for a query, short queries must match exactly
+fn max_distance(query_len: usize) -> usize {
+    match query_len {
+        0..=2 => 0,
+        3..=5 => 1,
+        _ => 2,
+    }
+}
+
+// Helper function to compute the smallest edit distance (with transpositions) between the query and
+// any prefix of `text`, or `None` when it exceeds `max`.
+fn prefix_distance(query: &[char], text: &[char], max: usize) -> Option<usize> {
+    let m = query.len();
+    let n = text.len().min(m + max);
+    let mut rows = vec![vec![0usize; n + 1]; m + 1];
+    for (i, row) in rows.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    rows[0] = (0..=n).collect();
+    for i in 1..=m {
+        for j in 1..=n {
+            let cost = usize::from(query[i - 1] != text[j - 1]);
+            let mut best = (rows[i - 1][j] + 1).min(rows[i][j - 1] + 1).min(rows[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && query[i - 1] == text[j - 2] && query[i - 2] == text[j - 1] {
+                best = best.min(rows[i - 2][j - 2] + 1);
+            }
+            rows[i][j] = best;
+        }
+    }
+    rows[m].iter().copied().min().filter(|distance| *distance <= max)
+}
+
+// Helper function to score a term against the query. Exact and prefix matches of the whole text
+// rank first, then prefixes of an inner word, then fuzzy matches by distance.
+fn match_term(query: &[char], term: &Term) -> Option<(f64, usize)> {
+    if term.chars == query {
+        return Some((1.0, 0));
+    }
+    if term.chars.starts_with(query) {
+        return Some((0.9, 0));
+    }
+    if term.word_starts.iter().skip(1).any(|start| term.chars[*start..].starts_with(query)) {
+        return Some((0.75, 0));
+    }
+
+    let max = max_distance(query.len());
+    if max == 0 {
+        return None;
+    }
+    let mut best: Option<(f64, usize)> = None;
+    for (position, start) in term.word_starts.iter().enumerate() {
+        if let Some(distance) = prefix_distance(query, &term.chars[*start..], max) {
+            let base = if position == 0 { 0.6 } else { 0.45 };
+            let score = base / distance as f64;
+            if best.is_none_or(|(best_score, _)| score > best_score) {
+                best = Some((score, distance));
+            }
+        }
+    }
+    best
+}
+
+impl Index {
+    fn insert(&mut self, course_id: ObjectId, kind: SuggestKind, text: &str) {
+        let normalized = normalize(text);
+        if normalized.is_empty() {
+            return;
+        }
+        let key = (kind, normalized);
+        let term = self.terms.entry(key.clone()).or_insert_with(|| {
+            let chars: Vec<char> = key.1.chars().collect();
+            let word_starts = (0..chars.len()).filter(|i| *i == 0 || chars[i - 1] == ' ').collect();
+            Term { text: text.trim().to_string(), chars, word_starts, courses: HashSet::new() }
+        });
+        term.courses.insert(course_id);
+        self.courses.entry(course_id).or_default().push(key);
+    }
+
+    fn remove(&mut self, course_id: ObjectId) {
+        for key in self.courses.remove(&course_id).unwrap_or_default() {
+            if let Some(term) = self.terms.get_mut(&key) {
+                term.courses.remove(&course_id);
+                if term.courses.is_empty() {
+                    self.terms.remove(&key);
+                }
+            }
+        }
+    }
+}
+
+impl ApiService {
+    pub fn new() -> ApiService {
+        ApiService::default()
+    }
+
+    /// Replace the whole index, used at startup.
+    pub fn rebuild(&self, courses: &[Course]) {
+        let mut index = Index::default();
+        for course in courses {
+            if let Some(course_id) = course._id {
+                ApiService::index_course(&mut index, course_id, course);
+            }
+        }
+        *self.index.write().unwrap() = index;
+    }
+
+    // Helper function to add the terms of a course.
+    fn index_course(index: &mut Index, course_id: ObjectId, course: &Course) {
+        index.insert(course_id, SuggestKind::Title, &course.title);
+        index.insert(course_id, SuggestKind::Author, &course.author);
+        for topic in &course.topics {
+            index.insert(course_id, SuggestKind::Topic, topic);
+        }
+    }
+
+    /// Add a course or replace its terms after an update.
+    pub fn upsert(&self, course_id: ObjectId, course: &Course) {
+        let mut index = self.index.write().unwrap();
+        index.remove(course_id);
+        ApiService::index_course(&mut index, course_id, course);
+    }
+
+    /// Remove a course, its terms disappear once no other course uses them.
+    pub fn remove(&self, course_id: ObjectId) {
+        self.index.write().unwrap().remove(course_id);
+    }
+
+    /// Get the best completions for a partial or misspelled query.
+    pub fn suggest(&self, query: &str, limit: usize) -> Vec<Suggestion> {
+        let query: Vec<char> = normalize(query).chars().collect();
+        if query.is_empty() {
+            return vec![];
+        }
+
+        let index = self.index.read().unwrap();
+        let mut suggestions: Vec<Suggestion> = index
+            .terms
+            .iter()
+            .filter_map(|((kind, _), term)| {
+                let (score, distance) = match_term(&query, term)?;
+                // Terms shared by many courses rank slightly higher
+                let popularity = 0.05 * (1.0 + term.courses.len() as f64).ln();
+                let course_id = match term.courses.len() {
+                    1 => term.courses.iter().next().copied(),
+                    _ => None,
+                };
+                Some(Suggestion {
+                    text: term.text.clone(),
+                    kind: *kind,
+                    score: score + popularity,
+                    distance,
+                    course_count: term.courses.len(),
+                    course_id,
+                })
+            })
+            .collect();
+
+        suggestions.sort_by(|a, b| {
+            b.score
+                .partial_cmp(&a.score)
+                .unwrap_or(Ordering::Equal)
+                .then(a.distance.cmp(&b.distance))
+                .then(a.text.len().cmp(&b.text.len()))
+                .then_with(|| a.text.cmp(&b.text))
+        });
+        suggestions.truncate(limit);
+        suggestions
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mongodb::bson::DateTime;
+
+    fn chars(text: &str) -> Vec<char> {
+        text.chars().collect()
+    }
+
+    // Helper function to build the term of a text the way the index does.
+    fn term(text: &str) -> Term {
+        let mut index = Index::default();
+        index.insert(ObjectId::new(), SuggestKind::Title, text);
+        index.terms.into_values().next().unwrap()
+    }
+
+    fn course(title: &str, author: &str, topics: &[&str]) -> Course {
+        Course {
+            _id: None,
+            title: title.to_string(),
+            platform: String::new(),
+            author: author.to_string(),
+            platform_id: None,
+            author_id: None,
+            duration: 60,
+            language: "en".to_string(),
+            description: String::new(),
+            url: String::new(),
+            topics: topics.iter().map(|topic| topic.to_string()).collect(),
+            created_at: DateTime::now(),
+            updated_at: DateTime::now(),
+            rating_average: 0.0,
+            rating_count: 0,
+            rating_sum: 0,
+            link_checks: vec![],
+            link_failures: 0,
+            link_broken_at: None,
+            deleted_at: None,
+        }
+    }
+
+    fn texts(suggestions: &[Suggestion]) -> Vec<&str> {
+        suggestions.iter().map(|suggestion| suggestion.text.as_str()).collect()
+    }
+
+    #[test]
+    fn prefix_distance_counts_edits_against_the_closest_prefix() {
+        assert_eq!(prefix_distance(&chars("rus"), &chars("rust programming"), 1), Some(0));
+        assert_eq!(prefix_distance(&chars("rist"), &chars("rust"), 1), Some(1));
+        assert_eq!(prefix_distance(&chars("rst"), &chars("rust"), 1), Some(1));
+        // A swap of two neighbours is a single edit
+        assert_eq!(prefix_distance(&chars("rsut"), &chars("rust"), 1), Some(1));
+        assert_eq!(prefix_distance(&chars("java"), &chars("ruby"), 2), None);
+    }
+
+    #[test]
+    fn prefixes_rank_above_inner_words_and_fuzzy_matches() {
+        let rust = term("Rust Programming");
+        assert_eq!(match_term(&chars("rust programming"), &rust), Some((1.0, 0)));
+        assert_eq!(match_term(&chars("rust pro"), &rust), Some((0.9, 0)));
+        assert_eq!(match_term(&chars("prog"), &rust), Some((0.75, 0)));
+        assert_eq!(match_term(&chars("rsut"), &rust), Some((0.6, 1)));
+        assert_eq!(match_term(&chars("porg"), &rust), Some((0.45, 1)));
+    }
+
+    #[test]
+    fn allowed_edits_grow_with_the_query() {
+        let python = term("Python");
+        // Two characters must match exactly
+        assert_eq!(match_term(&chars("pz"), &python), None);
+        // One edit from three characters
+        assert_eq!(match_term(&chars("pyt"), &python), Some((0.9, 0)));
+        assert_eq!(match_term(&chars("pzt"), &python), Some((0.6, 1)));
+        assert_eq!(match_term(&chars("pxtx"), &python), None);
+        // Two edits from six characters
+        assert_eq!(match_term(&chars("pxthxn"), &python), Some((0.3, 2)));
+    }
+
+    #[test]
+    fn suggestions_are_ranked_by_match_then_distance() {
+        let service = ApiService::new();
+        service.upsert(ObjectId::new(), &course("Rust Programming", "Ferris", &[]));
+        service.upsert(ObjectId::new(), &course("Learning Rust", "Ann", &[]));
+        service.upsert(ObjectId::new(), &course("Trust and Safety", "Bob", &[]));
+        service.upsert(ObjectId::new(), &course("Ruby Basics", "Cleo", &[]));
+
+        // Two edits are too many for four characters
+        let found = service.suggest("rust", 10);
+        assert_eq!(texts(&found), vec!["Rust Programming", "Learning Rust", "Trust and Safety"]);
+        assert_eq!(found.iter().map(|suggestion| suggestion.distance).collect::<Vec<_>>(), vec![0, 0, 1]);
+        assert_eq!(service.suggest("rust", 1).len(), 1);
+    }
+
+    #[test]
+    fn removing_a_course_keeps_the_terms_other_courses_share() {
+        let (first, second) = (ObjectId::new(), ObjectId::new());
+        let service = ApiService::new();
+        service.upsert(first, &course("Async Rust", "Ferris", &["rust"]));
+        service.upsert(second, &course("Embedded Rust", "Ferris", &["rust"]));
+
+        service.remove(first);
+        let topic = service.suggest("rust", 10).into_iter().find(|suggestion| suggestion.kind == SuggestKind::Topic).unwrap();
+        assert_eq!((topic.course_count, topic.course_id), (1, Some(second)));
+        assert!(!texts(&service.suggest("async", 10)).contains(&"Async Rust"));
+
+        service.remove(second);
+        assert!(service.suggest("rust", 10).is_empty());
+        assert!(service.index.read().unwrap().courses.is_empty());
+    }
+
+    #[test]
+    fn upsert_replaces_the_terms_of_a_course() {
+        let course_id = ObjectId::new();
+        let service = ApiService::new();
+        service.upsert(course_id, &course("Intro to Go", "Rob", &["go"]));
+        service.upsert(course_id, &course("Intro to Zig", "Rob", &["zig"]));
+
+        assert!(service.suggest("intro to go", 10).iter().all(|suggestion| suggestion.text != "Intro to Go"));
+        assert_eq!(texts(&service.suggest("intro to zig", 10)), vec!["Intro to Zig"]);
+        let authors = service.suggest("rob", 10);
+        assert_eq!((authors.len(), authors[0].course_count), (1, 1));
+    }
+}
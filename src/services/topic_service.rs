@@ -0,0 +1,404 @@
+use crate::extractors::audit_extractor::AuditContext;
+use crate::models::{
+    audit_model::AuditAction,
+    topic_model::{Topic, TopicMigrationReport, TopicRequest},
+};
+use crate::services::audit_service::ApiService as AuditService;
+use crate::utils::{mongo::is_duplicate_key, slug::slugify};
+use futures::stream::StreamExt;
+use mongodb::{
+    bson::{doc, oid::ObjectId, DateTime, Document},
+    error::Error as MongoError,
+    options::{FindOptions, IndexOptions},
+    results::{DeleteResult, InsertOneResult, UpdateResult},
+    Collection, IndexModel,
+};
+use std::collections::{HashMap, HashSet};
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum ApiServiceError {
+    #[error("Invalid ObjectId format")]
+    InvalidObjectId,
+    #[error("Invalid topic: {0}")]
+    InvalidTopic(String),
+    #[error("Topic conflict: {0}")]
+    Conflict(String),
+    #[error("The topic has child topics")]
+    HasChildren,
+    #[error("Database error: {0}")]
+    DatabaseError(#[from] MongoError),
+}
+
+/// Requested search topics mapped to every value a course may store for them.
+pub type TopicExpansion = HashMap<String, Vec<String>>;
+
+#[derive(Clone)]
+pub struct ApiService {
+    collection: Collection<Topic>,
+    courses: Collection<Document>, // Course collection, read and rewritten by the migration
+    audit: AuditService,
+}
+
+/// The whole taxonomy in memory, loaded for each lookup since it stays small.
+pub struct Taxonomy {
+    aliases: HashMap<String, String>,        // Slug or synonym to canonical slug
+    synonyms: HashMap<String, Vec<String>>,  // Canonical slug to its synonyms
+    parents: HashMap<String, String>,        // Canonical slug to the slug of its parent
+    children: HashMap<String, Vec<String>>,  // Canonical slug to the slugs of its children
+}
+
+// Helper function to convert a `Topic` into a MongoDB Document.
+fn topic_to_document(t: &Topic) -> Document {
+    doc! {
+        "slug"       : t.slug.clone(),
+        "name"       : t.name.clone(),
+        "synonyms"   : t.synonyms.clone(),
+        "parent"     : t.parent.clone(),
+        "created_at" : t.created_at,
+        "updated_at" : t.updated_at,
+    }
+}
+
+impl Taxonomy {
+    fn from_topics(topics: &[Topic]) -> Taxonomy {
+        let mut taxonomy = Taxonomy {
+            aliases: HashMap::new(),
+            synonyms: HashMap::new(),
+            parents: HashMap::new(),
+            children: HashMap::new(),
+        };
+        for topic in topics {
+            taxonomy.add(topic);
+        }
+        taxonomy
+    }
+
+    fn add(&mut self, topic: &Topic) {
+        for synonym in &topic.synonyms {
+            self.aliases.insert(synonym.clone(), topic.slug.clone());
+        }
+        // A slug always wins over a synonym of another topic
+        self.aliases.insert(topic.slug.clone(), topic.slug.clone());
+        self.synonyms.insert(topic.slug.clone(), topic.synonyms.clone());
+        if let Some(ref parent) = topic.parent {
+            self.parents.insert(topic.slug.clone(), parent.clone());
+            self.children.entry(parent.clone()).or_default().push(topic.slug.clone());
+        }
+    }
+
+    /// Whether a slug belongs to a managed topic.
+    pub fn contains(&self, slug: &str) -> bool {
+        self.synonyms.contains_key(slug)
+    }
+
+    /// The canonical slug of a topic, its slugified form when it is not managed.
+    pub fn canonical(&self, topic: &str) -> String {
+        let slug = slugify(topic);
+        self.aliases.get(&slug).cloned().unwrap_or(slug)
+    }
+
+    /// Canonicalize a list of topics, dropping empty ones and duplicates.
+    pub fn normalize(&self, topics: &[String]) -> Vec<String> {
+        let mut seen = HashSet::new();
+        topics
+            .iter()
+            .map(|topic| self.canonical(topic))
+            .filter(|slug| !slug.is_empty() && seen.insert(slug.clone()))
+            .collect()
+    }
+
+    /// A topic with its synonyms and all its descendants with theirs. The text as given is kept
+    /// so courses written before the taxonomy still match.
+    pub fn expand(&self, topic: &str) -> Vec<String> {
+        let mut values = vec![topic.to_string()];
+        let mut visited: HashSet<String> = HashSet::new();
+        let mut pending = vec![self.canonical(topic)];
+        while let Some(slug) = pending.pop() {
+            if !visited.insert(slug.clone()) {
+                continue;
+            }
+            values.push(slug.clone());
+            values.extend(self.synonyms.get(&slug).cloned().unwrap_or_default());
+            pending.extend(self.children.get(&slug).cloned().unwrap_or_default());
+        }
+        let mut seen = HashSet::new();
+        values.retain(|value| seen.insert(value.clone()));
+        values
+    }
+}
+
+impl ApiService {
+    pub fn new(collection: Collection<Topic>, courses: Collection<Document>, audit: AuditService) -> ApiService {
+        ApiService { collection, courses, audit }
+    }
+
+    /// Create the unique index on `slug`.
+    pub async fn ensure_indexes(&self) -> Result<(), MongoError> {
+        let options = IndexOptions::builder().name("unique_slug".to_string()).unique(true).build();
+        let index = IndexModel::builder().keys(doc! { "slug": 1 }).options(options).build();
+        self.collection.create_index(index, None).await?;
+        Ok(())
+    }
+
+    /// Get all topics, sorted by slug.
+    pub async fn get_all(&self) -> Result<Vec<Topic>, MongoError> {
+        let options = FindOptions::builder().sort(doc! { "slug": 1 }).build();
+        let mut cursor = self.collection.find(None, options).await?;
+        let mut docs = Vec::new();
+
+        while let Some(result) = cursor.next().await {
+            match result {
+                Ok(topic) => docs.push(topic),
+                Err(err) => return Err(err),
+            }
+        }
+
+        Ok(docs)
+    }
+
+    /// Get a topic by its MongoDB `_id`.
+    pub async fn get_by_id(&self, topic_id: &str) -> Result<Option<Topic>, ApiServiceError> {
+        let object_id = ObjectId::parse_str(topic_id).map_err(|_| ApiServiceError::InvalidObjectId)?;
+        let result = self.collection.find_one(doc! { "_id": object_id }, None).await?;
+        Ok(result)
+    }
+
+    /// Load the taxonomy to normalize or expand topics.
+    pub async fn taxonomy(&self) -> Result<Taxonomy, MongoError> {
+        Ok(Taxonomy::from_topics(&self.get_all().await?))
+    }
+
+    /// Canonicalize the topics of a course before it is written.
+    pub async fn normalize(&self, topics: &[String]) -> Result<Vec<String>, MongoError> {
+        Ok(self.taxonomy().await?.normalize(topics))
+    }
+
+    /// Expand the topics of a search to their synonyms and descendants.
+    pub async fn expand(&self, topics: &[String]) -> Result<TopicExpansion, MongoError> {
+        if topics.is_empty() {
+            return Ok(TopicExpansion::new());
+        }
+        let taxonomy = self.taxonomy().await?;
+        Ok(topics.iter().map(|topic| (topic.clone(), taxonomy.expand(topic))).collect())
+    }
+
+    // Helper function to build a topic from a request and check it against the rest of the taxonomy.
+    // `existing` is the topic being updated.
+    async fn validate(&self, req: &TopicRequest, existing: Option<&Topic>) -> Result<Topic, ApiServiceError> {
+        let name = req.name.trim().to_string();
+        if name.is_empty() {
+            return Err(ApiServiceError::InvalidTopic("name is required".to_string()));
+        }
+        let slug = slugify(req.slug.as_deref().unwrap_or(&name));
+        if slug.is_empty() {
+            return Err(ApiServiceError::InvalidTopic("slug is empty".to_string()));
+        }
+        if existing.is_some_and(|existing| existing.slug != slug) {
+            return Err(ApiServiceError::InvalidTopic("The slug of a topic cannot be changed".to_string()));
+        }
+        let mut seen = HashSet::from([slug.clone()]);
+        let synonyms: Vec<String> = req
+            .synonyms
+            .iter()
+            .map(|synonym| slugify(synonym))
+            .filter(|synonym| !synonym.is_empty() && seen.insert(synonym.clone()))
+            .collect();
+
+        // Every spelling must point to a single topic
+        let others: Vec<Topic> = self.get_all().await?.into_iter().filter(|t| t.slug != slug).collect();
+        let taxonomy = Taxonomy::from_topics(&others);
+        if existing.is_none() && taxonomy.aliases.contains_key(&slug) {
+            return Err(ApiServiceError::Conflict(format!("{} is already used by topic {}", slug, taxonomy.aliases[&slug])));
+        }
+        if let Some(synonym) = synonyms.iter().find(|synonym| taxonomy.aliases.contains_key(*synonym)) {
+            return Err(ApiServiceError::Conflict(format!("{} is already used by topic {}", synonym, taxonomy.aliases[synonym])));
+        }
+
+        // The parent must exist and must not be a descendant of the topic
+        let parent = match req.parent.as_deref().map(slugify) {
+            Some(parent) if parent == slug => {
+                return Err(ApiServiceError::InvalidTopic("A topic cannot be its own parent".to_string()))
+            }
+            Some(parent) if !taxonomy.contains(&parent) => {
+                return Err(ApiServiceError::InvalidTopic(format!("Parent topic {} not found", parent)))
+            }
+            Some(parent) => {
+                let mut ancestor = Some(parent.clone());
+                while let Some(current) = ancestor {
+                    if current == slug {
+                        return Err(ApiServiceError::InvalidTopic("The parent is a descendant of the topic".to_string()));
+                    }
+                    ancestor = taxonomy.parents.get(&current).cloned();
+                }
+                Some(parent)
+            }
+            None => None,
+        };
+
+        let now = DateTime::now();
+        Ok(Topic {
+            _id: existing.and_then(|existing| existing._id),
+            slug,
+            name,
+            synonyms,
+            parent,
+            created_at: existing.map_or(now, |existing| existing.created_at),
+            updated_at: now,
+        })
+    }
+
+    /// Create a new topic in the collection.
+    pub async fn create(&self, req: &TopicRequest, ctx: &AuditContext) -> Result<InsertOneResult, ApiServiceError> {
+        let topic = self.validate(req, None).await?;
+        let result = match self.collection.insert_one(&topic, None).await {
+            Ok(result) => result,
+            Err(e) if is_duplicate_key(&e) => {
+                return Err(ApiServiceError::Conflict(format!("{} already exists", topic.slug)))
+            }
+            Err(e) => return Err(e.into()),
+        };
+        let target_id = result.inserted_id.as_object_id().map(|id| id.to_hex());
+        self.audit.record(ctx, AuditAction::Create, "topic", target_id, None, Some(topic_to_document(&topic))).await;
+        Ok(result)
+    }
+
+    /// Update the name, synonyms and parent of a topic by its MongoDB `_id`.
+    pub async fn update(&self, req: &TopicRequest, topic_id: &str, ctx: &AuditContext) -> Result<Option<UpdateResult>, ApiServiceError> {
+        let Some(before) = self.get_by_id(topic_id).await? else {
+            return Ok(None);
+        };
+        let topic = self.validate(req, Some(&before)).await?;
+        let changes = topic_to_document(&topic);
+        let result = self
+            .collection
+            .update_one(doc! { "_id": before._id }, doc! { "$set": changes.clone() }, None)
+            .await?;
+
+        if result.modified_count > 0 {
+            self.audit.record_update(ctx, "topic", topic_id, &topic_to_document(&before), &changes).await;
+        }
+        Ok(Some(result))
+    }
+
+    /// Delete a topic by its MongoDB `_id`. Topics with children must be emptied first, courses
+    /// keep the slug as a free-form topic.
+    pub async fn delete(&self, topic_id: &str, ctx: &AuditContext) -> Result<DeleteResult, ApiServiceError> {
+        let object_id = ObjectId::parse_str(topic_id).map_err(|_| ApiServiceError::InvalidObjectId)?;
+        let filter = doc! { "_id": object_id };
+        let before = self.collection.find_one(filter.clone(), None).await?;
+        if let Some(ref before) = before {
+            if self.collection.count_documents(doc! { "parent": &before.slug }, None).await? > 0 {
+                return Err(ApiServiceError::HasChildren);
+            }
+        }
+        let result = self.collection.delete_one(filter, None).await?;
+
+        if let (Some(before), true) = (before, result.deleted_count > 0) {
+            let before = Some(topic_to_document(&before));
+            self.audit.record(ctx, AuditAction::Delete, "topic", Some(topic_id.to_string()), before, None).await;
+        }
+        Ok(result)
+    }
+
+    // Helper function to add the synonyms of the migration request to their topics. Spellings
+    // already pointing to the same topic are skipped, the ones used by another topic are rejected.
+    async fn add_migration_synonyms(
+        &self,
+        taxonomy: &mut Taxonomy,
+        synonyms: &HashMap<String, String>,
+        report: &mut TopicMigrationReport,
+        ctx: &AuditContext,
+    ) -> Result<(), ApiServiceError> {
+        let mut additions: HashMap<String, Vec<String>> = HashMap::new();
+        for (spelling, target) in synonyms {
+            let synonym = slugify(spelling);
+            let target = slugify(target);
+            if synonym.is_empty() {
+                return Err(ApiServiceError::InvalidTopic(format!("Synonym {:?} is empty", spelling)));
+            }
+            if !taxonomy.contains(&target) {
+                return Err(ApiServiceError::InvalidTopic(format!("Topic {} not found", target)));
+            }
+            match taxonomy.aliases.get(&synonym) {
+                Some(existing) if *existing == target => continue,
+                Some(existing) => {
+                    return Err(ApiServiceError::Conflict(format!("{} is already used by topic {}", synonym, existing)))
+                }
+                None => additions.entry(target).or_default().push(synonym),
+            }
+        }
+
+        for (target, new_synonyms) in additions {
+            if !report.dry_run {
+                let filter = doc! { "slug": &target };
+                let before = self.collection.find_one(filter.clone(), None).await?;
+                let update = doc! {
+                    "$addToSet": { "synonyms": { "$each": &new_synonyms } },
+                    "$set": { "updated_at": DateTime::now() },
+                };
+                self.collection.update_one(filter.clone(), update, None).await?;
+                let after = self.collection.find_one(filter, None).await?;
+                if let (Some(before), Some(after)) = (before, after) {
+                    let target_id = before._id.map(|id| id.to_hex()).unwrap_or_default();
+                    self.audit
+                        .record_update(ctx, "topic", &target_id, &topic_to_document(&before), &topic_to_document(&after))
+                        .await;
+                }
+            }
+            for synonym in new_synonyms {
+                taxonomy.aliases.insert(synonym.clone(), target.clone());
+                taxonomy.synonyms.entry(target.clone()).or_default().push(synonym.clone());
+                report.synonyms_added.push(synonym);
+            }
+        }
+        report.synonyms_added.sort();
+        Ok(())
+    }
+
+    /// Canonicalize the topics of every course, including deleted ones. The spellings of
+    /// `synonyms` are first added to their topics, the free-form topics still matching no managed
+    /// topic are reported so they can be created or mapped in another run.
+    pub async fn migrate(
+        &self,
+        dry_run: bool,
+        synonyms: &HashMap<String, String>,
+        ctx: &AuditContext,
+    ) -> Result<TopicMigrationReport, ApiServiceError> {
+        let mut taxonomy = self.taxonomy().await?;
+        let mut report = TopicMigrationReport { dry_run, ..Default::default() };
+        self.add_migration_synonyms(&mut taxonomy, synonyms, &mut report, ctx).await?;
+
+        let options = FindOptions::builder().projection(doc! { "topics": 1 }).build();
+        let mut cursor = self.courses.find(None, options).await?;
+        while let Some(course) = cursor.next().await {
+            let course = course?;
+            let Ok(course_id) = course.get_object_id("_id") else { continue };
+            let topics: Vec<String> = course
+                .get_array("topics")
+                .map(|topics| topics.iter().filter_map(|topic| topic.as_str().map(str::to_string)).collect())
+                .unwrap_or_default();
+            report.courses_scanned += 1;
+
+            let normalized = taxonomy.normalize(&topics);
+            for slug in normalized.iter().filter(|slug| !taxonomy.contains(slug)) {
+                *report.unmatched_topics.entry(slug.clone()).or_default() += 1;
+            }
+            if normalized == topics {
+                continue;
+            }
+            report.courses_updated += 1;
+            if !dry_run {
+                self.courses
+                    .update_one(doc! { "_id": course_id }, doc! { "$set": { "topics": &normalized } }, None)
+                    .await?;
+                self.audit
+                    .record_update(ctx, "course", &course_id.to_hex(), &doc! { "topics": topics }, &doc! { "topics": normalized })
+                    .await;
+                report.updated_course_ids.push(course_id);
+            }
+        }
+
+        Ok(report)
+    }
+}
@@ -1,5 +1,5 @@
 use crate::models::user_model::User; // Assuming `User` model exists
-use mongodb::{bson::Document, error::Error as MongoError};
+use mongodb::{bson::{doc, Document}, error::Error as MongoError, Cursor};
 use futures::stream::StreamExt;
 
 #[derive(Clone)]
@@ -14,7 +14,7 @@ impl ApiService {
 
     /// Search for a user by email.
     pub async fn search(&self, filter: Document) -> Result<Vec<User>, MongoError> {
-        let mut cursor = self.collection.find(filter, None).await?;
+        let mut cursor = self.search_stream(filter).await?;
         let mut results = Vec::new();
 
         while let Some(doc) = cursor.next().await {
@@ -26,4 +26,10 @@ impl ApiService {
 
         Ok(results)
     }
+    /// Same search as `search`, returning the cursor so the results can be streamed without buffering.
+    pub async fn search_stream(&self, filter: Document) -> Result<Cursor<User>, MongoError> {
+        // Deleted documents never show up in search results
+        let filter = doc! { "$and": [filter, { "deleted_at": null }] };
+        self.collection.find(filter, None).await
+    }
 }
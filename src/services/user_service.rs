@@ -1,9 +1,13 @@
-use crate::models::user_model::User;
+use crate::extractors::audit_extractor::AuditContext;
+use crate::models::{audit_model::AuditAction, user_model::User};
+use crate::services::audit_service::ApiService as AuditService;
+use crate::utils::mongo::is_duplicate_key;
 use mongodb::{
-    bson::{doc, oid::ObjectId, Document},
+    bson::{doc, oid::ObjectId, DateTime, Document},
     error::Error as MongoError,
+    options::IndexOptions,
     results::{DeleteResult, UpdateResult, InsertOneResult},
-    Collection,
+    Collection, Cursor, IndexModel,
 };
 use futures::stream::StreamExt;
 use thiserror::Error;
@@ -12,6 +16,8 @@ use thiserror::Error;
 pub enum ApiServiceError {
     #[error("Invalid ObjectId format")]
     InvalidObjectId,
+    #[error("Email already in use")]
+    EmailTaken,
     #[error("Database error: {0}")]
     DatabaseError(#[from] MongoError),
 }
@@ -19,6 +25,7 @@ pub enum ApiServiceError {
 #[derive(Clone)]
 pub struct ApiService {
     collection: Collection<User>,
+    audit: AuditService,
 }
 
 // Helper function to convert a `Course` into a MongoDB Document.
@@ -29,6 +36,7 @@ fn user_to_document(u: &User) -> Document {
         "major"         : u.major.clone(),
         "email"         : u.email.clone(),
         "password"      : u.password.clone(),
+        "email_verified": u.email_verified,
         "watched_ids"   : u.watched_ids.clone().unwrap_or_default(), // Use an empty Vec if None
         "created_at"    : u.created_at,
         "updated_at"    : u.updated_at,
@@ -36,13 +44,22 @@ fn user_to_document(u: &User) -> Document {
 }
 
 impl ApiService {
-    pub fn new(collection: Collection<User>) -> ApiService {
-        ApiService { collection }
+    pub fn new(collection: Collection<User>, audit: AuditService) -> ApiService {
+        ApiService { collection, audit }
     }
 
-    /// Get all courses from the collection.
+    /// Create the unique index on `email`, deleted users included so restoring one never collides.
+    /// Fails while duplicates exist.
+    pub async fn ensure_indexes(&self) -> Result<(), MongoError> {
+        let options = IndexOptions::builder().name("unique_email".to_string()).unique(true).build();
+        let index = IndexModel::builder().keys(doc! { "email": 1 }).options(options).build();
+        self.collection.create_index(index, None).await?;
+        Ok(())
+    }
+
+    /// Get all users from the collection, except the deleted ones.
     pub async fn get_all(&self) -> Result<Vec<User>, MongoError> {
-        let mut cursor = self.collection.find(None, None).await?;
+        let mut cursor = self.collection.find(doc! { "deleted_at": null }, None).await?;
         let mut docs = Vec::new();
 
         while let Some(result) = cursor.next().await {
@@ -55,30 +72,61 @@ impl ApiService {
         Ok(docs)
     }
 
-    /// Get a course by its MongoDB `_id`.
+    /// Open a cursor over all users, except the deleted ones, to stream them without buffering.
+    pub async fn stream_all(&self) -> Result<Cursor<User>, MongoError> {
+        self.collection.find(doc! { "deleted_at": null }, None).await
+    }
+
+    /// Get a user by its MongoDB `_id`, unless it is deleted.
     pub async fn get_by_id(&self, course_id: &str) -> Result<Option<User>, ApiServiceError> {
         let object_id = ObjectId::parse_str(course_id).map_err(|_| ApiServiceError::InvalidObjectId)?;
-        let filter = doc! { "_id": object_id };
+        let filter = doc! { "_id": object_id, "deleted_at": null };
         let result = self.collection.find_one(filter, None).await?;
         Ok(result)
     }
 
+    /// Check whether a user exists and is not deleted.
+    pub async fn exists(&self, user_id: ObjectId) -> Result<bool, MongoError> {
+        let count = self.collection.count_documents(doc! { "_id": user_id, "deleted_at": null }, None).await?;
+        Ok(count > 0)
+    }
+
+    /// Get the `_id` of every user, including the deleted ones.
+    pub async fn get_ids(&self) -> Result<Vec<ObjectId>, MongoError> {
+        let ids = self.collection.distinct("_id", None, None).await?;
+        Ok(ids.iter().filter_map(|id| id.as_object_id()).collect())
+    }
+
     /// Create a new course in the collection.
-    pub async fn create(&self, u: &User) -> Result<InsertOneResult, MongoError> {
-        self.collection.insert_one(u, None).await
+    pub async fn create(&self, u: &User, ctx: &AuditContext) -> Result<InsertOneResult, MongoError> {
+        let result = self.collection.insert_one(u, None).await?;
+        let target_id = result.inserted_id.as_object_id().map(|id| id.to_hex());
+        self.audit.record(ctx, AuditAction::Create, "user", target_id, None, Some(user_to_document(u))).await;
+        Ok(result)
     }
 
-    /// Update an existing user (except the password) by its MongoDB `_id`.
-    pub async fn update(&self, updated_user: &User, user_id: &str) -> Result<UpdateResult, ApiServiceError> {
+    /// Update an existing user (except the password) by its MongoDB `_id`. Also returns whether the
+    /// email changed, the new address is then unverified until a new token is confirmed.
+    pub async fn update(&self, updated_user: &User, user_id: &str, ctx: &AuditContext) -> Result<(UpdateResult, bool), ApiServiceError> {
         let object_id = ObjectId::parse_str(user_id).map_err(|_| ApiServiceError::InvalidObjectId)?;
 
         // Fetch the current user from the database
-        let filter = doc! { "_id": object_id };
+        let filter = doc! { "_id": object_id, "deleted_at": null };
         let existing_user = self.collection.find_one(filter.clone(), None).await?;
 
         if let Some(mut existing_user) = existing_user {
+            let before = user_to_document(&existing_user);
+
             // Keep the old password
             let password = existing_user.password.clone();
+            let email_changed = existing_user.email != updated_user.email;
+            if email_changed {
+                let taken = doc! { "email": &updated_user.email, "_id": { "$ne": object_id }, "deleted_at": null };
+                if self.collection.count_documents(taken, None).await? > 0 {
+                    return Err(ApiServiceError::EmailTaken);
+                }
+                existing_user.email_verified = false;
+            }
             existing_user.name = updated_user.name.clone();
             existing_user.lastname = updated_user.lastname.clone();
             existing_user.major = updated_user.major.clone();
@@ -88,22 +136,180 @@ impl ApiService {
             existing_user.password = password; // Preserve the old password
 
             // Convert updated user to a document
-            let update = doc! { "$set": user_to_document(&existing_user) };
+            let changes = user_to_document(&existing_user);
+            let update = doc! { "$set": changes.clone() };
 
-            // Perform the update
-            let result = self.collection.update_one(filter, update, None).await?;
-            Ok(result)
+            // Perform the update, the unique index catches a concurrent taker and deleted users
+            let result = match self.collection.update_one(filter, update, None).await {
+                Ok(result) => result,
+                Err(e) if is_duplicate_key(&e) => return Err(ApiServiceError::EmailTaken),
+                Err(e) => return Err(e.into()),
+            };
+            if result.modified_count > 0 {
+                self.audit.record_update(ctx, "user", user_id, &before, &changes).await;
+            }
+            Ok((result, email_changed))
         } else {
             Err(ApiServiceError::InvalidObjectId) // User not found
         }
     }
 
 
-    /// Delete a course by its MongoDB `_id`.
-    pub async fn delete(&self, user_id: &str) -> Result<DeleteResult, ApiServiceError> {
+    /// Find a user by email address, unless it is deleted.
+    pub async fn get_by_email(&self, email: &str) -> Result<Option<User>, MongoError> {
+        self.collection.find_one(doc! { "email": email, "deleted_at": null }, None).await
+    }
+
+    /// Mark the email address of a user as verified.
+    pub async fn mark_email_verified(&self, user_id: ObjectId, ctx: &AuditContext) -> Result<UpdateResult, MongoError> {
+        let filter = doc! { "_id": user_id };
+        let update = doc! { "$set": { "email_verified": true, "updated_at": bson::DateTime::now() } };
+        let result = self.collection.update_one(filter, update, None).await?;
+
+        if result.modified_count > 0 {
+            let before = doc! { "email_verified": false };
+            let after = doc! { "email_verified": true };
+            self.audit.record(ctx, AuditAction::Update, "user", Some(user_id.to_hex()), Some(before), Some(after)).await;
+        }
+        Ok(result)
+    }
+
+    /// Replace merged watched records in the `watched_ids` of a user by the record they were merged into.
+    pub async fn replace_watched_ids(
+        &self,
+        user_id: ObjectId,
+        removed: &[ObjectId],
+        kept: ObjectId,
+        ctx: &AuditContext,
+    ) -> Result<(), MongoError> {
+        let filter = doc! { "_id": user_id };
+        let user = match self.collection.find_one(filter.clone(), None).await? {
+            Some(user) => user,
+            None => return Ok(()),
+        };
+        let before = user.watched_ids.unwrap_or_default();
+        let removed: Vec<String> = removed.iter().map(|id| id.to_hex()).collect();
+        if !before.iter().any(|id| removed.contains(id)) {
+            return Ok(());
+        }
+
+        let mut after: Vec<String> = before.iter().filter(|id| !removed.contains(id)).cloned().collect();
+        if !after.contains(&kept.to_hex()) {
+            after.push(kept.to_hex());
+        }
+        let update = doc! { "$set": { "watched_ids": &after, "updated_at": bson::DateTime::now() } };
+        self.collection.update_one(filter, update, None).await?;
+
+        let before = doc! { "watched_ids": before };
+        let after = doc! { "watched_ids": after };
+        self.audit.record_update(ctx, "user", &user_id.to_hex(), &before, &after).await;
+        Ok(())
+    }
+
+    /// Remove watched record ids from the `watched_ids` of every user listing them.
+    pub async fn pull_watched_ids(&self, watched_ids: &[ObjectId], ctx: &AuditContext) -> Result<u64, MongoError> {
+        let watched_ids: Vec<String> = watched_ids.iter().map(|id| id.to_hex()).collect();
+        let mut cursor = self
+            .collection
+            .find(doc! { "watched_ids": { "$in": &watched_ids } }, None)
+            .await?;
+        let mut users = Vec::new();
+        while let Some(result) = cursor.next().await {
+            users.push(result?);
+        }
+
+        for user in &users {
+            let before = user.watched_ids.clone().unwrap_or_default();
+            let after: Vec<String> = before.iter().filter(|id| !watched_ids.contains(id)).cloned().collect();
+            let update = doc! { "$set": { "watched_ids": &after, "updated_at": bson::DateTime::now() } };
+            self.collection.update_one(doc! { "_id": user._id }, update, None).await?;
+
+            if let Some(user_id) = user._id {
+                let before = doc! { "watched_ids": before };
+                let after = doc! { "watched_ids": after };
+                self.audit.record_update(ctx, "user", &user_id.to_hex(), &before, &after).await;
+            }
+        }
+
+        Ok(users.len() as u64)
+    }
+
+    /// Get the `watched_ids` of every user listing at least one.
+    pub async fn get_watched_ids(&self) -> Result<Vec<(ObjectId, Vec<String>)>, MongoError> {
+        let filter = doc! { "watched_ids.0": { "$exists": true } };
+        let mut cursor = self.collection.find(filter, None).await?;
+        let mut results = Vec::new();
+
+        while let Some(result) = cursor.next().await {
+            let user = result?;
+            if let (Some(user_id), Some(watched_ids)) = (user._id, user.watched_ids) {
+                results.push((user_id, watched_ids));
+            }
+        }
+
+        Ok(results)
+    }
+
+    /// Soft delete a user by its MongoDB `_id`. It stays in the collection with a `deleted_at`
+    /// tombstone until it is restored or purged.
+    pub async fn delete(&self, user_id: &str, ctx: &AuditContext) -> Result<UpdateResult, ApiServiceError> {
         let object_id = ObjectId::parse_str(user_id).map_err(|_| ApiServiceError::InvalidObjectId)?;
-        let filter = doc! { "_id": object_id };
+        let filter = doc! { "_id": object_id, "deleted_at": null };
+        let now = DateTime::now();
+        let update = doc! { "$set": { "deleted_at": now } };
+        let result = self.collection.update_one(filter, update, None).await?;
+
+        if result.modified_count > 0 {
+            let after = Some(doc! { "deleted_at": now });
+            self.audit.record(ctx, AuditAction::Delete, "user", Some(user_id.to_string()), None, after).await;
+        }
+        Ok(result)
+    }
+
+    /// Restore a soft deleted user by its MongoDB `_id`.
+    pub async fn restore(&self, user_id: &str, ctx: &AuditContext) -> Result<UpdateResult, ApiServiceError> {
+        let object_id = ObjectId::parse_str(user_id).map_err(|_| ApiServiceError::InvalidObjectId)?;
+        let filter = doc! { "_id": object_id, "deleted_at": { "$ne": null } };
+        let before = self.collection.find_one(filter.clone(), None).await?;
+        let update = doc! { "$unset": { "deleted_at": "" } };
+        let result = self.collection.update_one(filter, update, None).await?;
+
+        if let (Some(before), true) = (before, result.modified_count > 0) {
+            let before = Some(doc! { "deleted_at": before.deleted_at });
+            self.audit.record(ctx, AuditAction::Restore, "user", Some(user_id.to_string()), before, None).await;
+        }
+        Ok(result)
+    }
+
+    /// Get the soft deleted users, optionally only the ones deleted before `cutoff`.
+    pub async fn get_deleted(&self, cutoff: Option<DateTime>) -> Result<Vec<User>, MongoError> {
+        let filter = match cutoff {
+            Some(cutoff) => doc! { "deleted_at": { "$ne": null, "$lt": cutoff } },
+            None => doc! { "deleted_at": { "$ne": null } },
+        };
+        let mut cursor = self.collection.find(filter, None).await?;
+        let mut docs = Vec::new();
+
+        while let Some(result) = cursor.next().await {
+            match result {
+                Ok(user) => docs.push(user),
+                Err(err) => return Err(err),
+            }
+        }
+
+        Ok(docs)
+    }
+
+    /// Permanently remove a soft deleted user.
+    pub async fn purge(&self, user_id: ObjectId, ctx: &AuditContext) -> Result<DeleteResult, MongoError> {
+        let filter = doc! { "_id": user_id, "deleted_at": { "$ne": null } };
+        let before = self.collection.find_one(filter.clone(), None).await?;
         let result = self.collection.delete_one(filter, None).await?;
+
+        if let (Some(before), true) = (before, result.deleted_count > 0) {
+            let before = Some(user_to_document(&before));
+            self.audit.record(ctx, AuditAction::Purge, "user", Some(user_id.to_hex()), before, None).await;
+        }
         Ok(result)
     }
 }
\ No newline at end of file
@@ -0,0 +1,51 @@
+use crate::mailers::{EmailMessage, Mailer, MailerError};
+use std::{str::FromStr, sync::Arc};
+
+/// What an account with an unverified email address is not allowed to do.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VerificationPolicy {
+    RestrictLogin,          // Unverified users cannot log in
+    RestrictWatchedWrites,  // Unverified users can log in but cannot write watched records
+}
+
+impl FromStr for VerificationPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "login" => Ok(VerificationPolicy::RestrictLogin),
+            "watched" => Ok(VerificationPolicy::RestrictWatchedWrites),
+            other => Err(format!("Unknown email verification policy: {}", other)),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct ApiService {
+    mailer: Arc<dyn Mailer>,
+    policy: VerificationPolicy,
+    verification_url: String,
+}
+
+impl ApiService {
+    pub fn new(mailer: Arc<dyn Mailer>, policy: VerificationPolicy, verification_url: String) -> ApiService {
+        ApiService { mailer, policy, verification_url }
+    }
+
+    pub fn policy(&self) -> VerificationPolicy {
+        self.policy
+    }
+
+    /// Send the verification link containing `token` to `email`.
+    pub async fn send_verification_email(&self, email: &str, token: &str) -> Result<(), MailerError> {
+        let message = EmailMessage {
+            to: email.to_string(),
+            subject: "Verify your mylearning email address".to_string(),
+            body: format!(
+                "Welcome to mylearning!\n\nConfirm your email address by opening the link below:\n{}?token={}\n",
+                self.verification_url, token
+            ),
+        };
+        self.mailer.send(&message).await
+    }
+}
@@ -1,9 +1,13 @@
-use crate::models::watched_model::Watched;
+use crate::extractors::audit_extractor::AuditContext;
+use crate::models::{audit_model::AuditAction, user_model::User, watched_model::{MergedDuplicates, ProgressUpdate, Watched, WatchedStatus}};
+use crate::services::audit_service::ApiService as AuditService;
+use crate::utils::mongo::is_duplicate_key;
 use mongodb::{
-    bson::{doc, oid::ObjectId, Document},
+    bson::{doc, oid::ObjectId, DateTime, Document},
     error::Error as MongoError,
+    options::{FindOneAndUpdateOptions, IndexOptions, ReturnDocument},
     results::{DeleteResult, UpdateResult, InsertOneResult},
-    Collection,
+    Collection, Cursor, IndexModel,
 };
 use futures::stream::StreamExt;
 use thiserror::Error;
@@ -12,6 +16,10 @@ use thiserror::Error;
 pub enum ApiServiceError {
     #[error("Invalid ObjectId format")]
     InvalidObjectId,
+    #[error("Invalid progress: {0}")]
+    InvalidProgress(String),
+    #[error("An active record already exists for this user and course")]
+    ActiveRecordExists,
     #[error("Database error: {0}")]
     DatabaseError(#[from] MongoError),
 }
@@ -19,12 +27,13 @@ pub enum ApiServiceError {
 #[derive(Clone)]
 pub struct ApiService {
     collection: Collection<Watched>,
+    audit: AuditService,
 }
 
 // Helper function to convert a `Watched` into a MongoDB Document.
 fn watched_to_document(w: &Watched) -> Document {
     doc! {
-        "course_id"    : w.course_id.clone(),
+        "course_id"    : w.course_id,
         "finished_at"  : w.finished_at,
         "created_at"   : w.created_at,
         "updated_at"   : w.updated_at,
@@ -32,13 +41,74 @@ fn watched_to_document(w: &Watched) -> Document {
     }
 }
 
+// Helper function to extract the progress fields of a `Watched` for the audit log.
+fn progress_to_document(w: &Watched) -> Document {
+    doc! {
+        "percent_complete"      : w.percent_complete,
+        "last_position_minutes" : w.last_position_minutes,
+        "time_spent_minutes"    : w.time_spent_minutes,
+        "started_at"            : w.started_at,
+        "finished_at"           : w.finished_at,
+    }
+}
+
 impl ApiService {
-    pub fn new(collection: Collection<Watched>) -> ApiService {
-        ApiService { collection }
+    pub fn new(collection: Collection<Watched>, audit: AuditService) -> ApiService {
+        ApiService { collection, audit }
+    }
+
+    /// Create the unique index allowing a single active (not archived) record per user and course.
+    /// Fails while duplicates exist, see `merge_duplicates`.
+    pub async fn ensure_indexes(&self) -> Result<(), MongoError> {
+        let options = IndexOptions::builder()
+            .name("unique_active_user_course".to_string())
+            .unique(true)
+            .partial_filter_expression(doc! { "archived": false, "user_id": { "$type": "objectId" } })
+            .build();
+        let index = IndexModel::builder()
+            .keys(doc! { "user_id": 1, "course_id": 1 })
+            .options(options)
+            .build();
+        self.collection.create_index(index, None).await?;
+        Ok(())
+    }
+
+    /// Get the watched records with the given archive status.
+    pub async fn get_all(&self, status: WatchedStatus) -> Result<Vec<Watched>, MongoError> {
+        let mut cursor = self.stream_all(status).await?;
+        let mut docs = Vec::new();
+
+        while let Some(result) = cursor.next().await {
+            match result {
+                Ok(watched) => docs.push(watched),
+                Err(err) => return Err(err),
+            }
+        }
+
+        Ok(docs)
+    }
+
+    /// Open a cursor over the watched records with the given archive status, to stream them without buffering.
+    pub async fn stream_all(&self, status: WatchedStatus) -> Result<Cursor<Watched>, MongoError> {
+        let filter = match status {
+            WatchedStatus::Active => doc! { "archived": false },
+            WatchedStatus::Archived => doc! { "archived": true },
+            WatchedStatus::All => doc! {},
+        };
+        self.collection.find(filter, None).await
     }
 
-    pub async fn get_all(&self) -> Result<Vec<Watched>, MongoError> {
-        let mut cursor = self.collection.find(None, None).await?;
+    /// Get the watched records of a user, with the user's `user_id` or listed in their `watched_ids`.
+    pub async fn get_by_user(&self, user: &User) -> Result<Vec<Watched>, MongoError> {
+        let listed: Vec<ObjectId> = user
+            .watched_ids
+            .clone()
+            .unwrap_or_default()
+            .iter()
+            .filter_map(|id| ObjectId::parse_str(id).ok())
+            .collect();
+        let filter = doc! { "$or": [ { "user_id": user._id }, { "_id": { "$in": listed } } ] };
+        let mut cursor = self.collection.find(filter, None).await?;
         let mut docs = Vec::new();
 
         while let Some(result) = cursor.next().await {
@@ -51,6 +121,23 @@ impl ApiService {
         Ok(docs)
     }
 
+    /// Check whether a user has a watched record for a course, archived ones included.
+    pub async fn exists_for_user(&self, user: &User, course_id: ObjectId) -> Result<bool, MongoError> {
+        let listed: Vec<ObjectId> = user
+            .watched_ids
+            .clone()
+            .unwrap_or_default()
+            .iter()
+            .filter_map(|id| ObjectId::parse_str(id).ok())
+            .collect();
+        let filter = doc! {
+            "course_id": course_id,
+            "$or": [ { "user_id": user._id }, { "_id": { "$in": listed } } ],
+        };
+        let count = self.collection.count_documents(filter, None).await?;
+        Ok(count > 0)
+    }
+
     pub async fn get_by_id(&self, watched_id: &str) -> Result<Option<Watched>, ApiServiceError> {
         let object_id = ObjectId::parse_str(watched_id).map_err(|_| ApiServiceError::InvalidObjectId)?;
         let filter = doc! { "_id": object_id };
@@ -58,22 +145,313 @@ impl ApiService {
         Ok(result)
     }
 
-    pub async fn create(&self, w: &Watched) -> Result<InsertOneResult, MongoError> {
-        self.collection.insert_one(w, None).await
+    pub async fn create(&self, w: &Watched, ctx: &AuditContext) -> Result<InsertOneResult, MongoError> {
+        let result = self.collection.insert_one(w, None).await?;
+        let target_id = result.inserted_id.as_object_id().map(|id| id.to_hex());
+        self.audit.record(ctx, AuditAction::Create, "watched", target_id, None, Some(watched_to_document(w))).await;
+        Ok(result)
+    }
+
+    /// Start watching a course, or resume the active record the user already has for it.
+    /// Returns the record and whether it was created. `w.user_id` must be set.
+    /// Progress sent by the client is ignored, a new record always starts from zero.
+    pub async fn start_or_resume(&self, w: &Watched, ctx: &AuditContext) -> Result<(Watched, bool), MongoError> {
+        let filter = doc! { "user_id": w.user_id, "course_id": w.course_id, "archived": false };
+        if let Some(existing) = self.collection.find_one(filter.clone(), None).await? {
+            return Ok((existing, false));
+        }
+
+        let new = Watched {
+            _id: None,
+            user_id: w.user_id,
+            course_id: w.course_id,
+            finished_at: None,
+            created_at: w.created_at,
+            updated_at: w.updated_at,
+            archived: false,
+            percent_complete: 0.0,
+            last_position_minutes: 0.0,
+            time_spent_minutes: 0.0,
+            started_at: None,
+        };
+        match self.create(&new, ctx).await {
+            Ok(result) => {
+                let inserted = self.collection.find_one(doc! { "_id": result.inserted_id }, None).await?;
+                match inserted {
+                    Some(inserted) => Ok((inserted, true)),
+                    None => Err(MongoError::custom("Inserted watched record not found")),
+                }
+            }
+            // Another request created it concurrently
+            Err(err) if is_duplicate_key(&err) => match self.collection.find_one(filter, None).await? {
+                Some(existing) => Ok((existing, false)),
+                None => Err(err),
+            },
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Set the user of watched records that do not have one yet. Returns the number of records updated.
+    pub async fn assign_user(&self, user_id: ObjectId, watched_ids: &[ObjectId]) -> Result<u64, MongoError> {
+        let filter = doc! { "_id": { "$in": watched_ids }, "user_id": null };
+        let update = doc! { "$set": { "user_id": user_id } };
+        let result = self.collection.update_many(filter, update, None).await?;
+        Ok(result.modified_count)
     }
 
-    pub async fn update(&self, c: &Watched, watched_id: &str) -> Result<UpdateResult, ApiServiceError> {
+    /// Merge the active records sharing a user and course into the oldest one, keeping the earliest
+    /// start, the latest finish, the furthest progress and the total time spent. The others are deleted.
+    pub async fn merge_duplicates(&self, ctx: &AuditContext) -> Result<Vec<MergedDuplicates>, MongoError> {
+        let pipeline = vec![
+            doc! { "$match": { "archived": false, "user_id": { "$type": "objectId" } } },
+            doc! { "$sort": { "created_at": 1, "_id": 1 } },
+            doc! { "$group": {
+                "_id": { "user_id": "$user_id", "course_id": "$course_id" },
+                "ids": { "$push": "$_id" },
+                "count": { "$sum": 1 },
+                "started_at": { "$min": { "$ifNull": ["$started_at", "$created_at"] } },
+                "finished_at": { "$max": "$finished_at" },
+                "percent_complete": { "$max": "$percent_complete" },
+                "last_position_minutes": { "$max": "$last_position_minutes" },
+                "time_spent_minutes": { "$sum": "$time_spent_minutes" },
+            } },
+            doc! { "$match": { "count": { "$gt": 1 } } },
+        ];
+        let mut cursor = self.collection.aggregate(pipeline, None).await?;
+        let mut groups = Vec::new();
+        while let Some(group) = cursor.next().await {
+            groups.push(group?);
+        }
+
+        let mut merged = Vec::new();
+        for group in groups {
+            let key = group.get_document("_id").map_err(|e| MongoError::custom(e.to_string()))?;
+            let user_id = key.get_object_id("user_id").map_err(|e| MongoError::custom(e.to_string()))?;
+            let course_id = key.get_object_id("course_id").map_err(|e| MongoError::custom(e.to_string()))?;
+            let ids: Vec<ObjectId> = group
+                .get_array("ids")
+                .map_err(|e| MongoError::custom(e.to_string()))?
+                .iter()
+                .filter_map(|id| id.as_object_id())
+                .collect();
+            let (kept, removed) = match ids.split_first() {
+                Some((kept, removed)) => (*kept, removed.to_vec()),
+                None => continue,
+            };
+
+            let before = self.collection.find_one(doc! { "_id": kept }, None).await?;
+            let update = doc! { "$set": {
+                "started_at": group.get("started_at"),
+                "finished_at": group.get("finished_at"),
+                "percent_complete": group.get("percent_complete"),
+                "last_position_minutes": group.get("last_position_minutes"),
+                "time_spent_minutes": group.get("time_spent_minutes"),
+                "updated_at": DateTime::now(),
+            } };
+            self.collection.update_one(doc! { "_id": kept }, update, None).await?;
+            let after = self.collection.find_one(doc! { "_id": kept }, None).await?;
+            if let (Some(before), Some(after)) = (before, after) {
+                self.audit
+                    .record_update(ctx, "watched", &kept.to_hex(), &progress_to_document(&before), &progress_to_document(&after))
+                    .await;
+            }
+
+            self.collection.delete_many(doc! { "_id": { "$in": &removed } }, None).await?;
+            for id in &removed {
+                let merged_into = Some(doc! { "merged_into": kept });
+                self.audit.record(ctx, AuditAction::Delete, "watched", Some(id.to_hex()), merged_into, None).await;
+            }
+
+            merged.push(MergedDuplicates { user_id, course_id, kept, removed });
+        }
+
+        Ok(merged)
+    }
+
+    /// Update a watched record by its MongoDB `_id`. Its progress and archived state are left as they are.
+    pub async fn update(&self, c: &Watched, watched_id: &str, ctx: &AuditContext) -> Result<UpdateResult, ApiServiceError> {
         let object_id = ObjectId::parse_str(watched_id).map_err(|_| ApiServiceError::InvalidObjectId)?;
         let filter = doc! { "_id": object_id };
-        let update = doc! { "$set": watched_to_document(c) };
-        let result = self.collection.update_one(filter, update, None).await?;
+        let before = self.collection.find_one(filter.clone(), None).await?;
+        // Progress moves through `record_progress` and archiving through `set_archived`, both
+        // guarded, the generic update never writes them
+        let mut changes = watched_to_document(c);
+        changes.remove("finished_at");
+        changes.remove("archived");
+        let update = doc! { "$set": changes.clone() };
+        // Moving the record to another course can collide with an active record
+        let result = self.collection.update_one(filter, update, None).await.map_err(|e| {
+            if is_duplicate_key(&e) {
+                ApiServiceError::ActiveRecordExists
+            } else {
+                e.into()
+            }
+        })?;
+
+        if let (Some(before), true) = (before, result.modified_count > 0) {
+            self.audit.record_update(ctx, "watched", watched_id, &watched_to_document(&before), &changes).await;
+        }
         Ok(result)
     }
 
-    pub async fn delete(&self, watched_id: &str) -> Result<DeleteResult, ApiServiceError> {
+    pub async fn delete(&self, watched_id: &str, ctx: &AuditContext) -> Result<DeleteResult, ApiServiceError> {
         let object_id = ObjectId::parse_str(watched_id).map_err(|_| ApiServiceError::InvalidObjectId)?;
         let filter = doc! { "_id": object_id };
+        let before = self.collection.find_one(filter.clone(), None).await?;
         let result = self.collection.delete_one(filter, None).await?;
+
+        if let (Some(before), true) = (before, result.deleted_count > 0) {
+            let before = Some(watched_to_document(&before));
+            self.audit.record(ctx, AuditAction::Delete, "watched", Some(watched_id.to_string()), before, None).await;
+        }
         Ok(result)
     }
+
+    /// Apply a progress heartbeat. Percent complete and position only ever increase and time spent accumulates.
+    /// `finished_at` is set the first time progress reaches 100%.
+    /// `course_duration` is the course length in minutes, used to turn the position into a percentage.
+    pub async fn record_progress(
+        &self,
+        watched_id: &str,
+        progress: &ProgressUpdate,
+        course_duration: Option<i32>,
+        ctx: &AuditContext,
+    ) -> Result<Option<Watched>, ApiServiceError> {
+        let object_id = ObjectId::parse_str(watched_id).map_err(|_| ApiServiceError::InvalidObjectId)?;
+        let filter = doc! { "_id": object_id };
+
+        let invalid = |field: &str| ApiServiceError::InvalidProgress(format!("{} must be a non-negative number", field));
+        let position = progress.position_minutes.filter(|p| p.is_finite() && *p >= 0.0);
+        if progress.position_minutes.is_some() && position.is_none() {
+            return Err(invalid("position_minutes"));
+        }
+        let elapsed = progress.elapsed_minutes.unwrap_or(0.0);
+        if !elapsed.is_finite() || elapsed < 0.0 {
+            return Err(invalid("elapsed_minutes"));
+        }
+        if matches!(progress.percent_complete, Some(p) if !p.is_finite() || p < 0.0) {
+            return Err(invalid("percent_complete"));
+        }
+
+        // The percentage is the best of the explicit value and the one derived from the position
+        let duration = course_duration.filter(|d| *d > 0).map(f64::from);
+        let position = match (position, duration) {
+            (Some(position), Some(duration)) => Some(position.min(duration)),
+            (position, _) => position,
+        };
+        let from_position = match (position, duration) {
+            (Some(position), Some(duration)) => Some(position / duration * 100.0),
+            _ => None,
+        };
+        let percent = progress.percent_complete.into_iter().chain(from_position).fold(0.0_f64, f64::max).min(100.0);
+
+        // One pipeline update, so concurrent heartbeats never lose time or miss the completion
+        let now = DateTime::now();
+        let mut progress = doc! {
+            "percent_complete": { "$max": [{ "$ifNull": ["$percent_complete", 0.0] }, percent] },
+            "time_spent_minutes": { "$add": [{ "$ifNull": ["$time_spent_minutes", 0.0] }, elapsed] },
+            "started_at": { "$ifNull": ["$started_at", now] },
+            "updated_at": now,
+        };
+        if let Some(position) = position {
+            progress.insert("last_position_minutes", doc! { "$max": [{ "$ifNull": ["$last_position_minutes", 0.0] }, position] });
+        }
+        // Completion is only recorded once
+        let finished = doc! {
+            "finished_at": { "$cond": [
+                { "$and": [
+                    { "$eq": [{ "$ifNull": ["$finished_at", null] }, null] },
+                    { "$gte": ["$percent_complete", 100.0] },
+                ] },
+                now,
+                "$finished_at",
+            ] },
+        };
+        let update = vec![doc! { "$set": progress }, doc! { "$set": finished }];
+        let options = FindOneAndUpdateOptions::builder().return_document(ReturnDocument::Before).build();
+        let before = match self.collection.find_one_and_update(filter.clone(), update, options).await? {
+            Some(before) => before,
+            None => return Ok(None),
+        };
+
+        let after = self.collection.find_one(filter, None).await?;
+        if let Some(ref after) = after {
+            self.audit
+                .record_update(ctx, "watched", watched_id, &progress_to_document(&before), &progress_to_document(after))
+                .await;
+        }
+        Ok(after)
+    }
+
+    /// Get the `_id` of the watched records matching a filter.
+    pub async fn find_ids(&self, filter: Document) -> Result<Vec<ObjectId>, MongoError> {
+        let ids = self.collection.distinct("_id", filter, None).await?;
+        Ok(ids.iter().filter_map(|id| id.as_object_id()).collect())
+    }
+
+    /// Delete watched records by `_id`, with one audit entry per record.
+    pub async fn delete_many(&self, watched_ids: &[ObjectId], ctx: &AuditContext) -> Result<u64, MongoError> {
+        let result = self.collection.delete_many(doc! { "_id": { "$in": watched_ids } }, None).await?;
+        for id in watched_ids {
+            self.audit.record(ctx, AuditAction::Delete, "watched", Some(id.to_hex()), None, None).await;
+        }
+        Ok(result.deleted_count)
+    }
+
+    /// Archive or unarchive a watched record. Returns `None` when it does not exist.
+    /// Unarchiving fails with `ActiveRecordExists` when the learner restarted the course meanwhile.
+    pub async fn set_archived(&self, watched_id: &str, archived: bool, ctx: &AuditContext) -> Result<Option<Watched>, ApiServiceError> {
+        let object_id = ObjectId::parse_str(watched_id).map_err(|_| ApiServiceError::InvalidObjectId)?;
+        let filter = doc! { "_id": object_id, "archived": !archived };
+        let update = doc! { "$set": { "archived": archived, "updated_at": DateTime::now() } };
+        let options = FindOneAndUpdateOptions::builder().return_document(ReturnDocument::After).build();
+
+        match self.collection.find_one_and_update(filter, update, options).await {
+            Ok(Some(after)) => {
+                let before = doc! { "archived": !archived };
+                let after_doc = doc! { "archived": archived };
+                self.audit
+                    .record(ctx, AuditAction::Update, "watched", Some(watched_id.to_string()), Some(before), Some(after_doc))
+                    .await;
+                Ok(Some(after))
+            }
+            // Already in the requested state, or missing
+            Ok(None) => Ok(self.collection.find_one(doc! { "_id": object_id }, None).await?),
+            Err(e) if is_duplicate_key(&e) => Err(ApiServiceError::ActiveRecordExists),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Archive the active records finished before `cutoff`, optionally only the ones of one learner.
+    pub async fn archive_finished_before(
+        &self,
+        cutoff: DateTime,
+        user_id: Option<ObjectId>,
+        ctx: &AuditContext,
+    ) -> Result<u64, MongoError> {
+        let mut filter = doc! { "archived": false, "finished_at": { "$ne": null, "$lt": cutoff } };
+        if let Some(user_id) = user_id {
+            filter.insert("user_id", user_id);
+        }
+        let watched_ids = self.find_ids(filter).await?;
+        if watched_ids.is_empty() {
+            return Ok(0);
+        }
+        self.archive_many(&watched_ids, ctx).await
+    }
+
+    /// Archive watched records by `_id`, with one audit entry per record that changed.
+    pub async fn archive_many(&self, watched_ids: &[ObjectId], ctx: &AuditContext) -> Result<u64, MongoError> {
+        let filter = doc! { "_id": { "$in": watched_ids }, "archived": false };
+        let changed = self.find_ids(filter.clone()).await?;
+        let update = doc! { "$set": { "archived": true, "updated_at": DateTime::now() } };
+        let result = self.collection.update_many(filter, update, None).await?;
+
+        for id in &changed {
+            let before = doc! { "archived": false };
+            let after = doc! { "archived": true };
+            self.audit.record(ctx, AuditAction::Update, "watched", Some(id.to_hex()), Some(before), Some(after)).await;
+        }
+        Ok(result.modified_count)
+    }
 }
@@ -0,0 +1,148 @@
+//! Row encoders shared by the export endpoints. Every function returns the bytes of one line
+//! so exports can be streamed record by record.
+
+use crate::models::{course_model::Course, export_model::UserExport, watched_model::Watched};
+use bson::{oid::ObjectId, DateTime};
+use serde::Serialize;
+
+/// A record that can be written as a CSV row.
+pub trait CsvRecord {
+    fn csv_header() -> &'static [&'static str];
+    fn csv_row(&self) -> Vec<String>;
+}
+
+// Helper functions to format optional values as CSV cells.
+fn id_cell(id: Option<ObjectId>) -> String {
+    id.map(|id| id.to_hex()).unwrap_or_default()
+}
+
+fn date_cell(date: Option<DateTime>) -> String {
+    date.and_then(|date| date.try_to_rfc3339_string().ok()).unwrap_or_default()
+}
+
+// Free text cells starting like a formula are prefixed with a quote so spreadsheets show them
+// as text instead of evaluating them.
+fn text_cell(text: &str) -> String {
+    match text.chars().next() {
+        Some('=' | '+' | '-' | '@' | '\t' | '\r') => format!("'{}", text),
+        _ => text.to_string(),
+    }
+}
+
+/// Encode one CSV line, quoting cells as needed.
+pub fn csv_line<I, S>(cells: I) -> Result<Vec<u8>, csv::Error>
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<[u8]>,
+{
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    writer.write_record(cells)?;
+    writer.into_inner().map_err(|e| e.into_error().into())
+}
+
+/// Encode one NDJSON line.
+pub fn ndjson_line<T: Serialize>(value: &T) -> Result<Vec<u8>, serde_json::Error> {
+    let mut line = serde_json::to_vec(value)?;
+    line.push(b'\n');
+    Ok(line)
+}
+
+impl CsvRecord for Course {
+    fn csv_header() -> &'static [&'static str] {
+        &[
+            "_id", "title", "platform", "author", "duration", "language", "description", "url", "topics",
+            "rating_average", "rating_count", "created_at", "updated_at",
+        ]
+    }
+
+    fn csv_row(&self) -> Vec<String> {
+        vec![
+            id_cell(self._id),
+            text_cell(&self.title),
+            text_cell(&self.platform),
+            text_cell(&self.author),
+            self.duration.to_string(),
+            text_cell(&self.language),
+            text_cell(&self.description),
+            text_cell(&self.url),
+            text_cell(&self.topics.join(";")),
+            self.rating_average.to_string(),
+            self.rating_count.to_string(),
+            date_cell(Some(self.created_at)),
+            date_cell(Some(self.updated_at)),
+        ]
+    }
+}
+
+impl CsvRecord for UserExport {
+    fn csv_header() -> &'static [&'static str] {
+        &[
+            "_id", "name", "lastname", "major", "email", "watched_ids", "email_verified", "role", "created_at",
+            "updated_at",
+        ]
+    }
+
+    fn csv_row(&self) -> Vec<String> {
+        let role = serde_json::to_value(self.role)
+            .ok()
+            .and_then(|role| role.as_str().map(str::to_string))
+            .unwrap_or_default();
+        vec![
+            id_cell(self._id),
+            text_cell(&self.name),
+            text_cell(&self.lastname),
+            text_cell(&self.major),
+            text_cell(&self.email),
+            self.watched_ids.join(";"),
+            self.email_verified.to_string(),
+            role,
+            date_cell(Some(self.created_at)),
+            date_cell(Some(self.updated_at)),
+        ]
+    }
+}
+
+impl CsvRecord for Watched {
+    fn csv_header() -> &'static [&'static str] {
+        &[
+            "_id", "user_id", "course_id", "archived", "percent_complete", "last_position_minutes",
+            "time_spent_minutes", "started_at", "finished_at", "created_at", "updated_at",
+        ]
+    }
+
+    fn csv_row(&self) -> Vec<String> {
+        vec![
+            id_cell(self._id),
+            id_cell(self.user_id),
+            self.course_id.to_hex(),
+            self.archived.to_string(),
+            self.percent_complete.to_string(),
+            self.last_position_minutes.to_string(),
+            self.time_spent_minutes.to_string(),
+            date_cell(self.started_at),
+            date_cell(self.finished_at),
+            date_cell(Some(self.created_at)),
+            date_cell(Some(self.updated_at)),
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formula_cells_are_quoted() {
+        assert_eq!(text_cell("=HYPERLINK(\"http://evil\")"), "'=HYPERLINK(\"http://evil\")");
+        assert_eq!(text_cell("+1"), "'+1");
+        assert_eq!(text_cell("-2"), "'-2");
+        assert_eq!(text_cell("@SUM(A1)"), "'@SUM(A1)");
+        assert_eq!(text_cell("Rust basics"), "Rust basics");
+        assert_eq!(text_cell(""), "");
+    }
+
+    #[test]
+    fn ndjson_lines_end_with_a_newline() {
+        assert_eq!(ndjson_line(&vec![1, 2]).unwrap(), b"[1,2]\n");
+    }
+}
@@ -0,0 +1,383 @@
+//! Course metadata found in an HTML page: JSON-LD first, then OpenGraph and the other meta
+//! tags, then the plain `<title>` and `lang` attribute. There is no full HTML parser, only
+//! the few tags of interest are scanned.
+
+use crate::models::enrichment_model::CourseMetadata;
+use serde_json::Value;
+use std::collections::HashMap;
+
+// JSON-LD types describing the course itself, the best first
+const LD_TYPES: [&str; 6] = ["Course", "LearningResource", "VideoObject", "CreativeWork", "Article", "WebPage"];
+
+// Helper function to decode the HTML entities of a text, unknown ones are kept as written.
+fn decode_entities(text: &str) -> String {
+    let mut decoded = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(pos) = rest.find('&') {
+        decoded.push_str(&rest[..pos]);
+        rest = &rest[pos..];
+        let entity = rest.find(';').filter(|end| *end <= 10).and_then(|end| {
+            let c = match &rest[1..end] {
+                "amp" => '&',
+                "lt" => '<',
+                "gt" => '>',
+                "quot" => '"',
+                "apos" => '\'',
+                "nbsp" => ' ',
+                other => {
+                    let number = other.strip_prefix('#')?;
+                    let code = match number.strip_prefix(['x', 'X']) {
+                        Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+                        None => number.parse().ok()?,
+                    };
+                    char::from_u32(code)?
+                }
+            };
+            Some((c, end + 1))
+        });
+        match entity {
+            Some((c, len)) => {
+                decoded.push(c);
+                rest = &rest[len..];
+            }
+            None => {
+                decoded.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    decoded.push_str(rest);
+    decoded
+}
+
+// Helper function to decode a text and collapse its whitespace, `None` when nothing is left.
+fn clean(text: &str) -> Option<String> {
+    let text = decode_entities(text).split_whitespace().collect::<Vec<_>>().join(" ");
+    (!text.is_empty()).then_some(text)
+}
+
+// Helper function to find the `>` closing the tag whose attributes start at `start`.
+fn tag_end(html: &str, start: usize) -> Option<usize> {
+    let mut quote = None;
+    for (i, c) in html[start..].char_indices() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), _) => {}
+            (None, '"' | '\'') => quote = Some(c),
+            (None, '>') => return Some(start + i),
+            _ => {}
+        }
+    }
+    None
+}
+
+// Helper function to parse the attributes of a tag, names are lowercased and values decoded.
+fn attributes(tag: &str) -> HashMap<String, String> {
+    let mut attrs = HashMap::new();
+    let mut chars = tag.chars().peekable();
+    loop {
+        while chars.next_if(|c| c.is_whitespace() || *c == '/').is_some() {}
+        let name: String = std::iter::from_fn(|| chars.next_if(|c| !c.is_whitespace() && !matches!(c, '=' | '/' | '>'))).collect();
+        if name.is_empty() {
+            break;
+        }
+        while chars.next_if(|c| c.is_whitespace()).is_some() {}
+        let mut value = String::new();
+        if chars.next_if_eq(&'=').is_some() {
+            while chars.next_if(|c| c.is_whitespace()).is_some() {}
+            value = match chars.next_if(|c| *c == '"' || *c == '\'') {
+                Some(quote) => chars.by_ref().take_while(|c| *c != quote).collect(),
+                None => std::iter::from_fn(|| chars.next_if(|c| !c.is_whitespace())).collect(),
+            };
+        }
+        attrs.entry(name.to_ascii_lowercase()).or_insert_with(|| decode_entities(&value));
+    }
+    attrs
+}
+
+// Helper function to find every `<name ...>` tag, with its attributes and the offset right after it.
+// `lower` is the page in ASCII lowercase, so offsets are the same in both.
+fn tags(html: &str, lower: &str, name: &str) -> Vec<(HashMap<String, String>, usize)> {
+    let open = format!("<{}", name);
+    let mut found = vec![];
+    let mut from = 0;
+    while let Some(pos) = lower[from..].find(&open) {
+        let start = from + pos + open.len();
+        from = start;
+        // `<meta` but not `<metadata`
+        if !lower[start..].starts_with(|c: char| c.is_whitespace() || c == '>' || c == '/') {
+            continue;
+        }
+        let Some(end) = tag_end(html, start) else { break };
+        found.push((attributes(&html[start..end]), end + 1));
+        from = end + 1;
+    }
+    found
+}
+
+// Helper function to get the raw text between `from` and the closing tag.
+fn text_until<'a>(html: &'a str, lower: &str, from: usize, closing: &str) -> Option<&'a str> {
+    lower[from..].find(closing).map(|len| &html[from..from + len])
+}
+
+// Helper function to convert seconds into whole minutes, rounded up.
+fn to_minutes(seconds: f64) -> Option<i32> {
+    (seconds.is_finite() && seconds > 0.0).then(|| (seconds / 60.0).ceil().min(i32::MAX as f64) as i32)
+}
+
+// Helper function to convert an ISO 8601 duration, e.g. `PT1H30M`, into minutes. Years and
+// months have no fixed length and are rejected.
+fn parse_iso_duration(text: &str) -> Option<i32> {
+    let rest = text.trim().strip_prefix(['P', 'p'])?;
+    let mut seconds = 0.0;
+    let mut in_time = false;
+    let mut number = String::new();
+    let mut found = false;
+    for c in rest.chars() {
+        match c.to_ascii_uppercase() {
+            'T' => in_time = true,
+            '0'..='9' | '.' => number.push(c),
+            ',' => number.push('.'),
+            unit => {
+                let value: f64 = number.parse().ok()?;
+                number.clear();
+                seconds += value
+                    * match (unit, in_time) {
+                        ('W', false) => 604_800.0,
+                        ('D', false) => 86_400.0,
+                        ('H', true) => 3_600.0,
+                        ('M', true) => 60.0,
+                        ('S', true) => 1.0,
+                        _ => return None,
+                    };
+                found = true;
+            }
+        }
+    }
+    if !found || !number.is_empty() {
+        return None;
+    }
+    to_minutes(seconds)
+}
+
+// Helper function to reduce a language tag such as `en-US` or `en_US` to its primary subtag.
+// Names such as `English` are kept as they are.
+fn language(text: &str) -> Option<String> {
+    let text = clean(text.split(',').next()?)?;
+    let primary = text.split(['-', '_']).next()?;
+    if (2..=3).contains(&primary.len()) && primary.chars().all(|c| c.is_ascii_alphabetic()) {
+        Some(primary.to_ascii_lowercase())
+    } else {
+        Some(text)
+    }
+}
+
+// Helper function to drop authors given as a profile link, only names are useful.
+fn author_name(text: String) -> Option<String> {
+    (!text.starts_with("http://") && !text.starts_with("https://")).then_some(text)
+}
+
+// Helper function to collect the JSON-LD nodes of a block, including the `@graph` ones.
+fn ld_nodes<'a>(value: &'a Value, nodes: &mut Vec<&'a Value>) {
+    match value {
+        Value::Array(items) => items.iter().for_each(|item| ld_nodes(item, nodes)),
+        Value::Object(map) => {
+            nodes.push(value);
+            if let Some(graph) = map.get("@graph") {
+                ld_nodes(graph, nodes);
+            }
+        }
+        _ => {}
+    }
+}
+
+// Position of the node type in `LD_TYPES`, types may be full schema.org URLs
+fn ld_rank(node: &Value) -> Option<usize> {
+    let types: Vec<&str> = match node.get("@type") {
+        Some(Value::String(t)) => vec![t],
+        Some(Value::Array(types)) => types.iter().filter_map(Value::as_str).collect(),
+        _ => vec![],
+    };
+    types
+        .iter()
+        .filter_map(|t| LD_TYPES.iter().position(|known| t.rsplit('/').next() == Some(known)))
+        .min()
+}
+
+// Helper function to get a text from a JSON-LD value: a string, the name of an object or the first of a list.
+fn ld_text(value: &Value) -> Option<String> {
+    match value {
+        Value::String(text) => clean(text),
+        Value::Object(map) => map.get("name").and_then(ld_text),
+        Value::Array(items) => items.iter().find_map(ld_text),
+        _ => None,
+    }
+}
+
+fn ld_language(value: &Value) -> Option<String> {
+    match value {
+        Value::String(text) => language(text),
+        Value::Object(map) => map.get("alternateName").or_else(|| map.get("name")).and_then(ld_language),
+        Value::Array(items) => items.iter().find_map(ld_language),
+        _ => None,
+    }
+}
+
+fn ld_duration(node: &Value) -> Option<i32> {
+    let direct = ["timeRequired", "duration"]
+        .iter()
+        .find_map(|key| node.get(*key).and_then(Value::as_str).and_then(parse_iso_duration));
+    // Courses give their workload per instance
+    direct.or_else(|| match node.get("hasCourseInstance")? {
+        Value::Array(instances) => instances.iter().find_map(ld_duration_of_instance),
+        instance => ld_duration_of_instance(instance),
+    })
+}
+
+fn ld_duration_of_instance(instance: &Value) -> Option<i32> {
+    instance.get("courseWorkload").and_then(Value::as_str).and_then(parse_iso_duration)
+}
+
+/// Extract the course metadata of a page. `url` is where the page was fetched from.
+pub fn extract(url: &str, html: &str) -> CourseMetadata {
+    let lower = html.to_ascii_lowercase();
+    let mut metadata = CourseMetadata { url: url.to_string(), ..Default::default() };
+
+    // JSON-LD, from the node with the most specific type
+    let blocks: Vec<Value> = tags(html, &lower, "script")
+        .into_iter()
+        .filter(|(attrs, _)| attrs.get("type").is_some_and(|t| t.trim().eq_ignore_ascii_case("application/ld+json")))
+        .filter_map(|(_, from)| text_until(html, &lower, from, "</script"))
+        .filter_map(|json| serde_json::from_str(json.trim()).ok())
+        .collect();
+    let mut nodes = vec![];
+    for block in &blocks {
+        ld_nodes(block, &mut nodes);
+    }
+    if let Some(node) = nodes.into_iter().filter(|node| ld_rank(node).is_some()).min_by_key(|node| ld_rank(node)) {
+        metadata.title = node.get("name").or_else(|| node.get("headline")).and_then(ld_text);
+        metadata.description = node.get("description").and_then(ld_text);
+        metadata.author = ["author", "creator"]
+            .iter()
+            .find_map(|key| node.get(*key).and_then(ld_text).and_then(author_name));
+        metadata.duration = ld_duration(node);
+        metadata.language = node.get("inLanguage").and_then(ld_language);
+    }
+
+    // OpenGraph and the other meta tags, in page order
+    let mut meta: HashMap<String, Vec<String>> = HashMap::new();
+    for (attrs, _) in tags(html, &lower, "meta") {
+        let key = ["property", "name", "itemprop", "http-equiv"].iter().find_map(|attr| attrs.get(*attr));
+        if let (Some(key), Some(content)) = (key, attrs.get("content")) {
+            meta.entry(key.to_ascii_lowercase()).or_default().push(content.clone());
+        }
+    }
+    let meta_values = |keys: &[&str]| -> Vec<String> {
+        keys.iter().flat_map(|key| meta.get(*key).into_iter().flatten()).filter_map(|value| clean(value)).collect()
+    };
+    let meta_value = |keys: &[&str]| meta_values(keys).into_iter().next();
+
+    metadata.title = metadata.title.or_else(|| meta_value(&["og:title", "twitter:title"]));
+    metadata.description = metadata
+        .description
+        .or_else(|| meta_value(&["og:description", "description", "twitter:description"]));
+    metadata.author = metadata
+        .author
+        .or_else(|| meta_values(&["author", "article:author"]).into_iter().find_map(author_name));
+    metadata.duration = metadata.duration.or_else(|| {
+        meta_value(&["video:duration", "og:video:duration"])
+            .and_then(|seconds| seconds.parse().ok())
+            .and_then(to_minutes)
+            .or_else(|| meta_value(&["duration"]).and_then(|duration| parse_iso_duration(&duration)))
+    });
+    metadata.language = metadata.language.or_else(|| {
+        meta_values(&["og:locale", "content-language", "language"]).iter().find_map(|value| language(value))
+    });
+
+    // Plain HTML
+    if metadata.title.is_none() {
+        metadata.title = tags(html, &lower, "title")
+            .first()
+            .and_then(|(_, from)| text_until(html, &lower, *from, "</title"))
+            .and_then(clean);
+    }
+    if metadata.language.is_none() {
+        metadata.language = tags(html, &lower, "html").first().and_then(|(attrs, _)| attrs.get("lang")).and_then(|lang| language(lang));
+    }
+
+    metadata
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn entities_are_decoded() {
+        assert_eq!(decode_entities("Rust &amp; WebAssembly &lt;3"), "Rust & WebAssembly <3");
+        assert_eq!(decode_entities("&quot;Caf&#233;&quot; &#x2014; &apos;x&apos;"), "\"Café\" — 'x'");
+        assert_eq!(decode_entities("Q&A &unknown; &#xZZ; a & b"), "Q&A &unknown; &#xZZ; a & b");
+        assert_eq!(decode_entities("trailing &amp"), "trailing &amp");
+    }
+
+    #[test]
+    fn iso_durations_are_converted_to_minutes() {
+        assert_eq!(parse_iso_duration("PT1H30M"), Some(90));
+        assert_eq!(parse_iso_duration("pt45m"), Some(45));
+        assert_eq!(parse_iso_duration("PT90S"), Some(2));
+        assert_eq!(parse_iso_duration("P1DT2H"), Some(1560));
+        assert_eq!(parse_iso_duration("P1W"), Some(10080));
+        assert_eq!(parse_iso_duration("PT1,5H"), Some(90));
+        assert_eq!(parse_iso_duration("P1M"), None); // Months have no fixed length
+        assert_eq!(parse_iso_duration("PT0M"), None);
+        assert_eq!(parse_iso_duration("PT"), None);
+        assert_eq!(parse_iso_duration("PT5"), None);
+        assert_eq!(parse_iso_duration("1H"), None);
+    }
+
+    #[test]
+    fn json_ld_comes_first() {
+        let html = r#"<html lang="fr"><head>
+            <title>Plain title</title>
+            <meta property="og:title" content="OpenGraph title">
+            <script type="application/ld+json">
+                { "@context": "https://schema.org", "@graph": [
+                    { "@type": "WebPage", "name": "The page" },
+                    { "@type": "Course", "name": "Rust &amp; Async", "description": "Learn  async\n Rust",
+                      "author": { "@type": "Person", "name": "Ferris" }, "inLanguage": "en-US",
+                      "hasCourseInstance": { "courseWorkload": "PT2H" } }
+                ] }
+            </script>
+        </head></html>"#;
+        let metadata = extract("https://example.com/rust", html);
+        assert_eq!(metadata.url, "https://example.com/rust");
+        assert_eq!(metadata.title.as_deref(), Some("Rust & Async"));
+        assert_eq!(metadata.description.as_deref(), Some("Learn async Rust"));
+        assert_eq!(metadata.author.as_deref(), Some("Ferris"));
+        assert_eq!(metadata.duration, Some(120));
+        assert_eq!(metadata.language.as_deref(), Some("en"));
+    }
+
+    #[test]
+    fn meta_tags_and_plain_html_fill_the_rest() {
+        let html = r#"<HTML LANG="de-DE"><head>
+            <metadata content="ignored">
+            <META property='og:title' content='Kurs &quot;Rust&quot;'>
+            <meta name="description" content="Beschreibung">
+            <meta name="author" content="https://example.com/profile">
+            <meta name="author" content="Jane Doe">
+            <meta property="video:duration" content="3601" />
+        </head></HTML>"#;
+        let metadata = extract("https://example.com/kurs", html);
+        assert_eq!(metadata.title.as_deref(), Some("Kurs \"Rust\""));
+        assert_eq!(metadata.description.as_deref(), Some("Beschreibung"));
+        assert_eq!(metadata.author.as_deref(), Some("Jane Doe"));
+        assert_eq!(metadata.duration, Some(61));
+        assert_eq!(metadata.language.as_deref(), Some("de"));
+
+        let plain = extract("https://example.com", "<html><title> Just a\n title </title></html>");
+        assert_eq!(plain.title.as_deref(), Some("Just a title"));
+        assert_eq!(plain.description, None);
+        assert_eq!(plain.language, None);
+    }
+}
@@ -0,0 +1,8 @@
+pub mod export;
+pub mod metadata;
+pub mod mongo;
+pub mod slug;
+pub mod stream;
+#[cfg(test)]
+pub mod test_server;
+pub mod token;
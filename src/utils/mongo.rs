@@ -0,0 +1,21 @@
+use mongodb::error::{Error as MongoError, ErrorKind, WriteFailure};
+
+/// Whether a write failed on a unique index.
+pub fn is_duplicate_key(err: &MongoError) -> bool {
+    matches!(
+        err.kind.as_ref(),
+        ErrorKind::Write(WriteFailure::WriteError(write_error)) if write_error.code == 11000
+    )
+}
+
+/// Escape a value so a `$regex` matches it literally.
+pub fn escape_regex(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        if "\\^$.|?*+()[]{}".contains(c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
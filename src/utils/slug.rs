@@ -0,0 +1,8 @@
+/// Turn any spelling into a slug: lowercase words joined by dashes, e.g. `Rust Programming` gives `rust-programming`.
+pub fn slugify(text: &str) -> String {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect::<Vec<_>>()
+        .join("-")
+}
@@ -0,0 +1,91 @@
+//! Streamed list responses. Documents are serialized one by one as the MongoDB cursor yields
+//! them and actix only polls for the next one once the previous chunk has been written, so a
+//! slow client holds back the cursor instead of filling memory.
+
+use crate::utils::export::ndjson_line;
+use actix_web::{http::header, web::Bytes, HttpRequest, HttpResponse};
+use futures::stream::{self, Stream, StreamExt};
+use serde::Serialize;
+use std::error::Error;
+
+/// How a list endpoint sends its results, negotiated from the `Accept` header.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ListFormat {
+    Buffered,  // Default, the whole list is read before the response is sent
+    JsonArray, // `Accept: application/json; stream=true`, the same JSON array streamed element by element
+    Ndjson,    // `Accept: application/x-ndjson`, one document per line
+}
+
+/// Pick the list format requested by the client, the first supported media type wins.
+pub fn list_format(req: &HttpRequest) -> ListFormat {
+    let Some(accept) = req.headers().get(header::ACCEPT).and_then(|accept| accept.to_str().ok()) else {
+        return ListFormat::Buffered;
+    };
+
+    for media_type in accept.split(',') {
+        let mut parts = media_type.split(';').map(str::trim);
+        match parts.next().unwrap_or_default().to_ascii_lowercase().as_str() {
+            "application/x-ndjson" | "application/ndjson" => return ListFormat::Ndjson,
+            "application/json" if parts.any(|param| param.eq_ignore_ascii_case("stream=true")) => {
+                return ListFormat::JsonArray
+            }
+            _ => {}
+        }
+    }
+    ListFormat::Buffered
+}
+
+// Helper function to encode each item of a stream, logging the errors. The body is cut short on
+// the first one.
+fn encode_items<T, S, E>(
+    items: S,
+    encode: fn(&T) -> Result<Vec<u8>, serde_json::Error>,
+) -> impl Stream<Item = Result<Vec<u8>, Box<dyn Error>>>
+where
+    S: Stream<Item = Result<T, E>>,
+    E: Error + 'static,
+{
+    items.map(move |item| {
+        let item = item.map_err(|e| {
+            eprintln!("Error while streaming list: {:?}", e);
+            Box::new(e) as Box<dyn Error>
+        })?;
+        encode(&item).map_err(|e| {
+            eprintln!("Error while serializing list item: {:?}", e);
+            Box::new(e) as Box<dyn Error>
+        })
+    })
+}
+
+/// Send a stream of documents as a streamed JSON array or as NDJSON. A `Buffered` format is
+/// sent as a JSON array too. Errors after the response has started can only be logged, the
+/// body is then cut short so the client sees an incomplete document.
+pub fn stream_list<T, S, E>(format: ListFormat, items: S) -> HttpResponse
+where
+    T: Serialize + 'static,
+    S: Stream<Item = Result<T, E>> + 'static,
+    E: Error + 'static,
+{
+    match format {
+        ListFormat::Ndjson => {
+            let body = encode_items(items, ndjson_line).map(|line| line.map(Bytes::from));
+            HttpResponse::Ok().content_type("application/x-ndjson").streaming(body)
+        }
+        ListFormat::JsonArray | ListFormat::Buffered => {
+            let elements = encode_items(items, serde_json::to_vec).enumerate().map(|(index, element)| {
+                element.map(|element| {
+                    let mut chunk = Vec::with_capacity(element.len() + 1);
+                    if index > 0 {
+                        chunk.push(b',');
+                    }
+                    chunk.extend(element);
+                    Bytes::from(chunk)
+                })
+            });
+            let body = stream::once(async { Ok(Bytes::from_static(b"[")) })
+                .chain(elements)
+                .chain(stream::once(async { Ok(Bytes::from_static(b"]")) }));
+            HttpResponse::Ok().content_type("application/json").streaming(body)
+        }
+    }
+}
@@ -0,0 +1,18 @@
+//! Local HTTP servers for tests, e.g. the mock identity provider or fixture pages.
+
+use actix_web::{rt::spawn, web, App, HttpServer};
+use std::net::SocketAddr;
+
+/// Serve the routes added by `configure` on a free port of 127.0.0.1 until the test ends.
+pub fn serve<F>(configure: F) -> SocketAddr
+where
+    F: Fn(&mut web::ServiceConfig) + Clone + Send + 'static,
+{
+    let server = HttpServer::new(move || App::new().configure(configure.clone()))
+        .workers(1)
+        .bind(("127.0.0.1", 0))
+        .expect("Failed to bind the test server");
+    let addr = server.addrs()[0];
+    spawn(server.run());
+    addr
+}
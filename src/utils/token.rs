@@ -0,0 +1,16 @@
+use rand::{distributions::Alphanumeric, Rng};
+use sha2::{Digest, Sha256};
+
+/// Generate a random URL-safe token of the given length.
+pub fn generate_token(length: usize) -> String {
+    rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(length)
+        .map(char::from)
+        .collect()
+}
+
+/// Hash a token with SHA-256 so only the digest is ever stored.
+pub fn hash_token(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
+}